@@ -0,0 +1,440 @@
+use crate::config::{AlertAction, AlertConfig};
+use crate::metrics::{MetricKind, MetricReading};
+use crate::sources::resolve_metric;
+use crate::textfile::split_label_string;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Eq,
+    Ne,
+}
+
+impl Comparison {
+    /// Longest operators first so `>=` isn't read as `>`.
+    const ALL: [(&'static str, Comparison); 6] = [
+        (">=", Comparison::Ge),
+        ("<=", Comparison::Le),
+        ("==", Comparison::Eq),
+        ("!=", Comparison::Ne),
+        (">", Comparison::Gt),
+        ("<", Comparison::Lt),
+    ];
+
+    pub fn holds(self, value: f64, threshold: f64) -> bool {
+        match self {
+            Comparison::Gt => value > threshold,
+            Comparison::Ge => value >= threshold,
+            Comparison::Lt => value < threshold,
+            Comparison::Le => value <= threshold,
+            Comparison::Eq => value == threshold,
+            Comparison::Ne => value != threshold,
+        }
+    }
+
+    /// The threshold a firing alert has to get back past to resolve.
+    fn relaxed(self, threshold: f64, hysteresis: f64) -> f64 {
+        match self {
+            Comparison::Gt | Comparison::Ge => threshold - hysteresis,
+            Comparison::Lt | Comparison::Le => threshold + hysteresis,
+            Comparison::Eq | Comparison::Ne => threshold,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Eq(String, String),
+    Ne(String, String),
+    Re(String, Regex),
+    NotRe(String, Regex),
+}
+
+impl Matcher {
+    /// A label the reading doesn't have counts as empty, as in PromQL.
+    fn matches(&self, reading: &MetricReading) -> bool {
+        let value = |key: &str| label_value(reading, key).unwrap_or_default();
+        match self {
+            Matcher::Eq(key, want) => value(key) == *want,
+            Matcher::Ne(key, want) => value(key) != *want,
+            Matcher::Re(key, re) => re.is_match(&value(key)),
+            Matcher::NotRe(key, re) => !re.is_match(&value(key)),
+        }
+    }
+}
+
+/// `label` and the family's own instance label (`sensor`, `mount`...) both
+/// name a reading's label; textfile readings carry their own label sets.
+fn label_value(reading: &MetricReading, key: &str) -> Option<String> {
+    let label = reading.label.as_deref()?;
+    if reading.kind == MetricKind::Textfile {
+        return split_label_string(label)
+            .ok()?
+            .into_iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v);
+    }
+    (key == "label" || key == reading.kind.instance_label()).then(|| label.to_string())
+}
+
+/// What a rule watches: a family by name (`temps`, `disk`...) means its
+/// headline reading, anything else a reading by exact metric name.
+#[derive(Debug, Clone)]
+enum Target {
+    Family(MetricKind, Option<&'static str>),
+    Metric(String),
+}
+
+impl Target {
+    fn matches(&self, reading: &MetricReading) -> bool {
+        match self {
+            Target::Family(kind, Some(metric)) => {
+                reading.kind == *kind && reading.metric == *metric
+            }
+            Target::Family(kind, None) => reading.kind == *kind,
+            Target::Metric(name) => reading.metric == *name,
+        }
+    }
+}
+
+/// A parsed rule: `<metric>[{matchers}] <op> <threshold> [for <duration>]`.
+#[derive(Debug, Clone)]
+pub struct Condition {
+    target: Target,
+    matchers: Vec<Matcher>,
+    pub comparison: Comparison,
+    pub threshold: f64,
+    /// How long the condition must hold before the alert fires.
+    pub hold: Duration,
+}
+
+impl Condition {
+    pub fn matches(&self, reading: &MetricReading) -> bool {
+        self.target.matches(reading) && self.matchers.iter().all(|m| m.matches(reading))
+    }
+}
+
+impl FromStr for Condition {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        let input = input.trim();
+        let name_end = input
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == ':'))
+            .unwrap_or(input.len());
+        let name = &input[..name_end];
+        anyhow::ensure!(!name.is_empty(), "rule must start with a metric name");
+        let target = match name.parse::<MetricKind>() {
+            Ok(kind) => Target::Family(
+                kind,
+                resolve_metric(kind.as_str()).and_then(|r| r.live.map(|(_, metric)| metric)),
+            ),
+            Err(_) => Target::Metric(name.to_string()),
+        };
+
+        let mut rest = input[name_end..].trim_start();
+        let mut matchers = Vec::new();
+        if let Some(after) = rest.strip_prefix('{') {
+            let (parsed, remaining) = parse_matchers(after)?;
+            matchers = parsed;
+            rest = remaining.trim_start();
+        }
+
+        let (comparison, remaining) = Comparison::ALL
+            .iter()
+            .find_map(|(op, cmp)| rest.strip_prefix(op).map(|r| (*cmp, r)))
+            .with_context(|| format!("expected a comparison after {name:?}"))?;
+        rest = remaining.trim_start();
+        let number_end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let threshold = rest[..number_end]
+            .parse()
+            .with_context(|| format!("invalid threshold {:?}", &rest[..number_end]))?;
+        rest = rest[number_end..].trim();
+
+        let hold = if rest.is_empty() {
+            Duration::ZERO
+        } else {
+            let duration = rest
+                .strip_prefix("for")
+                .filter(|d| d.starts_with(char::is_whitespace))
+                .with_context(|| format!("unexpected {rest:?}; expected `for <duration>`"))?;
+            humantime::parse_duration(duration.trim())
+                .with_context(|| format!("invalid duration {:?}", duration.trim()))?
+        };
+
+        Ok(Self {
+            target,
+            matchers,
+            comparison,
+            threshold,
+            hold,
+        })
+    }
+}
+
+fn parse_matchers(input: &str) -> Result<(Vec<Matcher>, &str)> {
+    let mut matchers = Vec::new();
+    let mut rest = input.trim_start();
+    loop {
+        if let Some(after) = rest.strip_prefix('}') {
+            return Ok((matchers, after));
+        }
+        let key_end = rest
+            .find(['=', '!', '~'])
+            .context("label matcher without operator")?;
+        let key = rest[..key_end].trim().to_string();
+        rest = &rest[key_end..];
+        let op = ["=~", "!~", "!=", "="]
+            .into_iter()
+            .find(|op| rest.starts_with(op))
+            .with_context(|| format!("invalid matcher operator for {key:?}"))?;
+        rest = rest[op.len()..]
+            .trim_start()
+            .strip_prefix('"')
+            .context("matcher value must be quoted")?;
+
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        let end = loop {
+            match chars.next() {
+                Some((i, '"')) => break i,
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, c)) => value.push(c),
+                    None => anyhow::bail!("unterminated escape"),
+                },
+                Some((_, c)) => value.push(c),
+                None => anyhow::bail!("unterminated matcher value"),
+            }
+        };
+        let regex = || {
+            Regex::new(&format!("^(?:{value})$"))
+                .with_context(|| format!("invalid regex {value:?}"))
+        };
+        matchers.push(match op {
+            "=~" => Matcher::Re(key, regex()?),
+            "!~" => Matcher::NotRe(key, regex()?),
+            "!=" => Matcher::Ne(key, value),
+            _ => Matcher::Eq(key, value),
+        });
+        rest = rest[end + 1..].trim_start();
+        if let Some(after) = rest.strip_prefix(',') {
+            rest = after.trim_start();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertState {
+    Firing,
+    Resolved,
+}
+
+impl AlertState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertState::Firing => "firing",
+            AlertState::Resolved => "resolved",
+        }
+    }
+}
+
+/// A series of a rule starting or stopping to fire.
+#[derive(Debug, Clone, Serialize)]
+pub struct AlertTransition {
+    pub rule: String,
+    pub state: AlertState,
+    pub metric: String,
+    pub label: Option<String>,
+    pub value: f64,
+    pub threshold: f64,
+    /// When the condition started to hold.
+    #[serde(with = "time::serde::timestamp")]
+    pub since: OffsetDateTime,
+    #[serde(with = "time::serde::timestamp")]
+    pub at: OffsetDateTime,
+}
+
+/// How many reads of its family in a row a series can be missing from
+/// (unmounted disk, removed sensor) before it is dropped, resolving it if
+/// it was firing.
+const EXPIRE_AFTER: u32 = 3;
+
+#[derive(Debug, Clone, Copy)]
+enum Series {
+    Pending(OffsetDateTime),
+    Firing(OffsetDateTime),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Tracked {
+    state: Series,
+    /// The latest value, reported if the series expires.
+    value: f64,
+    /// Reads of the family since the series was last in one.
+    missed: u32,
+}
+
+type SeriesKey = (String, String, Option<String>);
+
+/// Evaluates the `[[alerts]]` rules against each cycle's readings, tracking
+/// every matching series (rule, metric and label) on its own.
+#[derive(Debug, Default)]
+pub struct AlertEngine {
+    rules: Vec<(AlertConfig, Condition)>,
+    series: HashMap<SeriesKey, Tracked>,
+}
+
+impl AlertEngine {
+    pub fn new(configs: &[AlertConfig]) -> Result<Self> {
+        let mut rules: Vec<(AlertConfig, Condition)> = Vec::new();
+        for config in configs {
+            anyhow::ensure!(
+                !rules.iter().any(|(c, _)| c.name == config.name),
+                "duplicate alert name {:?}",
+                config.name
+            );
+            anyhow::ensure!(
+                config.command.is_some() || !config.actions.contains(&AlertAction::Command),
+                "alert {:?} has the command action but no command",
+                config.name
+            );
+            let condition = config
+                .rule
+                .parse()
+                .with_context(|| format!("alert {:?}", config.name))?;
+            rules.push((config.clone(), condition));
+        }
+        Ok(Self {
+            rules,
+            series: HashMap::new(),
+        })
+    }
+
+    /// Keeps the pending and firing series of rules that didn't change, so
+    /// a reload neither re-fires nor forgets them.
+    pub fn carry_over(&mut self, previous: AlertEngine) {
+        for (key, series) in previous.series {
+            let unchanged = previous
+                .rules
+                .iter()
+                .any(|(old, _)| old.name == key.0 && self.rules.iter().any(|(new, _)| new == old));
+            if unchanged {
+                self.series.insert(key, series);
+            }
+        }
+    }
+
+    pub fn config(&self, rule: &str) -> Option<&AlertConfig> {
+        self.rules
+            .iter()
+            .map(|(config, _)| config)
+            .find(|config| config.name == rule)
+    }
+
+    pub fn evaluate(&mut self, readings: &[MetricReading]) -> Vec<AlertTransition> {
+        let mut transitions = Vec::new();
+        for (config, condition) in &self.rules {
+            let mut seen = HashSet::new();
+            for reading in readings.iter().filter(|r| condition.matches(r)) {
+                let key = (
+                    config.name.clone(),
+                    reading.metric.clone(),
+                    reading.label.clone(),
+                );
+                seen.insert(key.clone());
+                let at = reading.timestamp;
+                let breached = condition
+                    .comparison
+                    .holds(reading.value, condition.threshold);
+                let transition = |state, since| AlertTransition {
+                    rule: config.name.clone(),
+                    state,
+                    metric: reading.metric.clone(),
+                    label: reading.label.clone(),
+                    value: reading.value,
+                    threshold: condition.threshold,
+                    since,
+                    at,
+                };
+                let tracked = |state| Tracked {
+                    state,
+                    value: reading.value,
+                    missed: 0,
+                };
+                match self.series.get(&key).map(|t| t.state) {
+                    None | Some(Series::Pending(_)) if !breached => {
+                        self.series.remove(&key);
+                    }
+                    None | Some(Series::Pending(_)) => {
+                        let since = match self.series.get(&key).map(|t| t.state) {
+                            Some(Series::Pending(since)) => since,
+                            _ => at,
+                        };
+                        if (at - since).unsigned_abs() >= condition.hold {
+                            self.series.insert(key, tracked(Series::Firing(since)));
+                            transitions.push(transition(AlertState::Firing, since));
+                        } else {
+                            self.series.insert(key, tracked(Series::Pending(since)));
+                        }
+                    }
+                    Some(Series::Firing(since)) => {
+                        let relaxed = condition
+                            .comparison
+                            .relaxed(condition.threshold, config.hysteresis);
+                        if !condition.comparison.holds(reading.value, relaxed) {
+                            self.series.remove(&key);
+                            transitions.push(transition(AlertState::Resolved, since));
+                        } else {
+                            self.series.insert(key, tracked(Series::Firing(since)));
+                        }
+                    }
+                }
+            }
+
+            // Only a read of the family counts against the series it left
+            // out; other sources' cycles say nothing about them.
+            let Some(at) = readings
+                .iter()
+                .filter(|r| condition.target.matches(r))
+                .map(|r| r.timestamp)
+                .max()
+            else {
+                continue;
+            };
+            self.series.retain(|key, tracked| {
+                if key.0 != config.name || seen.contains(key) {
+                    return true;
+                }
+                tracked.missed += 1;
+                if tracked.missed < EXPIRE_AFTER {
+                    return true;
+                }
+                if let Series::Firing(since) = tracked.state {
+                    transitions.push(AlertTransition {
+                        rule: config.name.clone(),
+                        state: AlertState::Resolved,
+                        metric: key.1.clone(),
+                        label: key.2.clone(),
+                        value: tracked.value,
+                        threshold: condition.threshold,
+                        since,
+                        at,
+                    });
+                }
+                false
+            });
+        }
+        transitions
+    }
+}
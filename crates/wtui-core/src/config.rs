@@ -1,14 +1,19 @@
-use crate::metrics::MetricKind;
+use crate::metrics::{HostRoots, MetricKind};
 use crate::timeutils::{duration_from_std, parse_range};
 use anyhow::{Context, Result};
 use directories::{BaseDirs, ProjectDirs};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ffi::OsString;
 use std::fs;
+use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 use time::Duration as TimeDuration;
 
+/// The shortest sampling interval the config may ask for.
+pub const MIN_INTERVAL: Duration = Duration::from_secs(1);
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub database: DatabaseConfig,
@@ -20,6 +25,13 @@ pub struct Config {
     pub viewer: ViewerConfig,
     #[serde(default)]
     pub presets: HashMap<String, Preset>,
+    /// Threshold rules the daemon evaluates every cycle, one `[[alerts]]`
+    /// table each.
+    #[serde(default)]
+    pub alerts: Vec<AlertConfig>,
+    /// Monthly data caps, one `[[quotas]]` table per interface.
+    #[serde(default)]
+    pub quotas: Vec<QuotaConfig>,
 }
 
 impl Default for Config {
@@ -30,6 +42,8 @@ impl Default for Config {
             logging: LoggingConfig::default(),
             viewer: ViewerConfig::default(),
             presets: Preset::default_presets(),
+            alerts: Vec::new(),
+            quotas: Vec::new(),
         }
     }
 }
@@ -49,15 +63,49 @@ impl Config {
             let content = fs::read_to_string(&path)
                 .with_context(|| format!("reading config at {:?}", path))?;
             let mut cfg: Config = toml::from_str(&content).context("parsing config")?;
+            cfg.daemon
+                .check_intervals()
+                .with_context(|| format!("invalid config at {:?}", path))?;
             cfg.expand_paths();
+            cfg.apply_env();
             Ok(cfg)
         } else {
             let mut cfg = Config::default();
             cfg.expand_paths();
+            cfg.apply_env();
             Ok(cfg)
         }
     }
 
+    /// Environment overrides for settings that depend on where the process
+    /// runs rather than on the user's preferences.
+    pub fn apply_env(&mut self) {
+        self.apply_env_from(|name| std::env::var_os(name));
+    }
+
+    /// [`Config::apply_env`] with the variables looked up through `var`.
+    pub fn apply_env_from(&mut self, var: impl Fn(&str) -> Option<OsString>) {
+        if let Some(root) = var("WTUI_PROC_ROOT") {
+            self.daemon.proc_root = PathBuf::from(root);
+        }
+        if let Some(root) = var("WTUI_SYS_ROOT") {
+            self.daemon.sys_root = PathBuf::from(root);
+        }
+    }
+
+    /// The SQLite sink's spool file: `daemon.spool.path` if set, else
+    /// `<database>.spool`, on the same disk as the database and as durable.
+    pub fn spool_path(&self) -> PathBuf {
+        match &self.daemon.spool.path {
+            Some(path) => path.clone(),
+            None => {
+                let mut path = self.database.path.clone().into_os_string();
+                path.push(".spool");
+                PathBuf::from(path)
+            }
+        }
+    }
+
     pub fn expand_paths(&mut self) {
         self.database.path = expand_tilde(&self.database.path);
         if let Some(file) = &self.logging.file {
@@ -66,6 +114,20 @@ impl Config {
         if let Some(pid) = &self.daemon.pid_file {
             self.daemon.pid_file = Some(expand_tilde(pid));
         }
+        if let Some(socket) = &self.daemon.control_socket {
+            self.daemon.control_socket = Some(expand_tilde(socket));
+        }
+        if let Some(dir) = &self.daemon.textfile_dir {
+            self.daemon.textfile_dir = Some(expand_tilde(dir));
+        }
+        if let Some(path) = &self.daemon.spool.path {
+            self.daemon.spool.path = Some(expand_tilde(path));
+        }
+        for sink in &mut self.daemon.sinks {
+            if let SinkConfig::Jsonl { path } = sink {
+                *path = expand_tilde(path);
+            }
+        }
     }
 }
 
@@ -88,6 +150,9 @@ impl Default for DatabaseConfig {
 pub struct DaemonConfig {
     #[serde(default = "DaemonConfig::default_interval", with = "humantime_serde")]
     pub interval: Duration,
+    /// Per-family overrides of `interval`, e.g. `{ temps = "5s", disk = "10m" }`.
+    #[serde(default, with = "interval_map")]
+    pub intervals: HashMap<MetricKind, Duration>,
     #[serde(default = "DaemonConfig::default_metrics")]
     pub metrics: Vec<MetricKind>,
     #[serde(default)]
@@ -96,16 +161,70 @@ pub struct DaemonConfig {
     pub net_interfaces: Vec<String>,
     #[serde(default = "DaemonConfig::default_pid_file")]
     pub pid_file: Option<PathBuf>,
+    /// Control socket; defaults to `$XDG_RUNTIME_DIR/wtui/daemon.sock`.
+    #[serde(default)]
+    pub control_socket: Option<PathBuf>,
+    /// How much recent data the daemon replays to new stream subscribers.
+    #[serde(
+        default = "DaemonConfig::default_stream_history",
+        with = "humantime_serde"
+    )]
+    pub stream_history: Duration,
+    /// CPU temperature at which a core running well below its maximum clock
+    /// counts as thermally throttled.
+    #[serde(default = "DaemonConfig::default_throttle_temp_c")]
+    pub throttle_temp_c: f64,
+    /// User-defined command metrics, one `[[daemon.custom]]` table each.
+    #[serde(default)]
+    pub custom: Vec<CustomMetricConfig>,
+    /// Where each cycle's samples are written; SQLite alone by default.
+    #[serde(default = "DaemonConfig::default_sinks")]
+    pub sinks: Vec<SinkConfig>,
+    /// Time-series databases that also receive every sample, one
+    /// `[[daemon.forward]]` table each.
+    #[serde(default)]
+    pub forward: Vec<ForwardConfig>,
+    /// Where SQLite writes wait while the database can't be written.
+    #[serde(default)]
+    pub spool: SpoolConfig,
+    /// Directory scanned for node_exporter-style `*.prom` files every cycle.
+    #[serde(default)]
+    pub textfile_dir: Option<PathBuf>,
+    /// Overrides applied while running on battery.
+    #[serde(default)]
+    pub battery: Option<BatteryProfile>,
+    /// Serves `/metrics` for Prometheus when present.
+    #[serde(default)]
+    pub exporter: Option<ExporterConfig>,
+    /// Mount point of procfs; overridden by `WTUI_PROC_ROOT`.
+    #[serde(default = "DaemonConfig::default_proc_root")]
+    pub proc_root: PathBuf,
+    /// Mount point of sysfs; overridden by `WTUI_SYS_ROOT`.
+    #[serde(default = "DaemonConfig::default_sys_root")]
+    pub sys_root: PathBuf,
 }
 
 impl Default for DaemonConfig {
     fn default() -> Self {
         Self {
             interval: Self::default_interval(),
+            intervals: HashMap::new(),
             metrics: Self::default_metrics(),
             disk_devices: vec!["/".into()],
             net_interfaces: vec![],
             pid_file: Some(PathBuf::from("~/.local/state/wtui/wtui-daemon.pid")),
+            control_socket: None,
+            stream_history: Self::default_stream_history(),
+            throttle_temp_c: Self::default_throttle_temp_c(),
+            custom: Vec::new(),
+            sinks: Self::default_sinks(),
+            forward: Vec::new(),
+            spool: SpoolConfig::default(),
+            textfile_dir: None,
+            battery: None,
+            exporter: None,
+            proc_root: Self::default_proc_root(),
+            sys_root: Self::default_sys_root(),
         }
     }
 }
@@ -119,6 +238,121 @@ impl DaemonConfig {
         Some(PathBuf::from("~/.local/state/wtui/wtui-daemon.pid"))
     }
 
+    fn default_throttle_temp_c() -> f64 {
+        90.0
+    }
+
+    fn default_sinks() -> Vec<SinkConfig> {
+        vec![SinkConfig::Sqlite]
+    }
+
+    fn default_stream_history() -> Duration {
+        Duration::from_secs(600)
+    }
+
+    fn default_proc_root() -> PathBuf {
+        HostRoots::default().proc
+    }
+
+    fn default_sys_root() -> PathBuf {
+        HostRoots::default().sys
+    }
+
+    /// Sampling interval for one family: its `intervals` entry, else `interval`.
+    pub fn interval_for(&self, kind: MetricKind) -> Duration {
+        self.intervals.get(&kind).copied().unwrap_or(self.interval)
+    }
+
+    /// Rejects intervals below [`MIN_INTERVAL`]: zero would spin the main
+    /// loop, and readings are stored with whole-second timestamps.
+    pub fn check_intervals(&self) -> Result<()> {
+        let mut intervals = vec![("daemon.interval".to_string(), self.interval)];
+        intervals.extend(
+            self.intervals
+                .iter()
+                .map(|(kind, every)| (format!("daemon.intervals.{kind}"), *every)),
+        );
+        if let Some(profile) = &self.battery {
+            intervals.extend(
+                profile
+                    .interval
+                    .map(|every| ("daemon.battery.interval".to_string(), every)),
+            );
+            intervals.extend(
+                profile
+                    .intervals
+                    .iter()
+                    .map(|(kind, every)| (format!("daemon.battery.intervals.{kind}"), *every)),
+            );
+        }
+        intervals.extend(self.custom.iter().filter_map(|custom| {
+            let every = custom.interval?;
+            Some((format!("interval of custom metric {}", custom.name), every))
+        }));
+        for (field, every) in intervals {
+            if every < MIN_INTERVAL {
+                anyhow::bail!(
+                    "{field} must be at least {}, got {}",
+                    humantime::format_duration(MIN_INTERVAL),
+                    humantime::format_duration(every)
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Where the control socket lives: `control_socket` if set, else under
+    /// the runtime dir, else next to the PID file.
+    pub fn control_socket_path(&self) -> PathBuf {
+        if let Some(path) = &self.control_socket {
+            return path.clone();
+        }
+        let dir = BaseDirs::new()
+            .and_then(|d| d.runtime_dir().map(|r| r.join("wtui")))
+            .or_else(|| {
+                self.pid_file
+                    .as_deref()
+                    .and_then(Path::parent)
+                    .map(Path::to_path_buf)
+            })
+            .unwrap_or_else(std::env::temp_dir);
+        dir.join("daemon.sock")
+    }
+
+    /// The reading stream socket, next to the control socket.
+    pub fn stream_socket_path(&self) -> PathBuf {
+        self.control_socket_path().with_file_name("stream.sock")
+    }
+
+    pub fn host_roots(&self) -> HostRoots {
+        HostRoots::new(&self.proc_root, &self.sys_root)
+    }
+
+    /// The settings to sample with for the given power source: unchanged on
+    /// AC or without a `[daemon.battery]` profile.
+    pub fn for_power_source(&self, on_battery: bool) -> DaemonConfig {
+        let mut daemon = self.clone();
+        let Some(profile) = self.battery.as_ref().filter(|_| on_battery) else {
+            return daemon;
+        };
+        if let Some(interval) = profile.interval {
+            daemon.interval = interval;
+        }
+        daemon
+            .intervals
+            .extend(profile.intervals.iter().map(|(k, v)| (*k, *v)));
+        daemon
+            .metrics
+            .retain(|kind| !profile.disabled.contains(kind));
+        if profile.disabled.contains(&MetricKind::Custom) {
+            daemon.custom.clear();
+        }
+        if profile.disabled.contains(&MetricKind::Textfile) {
+            daemon.textfile_dir = None;
+        }
+        daemon
+    }
+
     fn default_metrics() -> Vec<MetricKind> {
         vec![
             MetricKind::Cpu,
@@ -128,10 +362,293 @@ impl DaemonConfig {
             MetricKind::Temps,
             MetricKind::Disk,
             MetricKind::Power,
+            MetricKind::Gpu,
+            MetricKind::Uptime,
+            MetricKind::Backlight,
+            MetricKind::PowerProfile,
+            MetricKind::Throttle,
         ]
     }
 }
 
+/// `[daemon.battery]`: how sampling changes while the AC adapter is offline.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatteryProfile {
+    /// Replaces `daemon.interval` on battery.
+    #[serde(default, with = "humantime_serde")]
+    pub interval: Option<Duration>,
+    /// Per-family intervals on battery, taking precedence over
+    /// `daemon.intervals`.
+    #[serde(default, with = "interval_map")]
+    pub intervals: HashMap<MetricKind, Duration>,
+    /// Families not collected at all on battery.
+    #[serde(default)]
+    pub disabled: Vec<MetricKind>,
+}
+
+/// `[daemon.spool]`: the backlog of readings the SQLite sink failed to
+/// write, replayed in order once writes succeed again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SpoolConfig {
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    /// Readings held in memory before the oldest spill to the file.
+    #[serde(default = "SpoolConfig::default_memory_readings")]
+    pub memory_readings: usize,
+    /// Largest the file may grow; batches that don't fit are dropped,
+    /// keeping those already spooled.
+    #[serde(
+        default = "SpoolConfig::default_max_bytes",
+        deserialize_with = "crate::quota::deserialize_bytes"
+    )]
+    pub max_bytes: u64,
+}
+
+impl Default for SpoolConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            memory_readings: Self::default_memory_readings(),
+            max_bytes: Self::default_max_bytes(),
+        }
+    }
+}
+
+impl SpoolConfig {
+    fn default_memory_readings() -> usize {
+        10_000
+    }
+
+    fn default_max_bytes() -> u64 {
+        64 * 1024 * 1024
+    }
+}
+
+/// `[daemon.exporter]`: the OpenMetrics endpoint.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExporterConfig {
+    /// Address to listen on; keep it on loopback unless the port is
+    /// firewalled, as there is no authentication.
+    #[serde(default = "ExporterConfig::default_listen")]
+    pub listen: SocketAddr,
+}
+
+impl Default for ExporterConfig {
+    fn default() -> Self {
+        Self {
+            listen: Self::default_listen(),
+        }
+    }
+}
+
+impl ExporterConfig {
+    fn default_listen() -> SocketAddr {
+        SocketAddr::from(([127, 0, 0, 1], 9863))
+    }
+}
+
+/// One `[[alerts]]` table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AlertConfig {
+    pub name: String,
+    /// `<metric>[{matchers}] <op> <threshold> [for <duration>]`, e.g.
+    /// `temps{sensor=~"k10temp.*"} > 90 for 2m`.
+    pub rule: String,
+    /// How far the value must come back past the threshold before a firing
+    /// alert resolves, so a reading hovering around it doesn't flap.
+    #[serde(default)]
+    pub hysteresis: f64,
+    #[serde(default = "AlertConfig::default_actions")]
+    pub actions: Vec<AlertAction>,
+    /// Run through `sh -c` for the `command` action, with the alert in
+    /// `WTUI_ALERT_*` environment variables.
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+impl AlertConfig {
+    fn default_actions() -> Vec<AlertAction> {
+        vec![AlertAction::Log, AlertAction::Event]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertAction {
+    Log,
+    /// Record `alert` / `alert_resolved` events in the database.
+    Event,
+    Command,
+}
+
+/// One `[[quotas]]` table: a data cap on one interface, counted from the
+/// `net_samples` deltas of each billing cycle.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuotaConfig {
+    pub interface: String,
+    /// Bytes per billing cycle: a number, or a string such as `"20GB"` or
+    /// `"1.5 GiB"`.
+    #[serde(deserialize_with = "crate::quota::deserialize_bytes")]
+    pub limit: u64,
+    /// Day of the month (UTC) the cycle starts over; days past the end of a
+    /// short month fall on its last day.
+    #[serde(default = "QuotaConfig::default_reset_day")]
+    pub reset_day: u8,
+    #[serde(default)]
+    pub direction: QuotaDirection,
+    /// Percentages of `limit` that trigger a warning, once per cycle each.
+    #[serde(default = "QuotaConfig::default_warn_at")]
+    pub warn_at: Vec<f64>,
+    /// Run through `sh -c` on each warning, with `WTUI_QUOTA_*` environment
+    /// variables describing it.
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+impl QuotaConfig {
+    fn default_reset_day() -> u8 {
+        1
+    }
+
+    fn default_warn_at() -> Vec<f64> {
+        vec![80.0, 90.0, 100.0]
+    }
+}
+
+/// Which traffic counts against a quota.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuotaDirection {
+    #[default]
+    Total,
+    Rx,
+    Tx,
+}
+
+/// One `[[daemon.sinks]]` table.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SinkConfig {
+    /// The `[database]` the viewer reads.
+    Sqlite,
+    /// Appends one JSON reading per line to `path`.
+    Jsonl { path: PathBuf },
+    /// Prints one JSON reading per line to standard output.
+    Stdout,
+    /// Publishes the latest readings to an MQTT broker.
+    Mqtt(MqttSinkConfig),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MqttSinkConfig {
+    /// `host` or `host:port` (default port 1883); IPv6 as `[::1]:1883`.
+    pub broker: String,
+    /// Defaults to `wtui-<hostname>`.
+    #[serde(default)]
+    pub client_id: Option<String>,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Readings go to `<topic_prefix>/<host>/<family>/<metric>[/<instance>]`
+    /// and availability to `<topic_prefix>/<host>/status`.
+    #[serde(default = "MqttSinkConfig::default_topic_prefix")]
+    pub topic_prefix: String,
+    /// Host part of the topics; defaults to the hostname.
+    #[serde(default)]
+    pub host: Option<String>,
+    /// Announce batteries, temperatures and disk usage to Home Assistant.
+    #[serde(default)]
+    pub discovery: bool,
+    #[serde(default = "MqttSinkConfig::default_discovery_prefix")]
+    pub discovery_prefix: String,
+}
+
+impl MqttSinkConfig {
+    fn default_topic_prefix() -> String {
+        "wtui".into()
+    }
+
+    fn default_discovery_prefix() -> String {
+        "homeassistant".into()
+    }
+}
+
+/// `[[daemon.forward]]`: a remote time-series database fed alongside SQLite.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ForwardConfig {
+    #[serde(flatten)]
+    pub target: ForwardTarget,
+    /// `host` tag (Graphite path component); defaults to the hostname.
+    #[serde(default)]
+    pub host: Option<String>,
+    /// Lines held while the endpoint is unreachable; beyond this the oldest
+    /// are dropped.
+    #[serde(default = "ForwardConfig::default_queue_size")]
+    pub queue_size: usize,
+    /// Most lines sent in one request or write.
+    #[serde(default = "ForwardConfig::default_batch_size")]
+    pub batch_size: usize,
+}
+
+impl ForwardConfig {
+    fn default_queue_size() -> usize {
+        10_000
+    }
+
+    fn default_batch_size() -> usize {
+        500
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "protocol", rename_all = "kebab-case")]
+pub enum ForwardTarget {
+    /// InfluxDB write endpoint: `/write?db=...` (1.x) or
+    /// `/api/v2/write?org=...&bucket=...` (2.x).
+    InfluxHttp {
+        url: String,
+        /// Sent as `Authorization: Token <token>`.
+        #[serde(default)]
+        token: Option<String>,
+    },
+    /// InfluxDB UDP listener, `host:port`.
+    InfluxUdp { address: String },
+    /// Carbon plaintext listener, `host:port`.
+    Graphite {
+        address: String,
+        #[serde(default = "default_graphite_prefix")]
+        prefix: String,
+    },
+}
+
+fn default_graphite_prefix() -> String {
+    "wtui".into()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomMetricConfig {
+    pub name: String,
+    /// Shell command line, run through `sh -c`.
+    pub command: String,
+    /// How often to run the command; defaults to the daemon interval.
+    #[serde(default, with = "humantime_serde")]
+    pub interval: Option<Duration>,
+    #[serde(
+        default = "CustomMetricConfig::default_timeout",
+        with = "humantime_serde"
+    )]
+    pub timeout: Duration,
+    #[serde(default)]
+    pub unit: Option<String>,
+}
+
+impl CustomMetricConfig {
+    fn default_timeout() -> Duration {
+        Duration::from_secs(10)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoggingConfig {
     #[serde(default = "LoggingConfig::default_level")]
@@ -285,3 +802,32 @@ fn expand_tilde(path: &Path) -> PathBuf {
         expanded
     }
 }
+
+/// (De)serializes `{ family = "duration" }` tables with humantime strings.
+mod interval_map {
+    use crate::metrics::MetricKind;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::HashMap;
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        map: &HashMap<MetricKind, Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        map.iter()
+            .map(|(kind, interval)| (*kind, humantime_serde::Serde::from(*interval)))
+            .collect::<HashMap<_, _>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<MetricKind, Duration>, D::Error> {
+        let raw =
+            HashMap::<MetricKind, humantime_serde::Serde<Duration>>::deserialize(deserializer)?;
+        Ok(raw
+            .into_iter()
+            .map(|(kind, interval)| (kind, interval.into_inner()))
+            .collect())
+    }
+}
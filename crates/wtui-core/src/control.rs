@@ -0,0 +1,113 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::time::Duration;
+use time::OffsetDateTime;
+
+/// One command on the daemon's control socket. The wire format is a single
+/// JSON object per line, e.g. `{"cmd":"sample-now"}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "kebab-case")]
+pub enum ControlRequest {
+    Status,
+    Reload,
+    Pause,
+    Resume,
+    SampleNow,
+    PruneNow,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ControlResponse {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<DaemonStatus>,
+}
+
+impl ControlResponse {
+    pub fn ok(message: impl Into<String>) -> Self {
+        Self {
+            ok: true,
+            message: Some(message.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn error(error: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(error.into()),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    #[serde(with = "time::serde::timestamp")]
+    pub started_at: OffsetDateTime,
+    pub paused: bool,
+    pub on_battery: Option<bool>,
+    /// When the last cycle that read at least one source ran.
+    #[serde(default, with = "time::serde::timestamp::option")]
+    pub last_cycle: Option<OffsetDateTime>,
+    pub last_cycle_secs: Option<f64>,
+    pub sources: Vec<SourceHealth>,
+    #[serde(default)]
+    pub sinks: Vec<SourceHealth>,
+}
+
+/// Outcome of the most recent reads of one source, or writes of one sink.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SourceHealth {
+    pub name: String,
+    #[serde(default, with = "time::serde::timestamp::option")]
+    pub last_ok: Option<OffsetDateTime>,
+    #[serde(default, with = "time::serde::timestamp::option")]
+    pub last_error_at: Option<OffsetDateTime>,
+    pub last_error: Option<String>,
+    /// Consecutive failures; reset by a success.
+    pub failures: u32,
+    /// Readings a sink is holding back; only for sinks that queue.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backlog: Option<Backlog>,
+}
+
+/// What a sink holds while its destination fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Backlog {
+    /// Readings waiting to be written, in memory or spooled to disk.
+    pub queued: u64,
+    pub spooled_bytes: u64,
+    /// Readings given up on since the daemon started because the backlog
+    /// was full.
+    pub dropped: u64,
+}
+
+impl SourceHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.failures == 0
+    }
+}
+
+/// Sends one request to the daemon at `socket` and waits for its reply.
+pub fn send_request(socket: &Path, request: ControlRequest) -> Result<ControlResponse> {
+    let mut stream = UnixStream::connect(socket)
+        .with_context(|| format!("connecting to {socket:?}; is wtui-daemon running?"))?;
+    stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+    let mut line = serde_json::to_string(&request)?;
+    line.push('\n');
+    stream.write_all(line.as_bytes())?;
+    let mut reply = String::new();
+    BufReader::new(stream)
+        .read_line(&mut reply)
+        .context("reading daemon reply")?;
+    serde_json::from_str(&reply).context("parsing daemon reply")
+}
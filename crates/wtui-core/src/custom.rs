@@ -0,0 +1,122 @@
+use crate::config::CustomMetricConfig;
+use anyhow::{Context, Result};
+use nix::sys::signal::{killpg, Signal};
+use nix::unistd::Pid;
+use std::io::Read;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomReading {
+    pub name: String,
+    pub label: Option<String>,
+    pub value: f64,
+    pub unit: Option<String>,
+}
+
+/// Runs a `[[daemon.custom]]` command and parses its stdout, killing it if it
+/// outlives the configured timeout.
+pub fn run_custom_metric(cfg: &CustomMetricConfig) -> Result<Vec<CustomReading>> {
+    let output = run_with_timeout(&cfg.command, cfg.timeout)
+        .with_context(|| format!("running custom metric {}", cfg.name))?;
+    let values = parse_custom_output(&output)
+        .with_context(|| format!("parsing output of custom metric {}", cfg.name))?;
+    Ok(values
+        .into_iter()
+        .map(|(label, value)| CustomReading {
+            name: cfg.name.clone(),
+            label,
+            value,
+            unit: cfg.unit.clone(),
+        })
+        .collect())
+}
+
+/// Runs `command` in its own process group, so a timeout kills everything
+/// it started. Output is only waited for until the same deadline: a
+/// background job that inherited stdout would otherwise keep the pipe open
+/// long after `sh` itself exited.
+fn run_with_timeout(command: &str, timeout: Duration) -> Result<String> {
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()
+        .context("spawning command")?;
+
+    // Drain stdout on a separate thread so a chatty command can't fill the
+    // pipe and block before we get to observe its exit.
+    let mut stdout = child.stdout.take().context("capturing stdout")?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = String::new();
+        let _ = tx.send(stdout.read_to_string(&mut buf).map(|_| buf));
+    });
+
+    let deadline = Instant::now() + timeout;
+    let timed_out = |child: &mut Child| {
+        kill_group(child);
+        anyhow::anyhow!("timed out after {}", humantime::format_duration(timeout))
+    };
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            return Err(timed_out(&mut child));
+        }
+        thread::sleep(Duration::from_millis(20));
+    };
+
+    let output = match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        Ok(output) => output?,
+        Err(_) => return Err(timed_out(&mut child)),
+    };
+    if !status.success() {
+        anyhow::bail!("exited with {status}");
+    }
+    Ok(output)
+}
+
+fn kill_group(child: &mut Child) {
+    let _ = killpg(Pid::from_raw(child.id() as i32), Signal::SIGKILL);
+    let _ = child.wait();
+}
+
+/// Accepts either a single number, or one `label=value` pair per line. Blank
+/// lines and `#` comments are ignored.
+pub fn parse_custom_output(output: &str) -> Result<Vec<(Option<String>, f64)>> {
+    let lines: Vec<&str> = output
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .collect();
+    if lines.len() == 1 && !lines[0].contains('=') {
+        let value = lines[0]
+            .parse::<f64>()
+            .with_context(|| format!("not a number: {:?}", lines[0]))?;
+        return Ok(vec![(None, value)]);
+    }
+
+    let mut values = Vec::with_capacity(lines.len());
+    for line in lines {
+        let (label, value) = line
+            .split_once('=')
+            .with_context(|| format!("expected label=value, got {line:?}"))?;
+        let value = value
+            .trim()
+            .parse::<f64>()
+            .with_context(|| format!("not a number in {line:?}"))?;
+        values.push((Some(label.trim().to_string()), value));
+    }
+    if values.is_empty() {
+        anyhow::bail!("command produced no output");
+    }
+    Ok(values)
+}
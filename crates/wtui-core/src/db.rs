@@ -1,7 +1,12 @@
-use crate::metrics::NetSnapshot;
+use crate::custom::CustomReading;
+use crate::metrics::{
+    BacklightReading, CstateResidency, GpuReading, MetricKind, MetricReading, NetSnapshot,
+    PowerProfileReading, ThrottleEpisode,
+};
 use crate::timeutils::utc_from_timestamp;
 use anyhow::{Context, Result};
 use rusqlite::{params, Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
@@ -10,6 +15,17 @@ use time::OffsetDateTime;
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SchemaVersion {
     V1 = 1,
+    V2 = 2,
+    V3 = 3,
+    V4 = 4,
+    V5 = 5,
+    V6 = 6,
+    V7 = 7,
+    V8 = 8,
+}
+
+impl SchemaVersion {
+    pub const LATEST: SchemaVersion = SchemaVersion::V8;
 }
 
 #[derive(Debug)]
@@ -26,7 +42,7 @@ impl Database {
             path,
             OpenFlags::SQLITE_OPEN_READ_WRITE | OpenFlags::SQLITE_OPEN_CREATE,
         )?;
-        conn.pragma_update(None, "journal_mode", &"WAL")
+        conn.pragma_update(None, "journal_mode", "WAL")
             .context("enabling WAL mode")?;
         let db = Self { conn };
         db.migrate()?;
@@ -97,6 +113,143 @@ impl Database {
         Ok(())
     }
 
+    fn install_v2(&self) -> Result<()> {
+        self.conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS gpu_samples (
+                timestamp INTEGER NOT NULL,
+                card TEXT NOT NULL,
+                driver TEXT,
+                busy_percent REAL,
+                vram_used_bytes INTEGER,
+                vram_total_bytes INTEGER,
+                power_mw REAL,
+                cur_freq_mhz REAL,
+                act_freq_mhz REAL,
+                temp_c REAL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_gpu_ts ON gpu_samples(timestamp);
+            "#,
+        )?;
+        Ok(())
+    }
+
+    fn install_v3(&self) -> Result<()> {
+        self.conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS uptime_samples (
+                timestamp INTEGER NOT NULL,
+                boot_id TEXT NOT NULL,
+                uptime_secs REAL NOT NULL,
+                awake_secs REAL NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS events (
+                timestamp INTEGER NOT NULL,
+                kind TEXT NOT NULL,
+                duration_secs REAL,
+                detail TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_uptime_ts ON uptime_samples(timestamp);
+            CREATE INDEX IF NOT EXISTS idx_events_ts ON events(timestamp);
+            "#,
+        )?;
+        Ok(())
+    }
+
+    fn install_v4(&self) -> Result<()> {
+        self.conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS backlight_samples (
+                timestamp INTEGER NOT NULL,
+                device TEXT NOT NULL,
+                brightness INTEGER NOT NULL,
+                max_brightness INTEGER NOT NULL
+            );
+
+            CREATE TABLE IF NOT EXISTS power_profile_samples (
+                timestamp INTEGER NOT NULL,
+                source TEXT NOT NULL,
+                profile TEXT NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_backlight_ts ON backlight_samples(timestamp);
+            CREATE INDEX IF NOT EXISTS idx_power_profile_ts ON power_profile_samples(timestamp);
+            "#,
+        )?;
+        Ok(())
+    }
+
+    fn install_v5(&self) -> Result<()> {
+        self.conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS cstate_samples (
+                timestamp INTEGER NOT NULL,
+                cpu TEXT NOT NULL,
+                state TEXT NOT NULL,
+                residency_pct REAL NOT NULL,
+                usage_delta INTEGER NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_cstate_ts ON cstate_samples(timestamp);
+            "#,
+        )?;
+        Ok(())
+    }
+
+    fn install_v6(&self) -> Result<()> {
+        self.conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS custom_samples (
+                timestamp INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                label TEXT,
+                value REAL NOT NULL,
+                unit TEXT
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_custom_ts ON custom_samples(timestamp);
+            CREATE INDEX IF NOT EXISTS idx_custom_name ON custom_samples(name, timestamp);
+            "#,
+        )?;
+        Ok(())
+    }
+
+    fn install_v7(&self) -> Result<()> {
+        self.conn.execute_batch(
+            r#"
+            CREATE TABLE IF NOT EXISTS daemon_samples (
+                timestamp INTEGER NOT NULL,
+                name TEXT NOT NULL,
+                label TEXT,
+                value REAL NOT NULL
+            );
+
+            CREATE INDEX IF NOT EXISTS idx_daemon_name ON daemon_samples(name, timestamp);
+            CREATE INDEX IF NOT EXISTS idx_daemon_ts ON daemon_samples(timestamp);
+            "#,
+        )?;
+        Ok(())
+    }
+
+    /// Tells textfile series from `[[daemon.custom]]` commands of the same
+    /// name; rows from before this version can't be told apart and count as
+    /// custom.
+    fn install_v8(&self) -> Result<()> {
+        self.conn.execute_batch(
+            r#"
+            ALTER TABLE custom_samples ADD COLUMN source TEXT NOT NULL DEFAULT 'custom';
+
+            DROP INDEX IF EXISTS idx_custom_name;
+            CREATE INDEX IF NOT EXISTS idx_custom_name ON custom_samples(source, name, timestamp);
+            DROP VIEW IF EXISTS custom_samples_view;
+            "#,
+        )?;
+        Ok(())
+    }
+
     pub fn insert_cpu_usage(
         &self,
         timestamp: OffsetDateTime,
@@ -189,6 +342,358 @@ impl Database {
         Ok(())
     }
 
+    pub fn insert_gpu_sample(&self, timestamp: OffsetDateTime, gpu: &GpuReading) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO gpu_samples(timestamp, card, driver, busy_percent, vram_used_bytes, vram_total_bytes, power_mw, cur_freq_mhz, act_freq_mhz, temp_c) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                timestamp.unix_timestamp(),
+                gpu.card,
+                gpu.driver,
+                gpu.busy_percent,
+                gpu.vram_used_bytes.map(|v| v as i64),
+                gpu.vram_total_bytes.map(|v| v as i64),
+                gpu.power_mw,
+                gpu.cur_freq_mhz,
+                gpu.act_freq_mhz,
+                gpu.temp_c
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn insert_uptime_sample(
+        &self,
+        timestamp: OffsetDateTime,
+        boot_id: &str,
+        uptime_secs: f64,
+        awake_secs: f64,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO uptime_samples(timestamp, boot_id, uptime_secs, awake_secs) VALUES (?1, ?2, ?3, ?4)",
+            params![timestamp.unix_timestamp(), boot_id, uptime_secs, awake_secs],
+        )?;
+        Ok(())
+    }
+
+    pub fn insert_backlight_sample(
+        &self,
+        timestamp: OffsetDateTime,
+        backlight: &BacklightReading,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO backlight_samples(timestamp, device, brightness, max_brightness) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                timestamp.unix_timestamp(),
+                backlight.device,
+                backlight.brightness as i64,
+                backlight.max_brightness as i64
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn insert_power_profile_sample(
+        &self,
+        timestamp: OffsetDateTime,
+        profile: &PowerProfileReading,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO power_profile_samples(timestamp, source, profile) VALUES (?1, ?2, ?3)",
+            params![timestamp.unix_timestamp(), profile.source, profile.profile],
+        )?;
+        Ok(())
+    }
+
+    pub fn insert_cstate_sample(
+        &self,
+        timestamp: OffsetDateTime,
+        residency: &CstateResidency,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO cstate_samples(timestamp, cpu, state, residency_pct, usage_delta) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                timestamp.unix_timestamp(),
+                residency.cpu,
+                residency.state,
+                residency.residency_pct,
+                residency.usage_delta as i64
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// `source` is the family the reading came from, `custom` or `textfile`.
+    pub fn insert_custom_sample(
+        &self,
+        timestamp: OffsetDateTime,
+        source: MetricKind,
+        reading: &CustomReading,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO custom_samples(timestamp, source, name, label, value, unit) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                timestamp.unix_timestamp(),
+                source.to_string(),
+                reading.name,
+                reading.label,
+                reading.value,
+                reading.unit
+            ],
+        )?;
+        Ok(())
+    }
+
+    pub fn insert_daemon_sample(
+        &self,
+        timestamp: OffsetDateTime,
+        name: &str,
+        label: Option<&str>,
+        value: f64,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO daemon_samples(timestamp, name, label, value) VALUES (?1, ?2, ?3, ?4)",
+            params![timestamp.unix_timestamp(), name, label, value],
+        )?;
+        Ok(())
+    }
+
+    pub fn insert_event(
+        &self,
+        timestamp: OffsetDateTime,
+        kind: &str,
+        duration_secs: Option<f64>,
+        detail: Option<&str>,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO events(timestamp, kind, duration_secs, detail) VALUES (?1, ?2, ?3, ?4)",
+            params![timestamp.unix_timestamp(), kind, duration_secs, detail],
+        )?;
+        Ok(())
+    }
+
+    pub fn insert_throttle_episode(&self, episode: &ThrottleEpisode) -> Result<()> {
+        self.insert_event_row(&EventRow::throttle(episode)?)
+    }
+
+    pub fn insert_event_row(&self, event: &EventRow) -> Result<()> {
+        self.insert_event(
+            event.timestamp,
+            &event.kind,
+            event.duration_secs,
+            event.detail.as_deref(),
+        )
+    }
+
+    /// Writes `events` in a single transaction.
+    pub fn insert_events(&self, events: &[EventRow]) -> Result<()> {
+        let tx = self.conn.unchecked_transaction()?;
+        for event in events {
+            self.insert_event_row(event)?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Writes readings from any [`crate::sources::MetricSource`] into the
+    /// family tables, in a single transaction. Readings that describe one row
+    /// (e.g. a battery's capacity, health and power) are grouped by family,
+    /// timestamp and label. Returns the number of rows written.
+    pub fn insert_readings(&self, readings: &[MetricReading]) -> Result<usize> {
+        type GroupKey = (MetricKind, i64, Option<String>);
+        let mut groups: Vec<(GroupKey, Vec<&MetricReading>)> = Vec::new();
+        let mut index: HashMap<GroupKey, usize> = HashMap::new();
+        for r in readings {
+            let key = (r.kind, r.timestamp.unix_timestamp(), r.label.clone());
+            match index.get(&key) {
+                Some(&i) => groups[i].1.push(r),
+                None => {
+                    index.insert(key.clone(), groups.len());
+                    groups.push((key, vec![r]));
+                }
+            }
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+        let mut written = 0;
+        for ((kind, _, label), group) in &groups {
+            let ts = group[0].timestamp;
+            let value = |metric: &str| group.iter().find(|r| r.metric == metric).map(|r| r.value);
+            let label = label.as_deref().unwrap_or("");
+            match kind {
+                MetricKind::Cpu => {
+                    if let Some(usage) = value("cpu_usage") {
+                        self.insert_cpu_usage(ts, usage, Some(label))?;
+                        written += 1;
+                    }
+                }
+                MetricKind::Ram => {
+                    if let (Some(used), Some(total)) =
+                        (value("ram_used_bytes"), value("ram_total_bytes"))
+                    {
+                        self.insert_ram_usage(ts, used as u64, total as u64)?;
+                        written += 1;
+                    }
+                }
+                MetricKind::Net => {
+                    if let (Some(rx), Some(tx)) = (value("net_rx_bytes"), value("net_tx_bytes")) {
+                        let snapshot = NetSnapshot {
+                            rx_bytes: rx as u64,
+                            tx_bytes: tx as u64,
+                        };
+                        let delta = value("net_rx_delta")
+                            .zip(value("net_tx_delta"))
+                            .map(|(rx, tx)| (rx as i64, tx as i64));
+                        let reset = value("net_reset").is_some();
+                        self.insert_net_sample(ts, label, snapshot, delta, reset)?;
+                        written += 1;
+                    }
+                }
+                MetricKind::Battery => {
+                    let capacity = value("battery_capacity");
+                    let health = value("battery_health");
+                    let power = value("battery_power");
+                    if capacity.is_some() || health.is_some() || power.is_some() {
+                        self.insert_battery_sample(ts, label, capacity, health, power)?;
+                        written += 1;
+                    }
+                }
+                MetricKind::Temps => {
+                    if let Some(v) = value("temp") {
+                        self.insert_temp_sample(ts, label, v)?;
+                        written += 1;
+                    }
+                }
+                MetricKind::Disk => {
+                    if let (Some(used), Some(total)) =
+                        (value("disk_used_bytes"), value("disk_total_bytes"))
+                    {
+                        self.insert_disk_sample(ts, label, used as u64, total as u64)?;
+                        written += 1;
+                    }
+                }
+                MetricKind::Power => {
+                    if let Some(v) = value("power_draw") {
+                        self.insert_power_sample(ts, label, v)?;
+                        written += 1;
+                    }
+                }
+                MetricKind::Gpu => {
+                    let gpu = GpuReading {
+                        card: label.to_string(),
+                        driver: group[0].detail.clone().unwrap_or_default(),
+                        busy_percent: value("gpu_busy"),
+                        vram_used_bytes: value("gpu_vram_used_bytes").map(|v| v as u64),
+                        vram_total_bytes: value("gpu_vram_total_bytes").map(|v| v as u64),
+                        power_mw: value("gpu_power"),
+                        cur_freq_mhz: value("gpu_cur_freq"),
+                        act_freq_mhz: value("gpu_act_freq"),
+                        temp_c: value("gpu_temp"),
+                    };
+                    self.insert_gpu_sample(ts, &gpu)?;
+                    written += 1;
+                }
+                // Rows are per boot; without a boot id there is nothing to
+                // attribute them to.
+                MetricKind::Uptime if label.is_empty() => {}
+                MetricKind::Uptime => {
+                    if let (Some(uptime), Some(awake)) = (value("uptime"), value("awake")) {
+                        self.insert_uptime_sample(ts, label, uptime, awake)?;
+                        written += 1;
+                    }
+                }
+                MetricKind::Backlight => {
+                    if let (Some(brightness), Some(max)) = (
+                        value("backlight_brightness"),
+                        value("backlight_max_brightness"),
+                    ) {
+                        let backlight = BacklightReading {
+                            device: label.to_string(),
+                            brightness: brightness as u64,
+                            max_brightness: max as u64,
+                        };
+                        self.insert_backlight_sample(ts, &backlight)?;
+                        written += 1;
+                    }
+                }
+                MetricKind::PowerProfile => {
+                    let profile = PowerProfileReading {
+                        source: group[0].detail.clone().unwrap_or_default(),
+                        profile: label.to_string(),
+                    };
+                    self.insert_power_profile_sample(ts, &profile)?;
+                    written += 1;
+                }
+                MetricKind::Cstates => {
+                    let (cpu, state) = label.split_once(':').unwrap_or((label, ""));
+                    if let Some(residency_pct) = value("cstate_residency") {
+                        let residency = CstateResidency {
+                            cpu: cpu.to_string(),
+                            state: state.to_string(),
+                            residency_pct,
+                            usage_delta: value("cstate_usage").unwrap_or(0.0) as u64,
+                        };
+                        self.insert_cstate_sample(ts, &residency)?;
+                        written += 1;
+                    }
+                }
+                // Throttling is recorded as episodes in `events`.
+                MetricKind::Throttle => {}
+                MetricKind::Custom | MetricKind::Textfile => {
+                    for r in group {
+                        let custom = CustomReading {
+                            name: r.metric.clone(),
+                            label: r.label.clone(),
+                            value: r.value,
+                            unit: r.unit.clone(),
+                        };
+                        self.insert_custom_sample(ts, r.kind, &custom)?;
+                        written += 1;
+                    }
+                }
+                MetricKind::Daemon => {
+                    for r in group {
+                        self.insert_daemon_sample(ts, &r.metric, r.label.as_deref(), r.value)?;
+                        written += 1;
+                    }
+                }
+            }
+        }
+        tx.commit()?;
+        Ok(written)
+    }
+
+    pub fn has_event(&self, kind: &str, detail: &str) -> Result<bool> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM events WHERE kind = ?1 AND detail = ?2",
+            params![kind, detail],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
+    pub fn fetch_events(
+        &self,
+        since: Option<OffsetDateTime>,
+        kind: Option<&str>,
+    ) -> Result<Vec<EventRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT timestamp, kind, duration_secs, detail FROM events
+             WHERE (?1 IS NULL OR timestamp >= ?1) AND (?2 IS NULL OR kind = ?2)
+             ORDER BY timestamp",
+        )?;
+        let rows = stmt
+            .query_map(params![since.map(|s| s.unix_timestamp()), kind], |row| {
+                Ok(EventRow {
+                    timestamp: utc_from_timestamp(row.get(0)?),
+                    kind: row.get(1)?,
+                    duration_secs: row.get(2)?,
+                    detail: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
     pub fn prune_older_than(&self, cutoff: OffsetDateTime) -> Result<()> {
         let ts = cutoff.unix_timestamp();
         for table in [
@@ -199,12 +704,25 @@ impl Database {
             "temp_samples",
             "disk_samples",
             "power_samples",
+            "gpu_samples",
+            "uptime_samples",
+            "backlight_samples",
+            "power_profile_samples",
+            "cstate_samples",
+            "custom_samples",
+            "daemon_samples",
         ] {
             self.conn.execute(
                 &format!("DELETE FROM {table} WHERE timestamp < ?1"),
                 params![ts],
             )?;
         }
+        // A restarted daemon looks these up to avoid repeating them, however
+        // old they are; there are only a few per boot or billing cycle.
+        self.conn.execute(
+            "DELETE FROM events WHERE timestamp < ?1 AND kind NOT IN ('boot', 'quota_warning')",
+            params![ts],
+        )?;
         Ok(())
     }
 
@@ -244,24 +762,66 @@ impl Database {
         }
     }
 
-    pub fn latest_net_snapshots(&self) -> Result<HashMap<String, NetSnapshot>> {
+    /// Series for one custom or textfile metric, labelled by the `label=`
+    /// part of its output (or unlabelled for single-number commands).
+    pub fn fetch_custom_series(
+        &self,
+        source: MetricKind,
+        name: &str,
+        since: Option<OffsetDateTime>,
+    ) -> Result<Vec<MetricRow>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT timestamp, value, label FROM custom_samples
+             WHERE source = ?1 AND name = ?2 AND (?3 IS NULL OR timestamp >= ?3)
+             ORDER BY timestamp",
+        )?;
+        let since = since.map(|s| s.unix_timestamp());
+        let rows = stmt
+            .query_map(params![source.to_string(), name, since], |row| {
+                Ok(MetricRow {
+                    timestamp: utc_from_timestamp(row.get(0)?),
+                    value: row.get(1)?,
+                    label: row.get(2)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows)
+    }
+
+    /// The last stored counters of each interface, with when they were read.
+    pub fn latest_net_snapshots(&self) -> Result<HashMap<String, (OffsetDateTime, NetSnapshot)>> {
         let mut stmt = self.conn.prepare(
-            "SELECT interface, rx_bytes, tx_bytes FROM net_samples WHERE timestamp = (SELECT MAX(timestamp) FROM net_samples ns WHERE ns.interface = net_samples.interface)",
+            "SELECT interface, timestamp, rx_bytes, tx_bytes FROM net_samples WHERE timestamp = (SELECT MAX(timestamp) FROM net_samples ns WHERE ns.interface = net_samples.interface)",
         )?;
         let map = stmt
             .query_map([], |row| {
                 Ok((
                     row.get::<_, String>(0)?,
-                    NetSnapshot {
-                        rx_bytes: row.get::<_, i64>(1)? as u64,
-                        tx_bytes: row.get::<_, i64>(2)? as u64,
-                    },
+                    (
+                        utc_from_timestamp(row.get(1)?),
+                        NetSnapshot {
+                            rx_bytes: row.get::<_, i64>(2)? as u64,
+                            tx_bytes: row.get::<_, i64>(3)? as u64,
+                        },
+                    ),
                 ))
             })?
             .collect::<Result<HashMap<_, _>, _>>()?;
         Ok(map)
     }
 
+    /// Received and transmitted bytes on `interface` since `since`, summed
+    /// from the stored deltas.
+    pub fn net_usage(&self, interface: &str, since: OffsetDateTime) -> Result<(u64, u64)> {
+        let (rx, tx): (i64, i64) = self.conn.query_row(
+            "SELECT COALESCE(SUM(MAX(rx_delta, 0)), 0), COALESCE(SUM(MAX(tx_delta, 0)), 0)
+             FROM net_samples WHERE interface = ?1 AND timestamp >= ?2",
+            params![interface, since.unix_timestamp()],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok((rx as u64, tx as u64))
+    }
+
     pub fn aggregate_net(
         &self,
         since: Option<OffsetDateTime>,
@@ -285,7 +845,7 @@ impl Database {
                 |row| {
                     let bucket: String = row.get(0)?;
                     let value: f64 = row.get::<_, f64>(1)?;
-                    let ts = parse_bucket(&bucket).unwrap_or_else(|| OffsetDateTime::now_utc());
+                    let ts = parse_bucket(&bucket).unwrap_or_else(OffsetDateTime::now_utc);
                     Ok(MetricRow {
                         timestamp: ts,
                         value,
@@ -305,6 +865,44 @@ pub struct MetricRow {
     pub label: Option<String>,
 }
 
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventRow {
+    #[serde(with = "time::serde::timestamp")]
+    pub timestamp: OffsetDateTime,
+    pub kind: String,
+    pub duration_secs: Option<f64>,
+    pub detail: Option<String>,
+}
+
+impl EventRow {
+    pub fn new(
+        timestamp: OffsetDateTime,
+        kind: &str,
+        duration_secs: Option<f64>,
+        detail: Option<&str>,
+    ) -> Self {
+        Self {
+            timestamp,
+            kind: kind.to_string(),
+            duration_secs,
+            detail: detail.map(str::to_string),
+        }
+    }
+
+    /// A `throttle` event starting with the episode, with the full episode as
+    /// JSON detail.
+    pub fn throttle(episode: &ThrottleEpisode) -> Result<Self> {
+        let duration = (episode.end - episode.start).as_seconds_f64();
+        let detail = serde_json::to_string(episode)?;
+        Ok(Self::new(
+            episode.start,
+            "throttle",
+            Some(duration),
+            Some(&detail),
+        ))
+    }
+}
+
 /// SQLite views that normalize table schemas for the viewer.
 /// These are defined in the initial migration so the viewer can query without
 /// knowing the backing table details.
@@ -323,6 +921,12 @@ fn ensure_views(conn: &Connection) -> Result<()> {
         CREATE VIEW IF NOT EXISTS battery_samples_view AS
         SELECT timestamp, capacity AS value, name AS label FROM battery_samples;
 
+        CREATE VIEW IF NOT EXISTS battery_health_samples_view AS
+        SELECT timestamp, health AS value, name AS label FROM battery_samples WHERE health IS NOT NULL;
+
+        CREATE VIEW IF NOT EXISTS battery_power_samples_view AS
+        SELECT timestamp, power_mw AS value, name AS label FROM battery_samples WHERE power_mw IS NOT NULL;
+
         CREATE VIEW IF NOT EXISTS temp_samples_view AS
         SELECT timestamp, value, sensor AS label FROM temp_samples;
 
@@ -331,6 +935,86 @@ fn ensure_views(conn: &Connection) -> Result<()> {
 
         CREATE VIEW IF NOT EXISTS power_samples_view AS
         SELECT timestamp, draw_mw AS value, domain AS label FROM power_samples;
+
+        CREATE VIEW IF NOT EXISTS gpu_samples_view AS
+        SELECT timestamp, busy_percent AS value, card AS label FROM gpu_samples WHERE busy_percent IS NOT NULL;
+
+        CREATE VIEW IF NOT EXISTS gpu_temp_samples_view AS
+        SELECT timestamp, temp_c AS value, card AS label FROM gpu_samples WHERE temp_c IS NOT NULL;
+
+        CREATE VIEW IF NOT EXISTS gpu_power_samples_view AS
+        SELECT timestamp, power_mw AS value, card AS label FROM gpu_samples WHERE power_mw IS NOT NULL;
+
+        CREATE VIEW IF NOT EXISTS gpu_vram_samples_view AS
+        SELECT timestamp, (CAST(vram_used_bytes AS REAL) / CAST(vram_total_bytes AS REAL)) * 100.0 AS value, card AS label FROM gpu_samples WHERE vram_total_bytes > 0;
+
+        CREATE VIEW IF NOT EXISTS gpu_freq_samples_view AS
+        SELECT timestamp, COALESCE(act_freq_mhz, cur_freq_mhz) AS value, card AS label FROM gpu_samples WHERE COALESCE(act_freq_mhz, cur_freq_mhz) IS NOT NULL;
+
+        CREATE VIEW IF NOT EXISTS uptime_samples_view AS
+        SELECT timestamp, uptime_secs AS value, boot_id AS label FROM uptime_samples;
+
+        CREATE VIEW IF NOT EXISTS suspend_samples_view AS
+        SELECT timestamp, duration_secs AS value, kind AS label FROM events WHERE kind = 'suspend';
+
+        CREATE VIEW IF NOT EXISTS throttle_samples_view AS
+        SELECT timestamp, duration_secs AS value, json_extract(detail, '$.cores') AS label FROM events WHERE kind = 'throttle';
+
+        CREATE VIEW IF NOT EXISTS backlight_samples_view AS
+        SELECT timestamp, (CAST(brightness AS REAL) / CAST(max_brightness AS REAL)) * 100.0 AS value, device AS label FROM backlight_samples WHERE max_brightness > 0;
+
+        -- Profiles are ordered from most frugal to most aggressive so they chart as a
+        -- step line; vendor-specific profiles such as `custom` map to -1.
+        CREATE VIEW IF NOT EXISTS power_profile_samples_view AS
+        SELECT timestamp,
+            CASE profile
+                WHEN 'low-power' THEN 0
+                WHEN 'power-saver' THEN 0
+                WHEN 'cool' THEN 1
+                WHEN 'quiet' THEN 1
+                WHEN 'balanced' THEN 2
+                WHEN 'balanced-performance' THEN 3
+                WHEN 'performance' THEN 4
+                ELSE -1
+            END AS value,
+            profile AS label
+        FROM power_profile_samples;
+
+        CREATE VIEW IF NOT EXISTS cstate_samples_view AS
+        SELECT timestamp, residency_pct AS value, cpu || ':' || state AS label FROM cstate_samples;
+
+        CREATE VIEW IF NOT EXISTS custom_samples_view AS
+        SELECT timestamp, value, CASE WHEN label IS NULL THEN name ELSE name || ':' || label END AS label FROM custom_samples WHERE source = 'custom';
+
+        CREATE VIEW IF NOT EXISTS textfile_samples_view AS
+        SELECT timestamp, value, CASE WHEN label IS NULL THEN name ELSE name || ':' || label END AS label FROM custom_samples WHERE source = 'textfile';
+
+        CREATE VIEW IF NOT EXISTS daemon_read_samples_view AS
+        SELECT timestamp, value, label FROM daemon_samples WHERE name = 'daemon_read_duration';
+
+        CREATE VIEW IF NOT EXISTS daemon_written_samples_view AS
+        SELECT timestamp, value, label FROM daemon_samples WHERE name = 'daemon_cycle_samples';
+
+        CREATE VIEW IF NOT EXISTS daemon_read_errors_samples_view AS
+        SELECT timestamp, value, label FROM daemon_samples WHERE name = 'daemon_read_errors';
+
+        CREATE VIEW IF NOT EXISTS daemon_write_errors_samples_view AS
+        SELECT timestamp, value, label FROM daemon_samples WHERE name = 'daemon_write_errors';
+
+        CREATE VIEW IF NOT EXISTS daemon_sink_backlog_samples_view AS
+        SELECT timestamp, value, label FROM daemon_samples WHERE name = 'daemon_sink_backlog';
+
+        CREATE VIEW IF NOT EXISTS daemon_sink_dropped_samples_view AS
+        SELECT timestamp, value, label FROM daemon_samples WHERE name = 'daemon_sink_dropped';
+
+        CREATE VIEW IF NOT EXISTS daemon_db_samples_view AS
+        SELECT timestamp, value, label FROM daemon_samples WHERE name = 'daemon_db_size';
+
+        CREATE VIEW IF NOT EXISTS daemon_rss_samples_view AS
+        SELECT timestamp, value, label FROM daemon_samples WHERE name = 'daemon_rss';
+
+        CREATE VIEW IF NOT EXISTS daemon_cpu_samples_view AS
+        SELECT timestamp, value, label FROM daemon_samples WHERE name = 'daemon_cpu';
     "#,
     )?;
     Ok(())
@@ -366,10 +1050,33 @@ impl Database {
         let version: i32 = self
             .conn
             .query_row("PRAGMA user_version", [], |row| row.get(0))?;
-        if version == 0 {
+        if version < SchemaVersion::V1 as i32 {
             self.install_v1()?;
+        }
+        if version < SchemaVersion::V2 as i32 {
+            self.install_v2()?;
+        }
+        if version < SchemaVersion::V3 as i32 {
+            self.install_v3()?;
+        }
+        if version < SchemaVersion::V4 as i32 {
+            self.install_v4()?;
+        }
+        if version < SchemaVersion::V5 as i32 {
+            self.install_v5()?;
+        }
+        if version < SchemaVersion::V6 as i32 {
+            self.install_v6()?;
+        }
+        if version < SchemaVersion::V7 as i32 {
+            self.install_v7()?;
+        }
+        if version < SchemaVersion::V8 as i32 {
+            self.install_v8()?;
+        }
+        if version < SchemaVersion::LATEST as i32 {
             self.conn
-                .pragma_update(None, "user_version", &(SchemaVersion::V1 as i32))?;
+                .pragma_update(None, "user_version", SchemaVersion::LATEST as i32)?;
         }
         self.install_views()?;
         Ok(())
@@ -0,0 +1,91 @@
+use crate::metrics::{MetricKind, MetricReading};
+use crate::textfile::split_label_string;
+
+/// One reading as an InfluxDB line-protocol line (no trailing newline):
+/// measurement `wtui_<family>`, the metric as field, `host` plus the
+/// family's instance label as tags and a nanosecond timestamp. Textfile
+/// readings keep their own name (with a `value` field) and labels. `None`
+/// for values line protocol can't carry (NaN, infinities).
+pub fn influx_line(reading: &MetricReading, host: &str) -> Option<String> {
+    if !reading.value.is_finite() {
+        return None;
+    }
+    let (measurement, field) = match reading.kind {
+        MetricKind::Textfile => (reading.metric.clone(), "value"),
+        kind => (format!("wtui_{kind}"), reading.metric.as_str()),
+    };
+    let mut tags = instance_tags(reading);
+    tags.push(("host".into(), host.into()));
+    tags.sort();
+
+    let mut line = escape(&measurement, &[',', ' ']);
+    for (key, value) in tags.iter().filter(|(_, v)| !v.is_empty()) {
+        line.push(',');
+        line.push_str(&escape(key, &[',', '=', ' ']));
+        line.push('=');
+        line.push_str(&escape(value, &[',', '=', ' ']));
+    }
+    let nanos = reading.timestamp.unix_timestamp_nanos();
+    line.push_str(&format!(
+        " {}={} {nanos}",
+        escape(field, &[',', '=', ' ']),
+        reading.value
+    ));
+    Some(line)
+}
+
+/// One reading as a Graphite plaintext line (no trailing newline):
+/// `<prefix>.<host>.<family>[.<instance>...].<metric> <value> <seconds>`.
+pub fn graphite_line(reading: &MetricReading, prefix: &str, host: &str) -> Option<String> {
+    if !reading.value.is_finite() {
+        return None;
+    }
+    let mut path: Vec<String> = prefix
+        .split('.')
+        .filter(|part| !part.is_empty())
+        .map(graphite_part)
+        .collect();
+    path.push(graphite_part(host));
+    path.push(reading.kind.to_string());
+    path.extend(instance_tags(reading).iter().map(|(_, v)| graphite_part(v)));
+    path.push(graphite_part(&reading.metric));
+    Some(format!(
+        "{} {} {}",
+        path.join("."),
+        reading.value,
+        reading.timestamp.unix_timestamp()
+    ))
+}
+
+fn instance_tags(reading: &MetricReading) -> Vec<(String, String)> {
+    match (reading.kind, reading.label.as_deref()) {
+        (_, None) => Vec::new(),
+        (MetricKind::Textfile, Some(labels)) => split_label_string(labels).unwrap_or_default(),
+        (kind, Some(label)) => vec![(kind.instance_label().into(), label.into())],
+    }
+}
+
+fn escape(value: &str, special: &[char]) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if special.contains(&c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// A Graphite path component: dots would split it, whitespace ends it.
+fn graphite_part(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | ':') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
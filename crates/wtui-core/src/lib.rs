@@ -1,13 +1,26 @@
+pub mod alerts;
 pub mod config;
+pub mod control;
+pub mod custom;
 pub mod db;
+pub mod forward;
 pub mod metrics;
 pub mod models;
+pub mod mqtt;
+pub mod openmetrics;
+pub mod quota;
+pub mod sources;
+pub mod stream;
+pub mod textfile;
 pub mod timeutils;
 
 pub use config::{
-    Config, DaemonConfig, DatabaseConfig, LoggingConfig, Preset, PresetKind, ViewerConfig,
+    AlertAction, AlertConfig, BatteryProfile, Config, CustomMetricConfig, DaemonConfig,
+    DatabaseConfig, ExporterConfig, ForwardConfig, ForwardTarget, LoggingConfig, MqttSinkConfig,
+    Preset, PresetKind, QuotaConfig, QuotaDirection, SinkConfig, SpoolConfig, ViewerConfig,
 };
-pub use db::{Database, MetricRow, SchemaVersion};
-pub use metrics::{MetricKind, MetricReading};
+pub use db::{Database, EventRow, MetricRow, SchemaVersion};
+pub use metrics::{HostRoots, MetricKind, MetricReading};
 pub use models::{MetricPoint, MetricSeries, RangeSpec};
+pub use sources::{resolve_metric, MetricRef, MetricSource, SourceRegistry};
 pub use timeutils::{now_utc, parse_range, utc_from_timestamp};
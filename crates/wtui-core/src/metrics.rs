@@ -1,13 +1,52 @@
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
 use std::io::{self, BufRead};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
+use std::time::{Duration, Instant};
 use time::OffsetDateTime;
 
 use crate::timeutils::now_utc;
 
+/// Where procfs and sysfs are mounted. Readers resolve every kernel path
+/// against these, so the daemon can sample a host's `/proc` bind-mounted
+/// into a container, and tests can point them at fixture trees.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HostRoots {
+    pub proc: PathBuf,
+    pub sys: PathBuf,
+}
+
+impl Default for HostRoots {
+    fn default() -> Self {
+        Self {
+            proc: PathBuf::from("/proc"),
+            sys: PathBuf::from("/sys"),
+        }
+    }
+}
+
+impl HostRoots {
+    pub fn new(proc: impl Into<PathBuf>, sys: impl Into<PathBuf>) -> Self {
+        Self {
+            proc: proc.into(),
+            sys: sys.into(),
+        }
+    }
+
+    /// `relative` under the procfs root, e.g. `proc("stat")`.
+    pub fn proc(&self, relative: &str) -> PathBuf {
+        self.proc.join(relative)
+    }
+
+    /// `relative` under the sysfs root, e.g. `sys("class/hwmon")`.
+    pub fn sys(&self, relative: &str) -> PathBuf {
+        self.sys.join(relative)
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[serde(rename_all = "lowercase")]
 pub enum MetricKind {
@@ -18,6 +57,18 @@ pub enum MetricKind {
     Temps,
     Disk,
     Power,
+    Gpu,
+    Uptime,
+    Backlight,
+    #[serde(rename = "power_profile")]
+    PowerProfile,
+    Cstates,
+    Throttle,
+    Custom,
+    Textfile,
+    /// The daemon's own health, reported by the daemon rather than read
+    /// from the system.
+    Daemon,
 }
 
 impl FromStr for MetricKind {
@@ -31,21 +82,114 @@ impl FromStr for MetricKind {
             "temps" | "temp" | "temperature" => Ok(MetricKind::Temps),
             "disk" => Ok(MetricKind::Disk),
             "power" => Ok(MetricKind::Power),
+            "gpu" => Ok(MetricKind::Gpu),
+            "uptime" => Ok(MetricKind::Uptime),
+            "backlight" => Ok(MetricKind::Backlight),
+            "power_profile" | "profile" => Ok(MetricKind::PowerProfile),
+            "cstates" | "cstate" => Ok(MetricKind::Cstates),
+            "throttle" | "throttling" => Ok(MetricKind::Throttle),
+            "custom" => Ok(MetricKind::Custom),
+            "textfile" => Ok(MetricKind::Textfile),
+            "daemon" | "self" => Ok(MetricKind::Daemon),
             _ => anyhow::bail!("unknown metric kind: {s}"),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+impl MetricKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetricKind::Cpu => "cpu",
+            MetricKind::Ram => "ram",
+            MetricKind::Net => "net",
+            MetricKind::Battery => "battery",
+            MetricKind::Temps => "temps",
+            MetricKind::Disk => "disk",
+            MetricKind::Power => "power",
+            MetricKind::Gpu => "gpu",
+            MetricKind::Uptime => "uptime",
+            MetricKind::Backlight => "backlight",
+            MetricKind::PowerProfile => "power_profile",
+            MetricKind::Cstates => "cstates",
+            MetricKind::Throttle => "throttle",
+            MetricKind::Custom => "custom",
+            MetricKind::Textfile => "textfile",
+            MetricKind::Daemon => "daemon",
+        }
+    }
+
+    /// What a reading's `label` names for this family, used as the label or
+    /// tag key when readings leave wtui.
+    pub fn instance_label(&self) -> &'static str {
+        match self {
+            MetricKind::Cpu => "cpu",
+            MetricKind::Net => "interface",
+            MetricKind::Battery => "battery",
+            MetricKind::Temps => "sensor",
+            MetricKind::Disk => "mount",
+            MetricKind::Power => "domain",
+            MetricKind::Gpu => "gpu",
+            MetricKind::Uptime => "boot_id",
+            MetricKind::Backlight => "device",
+            MetricKind::PowerProfile => "profile",
+            MetricKind::Cstates => "state",
+            MetricKind::Daemon => "component",
+            _ => "label",
+        }
+    }
+}
+
+impl std::fmt::Display for MetricKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A single value produced by a [`crate::sources::MetricSource`]. `metric`
+/// names the quantity (`cpu_usage`, `net_rx_delta`, a custom metric name...)
+/// and `label` the instance it belongs to (interface, sensor, mount...).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MetricReading {
+    #[serde(with = "time::serde::timestamp")]
     pub timestamp: OffsetDateTime,
-    pub label: String,
-    pub value: f64,
-    pub unit: &'static str,
     pub kind: MetricKind,
+    pub metric: String,
+    pub label: Option<String>,
+    pub value: f64,
+    pub unit: Option<String>,
+    /// Extra context some tables keep next to the value, such as the GPU
+    /// driver or where a power profile was read from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+impl MetricReading {
+    pub fn new(
+        timestamp: OffsetDateTime,
+        kind: MetricKind,
+        metric: &str,
+        label: Option<&str>,
+        value: f64,
+        unit: Option<&str>,
+    ) -> Self {
+        Self {
+            timestamp,
+            kind,
+            metric: metric.to_string(),
+            label: label.map(str::to_string),
+            value,
+            unit: unit.map(str::to_string),
+            detail: None,
+        }
+    }
+
+    pub fn with_detail(mut self, detail: &str) -> Self {
+        self.detail = Some(detail.to_string());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
 pub struct CpuTimes {
     pub user: u64,
     pub nice: u64,
@@ -74,20 +218,46 @@ impl CpuTimes {
     }
 }
 
-pub fn read_cpu_times() -> Result<CpuTimes> {
-    let file = fs::File::open("/proc/stat").context("opening /proc/stat")?;
+pub fn read_cpu_times(roots: &HostRoots) -> Result<CpuTimes> {
+    let path = roots.proc("stat");
+    let file = fs::File::open(&path).with_context(|| format!("opening {path:?}"))?;
     let mut lines = io::BufReader::new(file).lines();
     if let Some(Ok(first)) = lines.next() {
-        let parts: Vec<&str> = first.split_whitespace().collect();
-        if parts.len() < 8 {
-            anyhow::bail!("unexpected /proc/stat format");
+        match parse_cpu_line(&first) {
+            Some((_, times)) => Ok(times),
+            None => anyhow::bail!("unexpected {path:?} format"),
         }
-        let nums: Vec<u64> = parts[1..]
-            .iter()
-            .take(8)
-            .map(|v| v.parse::<u64>().unwrap_or(0))
-            .collect();
-        Ok(CpuTimes {
+    } else {
+        anyhow::bail!("no contents in {path:?}")
+    }
+}
+
+/// The per-core `cpuN` lines of `/proc/stat`.
+pub fn read_core_times(roots: &HostRoots) -> Result<HashMap<String, CpuTimes>> {
+    let path = roots.proc("stat");
+    let content = fs::read_to_string(&path).with_context(|| format!("reading {path:?}"))?;
+    Ok(content
+        .lines()
+        .filter(|line| line.starts_with("cpu") && !line.starts_with("cpu "))
+        .filter_map(parse_cpu_line)
+        .collect())
+}
+
+fn parse_cpu_line(line: &str) -> Option<(String, CpuTimes)> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() < 8 {
+        return None;
+    }
+    let nums: Vec<u64> = parts[1..]
+        .iter()
+        .take(8)
+        .map(|v| v.parse::<u64>().unwrap_or(0))
+        .chain(std::iter::repeat(0))
+        .take(8)
+        .collect();
+    Some((
+        parts[0].to_string(),
+        CpuTimes {
             user: nums[0],
             nice: nums[1],
             system: nums[2],
@@ -96,10 +266,8 @@ pub fn read_cpu_times() -> Result<CpuTimes> {
             irq: nums[5],
             softirq: nums[6],
             steal: nums[7],
-        })
-    } else {
-        anyhow::bail!("no contents in /proc/stat")
-    }
+        },
+    ))
 }
 
 pub fn cpu_usage_percent(prev: &CpuTimes, current: &CpuTimes) -> Option<f64> {
@@ -122,8 +290,9 @@ pub struct RamUsage {
     pub available_bytes: u64,
 }
 
-pub fn read_ram_usage() -> Result<RamUsage> {
-    let content = fs::read_to_string("/proc/meminfo").context("reading /proc/meminfo")?;
+pub fn read_ram_usage(roots: &HostRoots) -> Result<RamUsage> {
+    let path = roots.proc("meminfo");
+    let content = fs::read_to_string(&path).with_context(|| format!("reading {path:?}"))?;
     let mut total = 0u64;
     let mut available = 0u64;
     for line in content.lines() {
@@ -134,7 +303,7 @@ pub fn read_ram_usage() -> Result<RamUsage> {
         }
     }
     if total == 0 {
-        anyhow::bail!("missing MemTotal in /proc/meminfo")
+        anyhow::bail!("missing MemTotal in {path:?}")
     }
     if available == 0 {
         available = total;
@@ -160,15 +329,16 @@ pub struct NetSnapshot {
     pub tx_bytes: u64,
 }
 
-pub fn read_net_snapshot(interface: &str) -> Result<NetSnapshot> {
-    let rx_path = format!("/sys/class/net/{interface}/statistics/rx_bytes");
-    let tx_path = format!("/sys/class/net/{interface}/statistics/tx_bytes");
+pub fn read_net_snapshot(roots: &HostRoots, interface: &str) -> Result<NetSnapshot> {
+    let stats = roots.sys("class/net").join(interface).join("statistics");
+    let rx_path = stats.join("rx_bytes");
+    let tx_path = stats.join("tx_bytes");
     let rx = fs::read_to_string(&rx_path)
-        .with_context(|| format!("reading {rx_path}"))?
+        .with_context(|| format!("reading {rx_path:?}"))?
         .trim()
         .parse::<u64>()?;
     let tx = fs::read_to_string(&tx_path)
-        .with_context(|| format!("reading {tx_path}"))?
+        .with_context(|| format!("reading {tx_path:?}"))?
         .trim()
         .parse::<u64>()?;
     Ok(NetSnapshot {
@@ -199,9 +369,9 @@ pub struct TempReading {
     pub value_c: f64,
 }
 
-pub fn read_temperatures() -> Result<Vec<TempReading>> {
+pub fn read_temperatures(roots: &HostRoots) -> Result<Vec<TempReading>> {
     let mut readings = Vec::new();
-    let hwmon_root = PathBuf::from("/sys/class/hwmon");
+    let hwmon_root = roots.sys("class/hwmon");
     if !hwmon_root.exists() {
         return Ok(readings);
     }
@@ -222,7 +392,7 @@ pub fn read_temperatures() -> Result<Vec<TempReading>> {
                 if let Ok(value) = raw.parse::<f64>() {
                     let mut c = value;
                     if c > 1000.0 {
-                        c = c / 1000.0;
+                        c /= 1000.0;
                     }
                     readings.push(TempReading {
                         sensor: format!("{}:{}", name.trim(), label.trim()),
@@ -243,9 +413,9 @@ pub struct BatteryReading {
     pub energy_now_uw: Option<f64>,
 }
 
-pub fn read_batteries() -> Result<Vec<BatteryReading>> {
+pub fn read_batteries(roots: &HostRoots) -> Result<Vec<BatteryReading>> {
     let mut readings = Vec::new();
-    let base = PathBuf::from("/sys/class/power_supply");
+    let base = roots.sys("class/power_supply");
     if !base.exists() {
         return Ok(readings);
     }
@@ -271,6 +441,27 @@ pub fn read_batteries() -> Result<Vec<BatteryReading>> {
     Ok(readings)
 }
 
+/// Whether any external supply (mains adapter or USB-C charger) is online.
+/// `None` when the machine has no such supply, e.g. a desktop.
+pub fn read_ac_online(roots: &HostRoots) -> Result<Option<bool>> {
+    let base = roots.sys("class/power_supply");
+    if !base.exists() {
+        return Ok(None);
+    }
+    let mut found = None;
+    for entry in fs::read_dir(base)? {
+        let path = entry?.path();
+        let ty = fs::read_to_string(path.join("type")).unwrap_or_default();
+        if !matches!(ty.trim(), "Mains" | "USB") {
+            continue;
+        }
+        if let Some(online) = read_f64(path.join("online")) {
+            found = Some(found.unwrap_or(false) || online > 0.0);
+        }
+    }
+    Ok(found)
+}
+
 fn read_health_percent(path: &Path) -> Option<f64> {
     if let Some(value) = read_f64(path.join("health")) {
         return Some(value);
@@ -308,9 +499,9 @@ pub struct PowerReading {
     pub draw_mw: f64,
 }
 
-pub fn read_powercap() -> Result<Vec<PowerReading>> {
+pub fn read_powercap(roots: &HostRoots) -> Result<Vec<PowerReading>> {
     let mut readings = Vec::new();
-    let root = PathBuf::from("/sys/class/powercap");
+    let root = roots.sys("class/powercap");
     if !root.exists() {
         return Ok(readings);
     }
@@ -333,6 +524,513 @@ pub fn read_powercap() -> Result<Vec<PowerReading>> {
     Ok(readings)
 }
 
+#[derive(Debug, Clone, Default)]
+pub struct GpuReading {
+    pub card: String,
+    pub driver: String,
+    pub busy_percent: Option<f64>,
+    pub vram_used_bytes: Option<u64>,
+    pub vram_total_bytes: Option<u64>,
+    pub power_mw: Option<f64>,
+    pub cur_freq_mhz: Option<f64>,
+    pub act_freq_mhz: Option<f64>,
+    pub temp_c: Option<f64>,
+}
+
+/// Reads GPU statistics from DRM sysfs. amdgpu exposes utilisation, VRAM and
+/// hwmon power under `card*/device/`, while i915 exposes its GT frequencies on
+/// the card node itself.
+pub fn read_gpus(roots: &HostRoots) -> Result<Vec<GpuReading>> {
+    let mut readings = Vec::new();
+    let root = roots.sys("class/drm");
+    if !root.exists() {
+        return Ok(readings);
+    }
+
+    let mut cards: Vec<_> = fs::read_dir(root)?
+        .flatten()
+        .filter(|entry| is_drm_card(&entry.file_name().to_string_lossy()))
+        .collect();
+    cards.sort_by_key(|entry| entry.file_name());
+
+    for entry in cards {
+        let path = entry.path();
+        let device = path.join("device");
+        let driver = fs::read_link(device.join("driver"))
+            .ok()
+            .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+            .unwrap_or_default();
+        let hwmon = first_hwmon_dir(&device);
+        let reading = GpuReading {
+            card: entry.file_name().to_string_lossy().to_string(),
+            driver,
+            busy_percent: read_f64(device.join("gpu_busy_percent")),
+            vram_used_bytes: read_f64(device.join("mem_info_vram_used")).map(|v| v as u64),
+            vram_total_bytes: read_f64(device.join("mem_info_vram_total")).map(|v| v as u64),
+            power_mw: hwmon
+                .as_ref()
+                .and_then(|h| read_f64(h.join("power1_average")))
+                .map(|uw| uw / 1000.0),
+            cur_freq_mhz: read_f64(path.join("gt_cur_freq_mhz"))
+                .or_else(|| read_f64(device.join("gt_cur_freq_mhz"))),
+            act_freq_mhz: read_f64(path.join("gt_act_freq_mhz"))
+                .or_else(|| read_f64(device.join("gt_act_freq_mhz"))),
+            temp_c: hwmon
+                .as_ref()
+                .and_then(|h| read_f64(h.join("temp1_input")))
+                .map(|t| t / 1000.0),
+        };
+        let has_data = reading.busy_percent.is_some()
+            || reading.vram_used_bytes.is_some()
+            || reading.power_mw.is_some()
+            || reading.cur_freq_mhz.is_some()
+            || reading.act_freq_mhz.is_some()
+            || reading.temp_c.is_some();
+        if has_data {
+            readings.push(reading);
+        }
+    }
+    Ok(readings)
+}
+
+/// Matches `card0`, `card1`, ... but not connector nodes such as `card0-DP-1`.
+fn is_drm_card(name: &str) -> bool {
+    name.strip_prefix("card")
+        .map(|rest| !rest.is_empty() && rest.chars().all(|c| c.is_ascii_digit()))
+        .unwrap_or(false)
+}
+
+fn first_hwmon_dir(device: &Path) -> Option<PathBuf> {
+    let mut dirs: Vec<PathBuf> = fs::read_dir(device.join("hwmon"))
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .collect();
+    dirs.sort();
+    dirs.into_iter().next()
+}
+
+#[derive(Debug, Clone)]
+pub struct BacklightReading {
+    pub device: String,
+    pub brightness: u64,
+    pub max_brightness: u64,
+}
+
+impl BacklightReading {
+    pub fn percent(&self) -> Option<f64> {
+        if self.max_brightness == 0 {
+            None
+        } else {
+            Some(self.brightness as f64 / self.max_brightness as f64 * 100.0)
+        }
+    }
+}
+
+pub fn read_backlights(roots: &HostRoots) -> Result<Vec<BacklightReading>> {
+    let mut readings = Vec::new();
+    let root = roots.sys("class/backlight");
+    if !root.exists() {
+        return Ok(readings);
+    }
+
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        let path = entry.path();
+        let brightness =
+            read_f64(path.join("actual_brightness")).or_else(|| read_f64(path.join("brightness")));
+        let max = read_f64(path.join("max_brightness"));
+        if let (Some(brightness), Some(max)) = (brightness, max) {
+            readings.push(BacklightReading {
+                device: entry.file_name().to_string_lossy().to_string(),
+                brightness: brightness as u64,
+                max_brightness: max as u64,
+            });
+        }
+    }
+    Ok(readings)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PowerProfileReading {
+    pub source: String,
+    pub profile: String,
+}
+
+const PLATFORM_PROFILE_PATH: &str = "firmware/acpi/platform_profile";
+const PPD_STATE_PATH: &str = "/var/lib/power-profiles-daemon/state.ini";
+
+/// Reads the ACPI platform profile, falling back to the profile persisted by
+/// power-profiles-daemon on machines without firmware profile support.
+pub fn read_power_profile(roots: &HostRoots) -> Result<Option<PowerProfileReading>> {
+    if let Ok(profile) = fs::read_to_string(roots.sys(PLATFORM_PROFILE_PATH)) {
+        let profile = profile.trim();
+        if !profile.is_empty() {
+            return Ok(Some(PowerProfileReading {
+                source: "platform_profile".into(),
+                profile: profile.into(),
+            }));
+        }
+    }
+    match fs::read_to_string(PPD_STATE_PATH) {
+        Ok(content) => Ok(
+            parse_ppd_state(&content).map(|profile| PowerProfileReading {
+                source: "power-profiles-daemon".into(),
+                profile,
+            }),
+        ),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err).with_context(|| format!("reading {PPD_STATE_PATH}")),
+    }
+}
+
+/// Extracts `Profile=` from the `[State]` section of power-profiles-daemon's
+/// state file.
+pub fn parse_ppd_state(content: &str) -> Option<String> {
+    let mut in_state = false;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_state = line == "[State]";
+            continue;
+        }
+        if in_state {
+            if let Some(value) = line.strip_prefix("Profile=") {
+                let value = value.trim();
+                if !value.is_empty() {
+                    return Some(value.to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+/// The `cpu` of package C-state rows.
+pub const PACKAGE_CSTATES: &str = "package";
+
+/// Cumulative cpuidle counters for every core and idle state, plus package
+/// residency where the platform exposes it.
+#[derive(Debug, Clone)]
+pub struct CstateSnapshot {
+    pub taken_at: Instant,
+    /// Keyed by (cpu, state name), holding (residency time in µs, entry count).
+    /// Package states have no entry count and are kept at 0.
+    pub states: HashMap<(String, String), (u64, u64)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CstateResidency {
+    pub cpu: String,
+    pub state: String,
+    pub residency_pct: f64,
+    pub usage_delta: u64,
+}
+
+pub fn read_cstate_snapshot(roots: &HostRoots) -> Result<CstateSnapshot> {
+    let mut states = HashMap::new();
+    let root = roots.sys("devices/system/cpu");
+    let taken_at = Instant::now();
+    read_package_cstates(roots, &mut states);
+    if !root.exists() {
+        return Ok(CstateSnapshot { taken_at, states });
+    }
+
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        let cpu = entry.file_name().to_string_lossy().to_string();
+        let is_core = cpu
+            .strip_prefix("cpu")
+            .map(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+            .unwrap_or(false);
+        if !is_core {
+            continue;
+        }
+        let Ok(idle_states) = fs::read_dir(entry.path().join("cpuidle")) else {
+            continue;
+        };
+        for state in idle_states.flatten() {
+            let path = state.path();
+            let name = fs::read_to_string(path.join("name"))
+                .map(|n| n.trim().to_string())
+                .unwrap_or_else(|_| state.file_name().to_string_lossy().to_string());
+            let time = read_f64(path.join("time"));
+            let usage = read_f64(path.join("usage"));
+            if let (Some(time), Some(usage)) = (time, usage) {
+                states.insert((cpu.clone(), name), (time as u64, usage as u64));
+            }
+        }
+    }
+    Ok(CstateSnapshot { taken_at, states })
+}
+
+/// Package residency isn't part of cpuidle. On Intel, `intel_pmc_core`
+/// reports it in debugfs (readable by root only) as `C6       : <µs>` lines.
+fn read_package_cstates(roots: &HostRoots, states: &mut HashMap<(String, String), (u64, u64)>) {
+    let Ok(content) = fs::read_to_string(roots.sys("kernel/debug/pmc_core/package_cstate_show"))
+    else {
+        return;
+    };
+    for line in content.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if let Ok(us) = value.trim().parse::<u64>() {
+            states.insert((PACKAGE_CSTATES.into(), name.trim().to_string()), (us, 0));
+        }
+    }
+}
+
+/// Share of wall time each core spent in each idle state between two
+/// snapshots, plus an `all` row per state averaged across cores. That
+/// average is not package residency, which is reported as its own
+/// `package` rows when available.
+pub fn cstate_residency(prev: &CstateSnapshot, current: &CstateSnapshot) -> Vec<CstateResidency> {
+    let elapsed_us = current
+        .taken_at
+        .saturating_duration_since(prev.taken_at)
+        .as_micros() as f64;
+    let mut result = Vec::new();
+    if elapsed_us <= 0.0 {
+        return result;
+    }
+
+    let mut totals: HashMap<String, (f64, u64, usize)> = HashMap::new();
+    for ((cpu, state), (time, usage)) in &current.states {
+        let Some((prev_time, prev_usage)) = prev.states.get(&(cpu.clone(), state.clone())) else {
+            continue;
+        };
+        let (Some(time_delta), Some(usage_delta)) =
+            (time.checked_sub(*prev_time), usage.checked_sub(*prev_usage))
+        else {
+            continue;
+        };
+        let residency_pct = (time_delta as f64 / elapsed_us * 100.0).min(100.0);
+        result.push(CstateResidency {
+            cpu: cpu.clone(),
+            state: state.clone(),
+            residency_pct,
+            usage_delta,
+        });
+        if cpu == PACKAGE_CSTATES {
+            continue;
+        }
+        let total = totals.entry(state.clone()).or_insert((0.0, 0, 0));
+        total.0 += residency_pct;
+        total.1 += usage_delta;
+        total.2 += 1;
+    }
+    for (state, (pct_sum, usage_sum, cores)) in totals {
+        result.push(CstateResidency {
+            cpu: "all".into(),
+            state,
+            residency_pct: pct_sum / cores as f64,
+            usage_delta: usage_sum,
+        });
+    }
+    result.sort_by(|a, b| (&a.cpu, &a.state).cmp(&(&b.cpu, &b.state)));
+    result
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ThrottleCounters {
+    pub core_count: u64,
+    pub package_count: u64,
+    pub cur_freq_khz: Option<f64>,
+    pub max_freq_khz: Option<f64>,
+    /// The core's `/proc/stat` times, to tell a busy core from an idle one.
+    pub times: Option<CpuTimes>,
+}
+
+pub type ThrottleSnapshot = HashMap<String, ThrottleCounters>;
+
+/// Reads the per-core thermal throttle counters (Intel) together with the
+/// current and maximum cpufreq frequency (all vendors) and CPU times.
+pub fn read_throttle_snapshot(roots: &HostRoots) -> Result<ThrottleSnapshot> {
+    let mut snapshot = HashMap::new();
+    let root = roots.sys("devices/system/cpu");
+    if !root.exists() {
+        return Ok(snapshot);
+    }
+    let times = read_core_times(roots).unwrap_or_default();
+
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        let cpu = entry.file_name().to_string_lossy().to_string();
+        let is_core = cpu
+            .strip_prefix("cpu")
+            .map(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+            .unwrap_or(false);
+        if !is_core {
+            continue;
+        }
+        let path = entry.path();
+        let throttle = path.join("thermal_throttle");
+        let cpufreq = path.join("cpufreq");
+        let counters = ThrottleCounters {
+            core_count: read_f64(throttle.join("core_throttle_count")).unwrap_or(0.0) as u64,
+            package_count: read_f64(throttle.join("package_throttle_count")).unwrap_or(0.0) as u64,
+            cur_freq_khz: read_f64(cpufreq.join("scaling_cur_freq")),
+            max_freq_khz: read_f64(cpufreq.join("cpuinfo_max_freq")),
+            times: times.get(&cpu).copied(),
+        };
+        snapshot.insert(cpu, counters);
+    }
+    Ok(snapshot)
+}
+
+/// A busy core whose clock sits below this share of its maximum while the
+/// package is hot is treated as throttled even without throttle counters
+/// (e.g. AMD).
+const THROTTLE_FREQ_RATIO: f64 = 0.6;
+
+/// How busy a core has to have been between two snapshots for a low clock to
+/// count; idle cores sit at low clocks under schedutil or powersave anyway.
+const THROTTLE_BUSY_PERCENT: f64 = 50.0;
+
+/// Cores that throttled between two snapshots: either their core/package
+/// throttle counter advanced, or they were busy but running well below their
+/// maximum clock while the hottest CPU sensor is at or above `hot_temp_c`.
+pub fn throttled_cores(
+    prev: &ThrottleSnapshot,
+    current: &ThrottleSnapshot,
+    cpu_temp_c: Option<f64>,
+    hot_temp_c: f64,
+) -> Vec<String> {
+    let hot = cpu_temp_c.map(|t| t >= hot_temp_c).unwrap_or(false);
+    let mut cores: Vec<String> = current
+        .iter()
+        .filter(|(cpu, counters)| {
+            let prev = prev.get(*cpu);
+            let counted = prev
+                .map(|p| {
+                    counters.core_count > p.core_count || counters.package_count > p.package_count
+                })
+                .unwrap_or(false);
+            let slowed = match (counters.cur_freq_khz, counters.max_freq_khz) {
+                (Some(cur), Some(max)) if max > 0.0 => cur / max < THROTTLE_FREQ_RATIO,
+                _ => false,
+            };
+            let busy = match (prev.and_then(|p| p.times), counters.times) {
+                (Some(prev), Some(current)) => cpu_usage_percent(&prev, &current)
+                    .is_some_and(|usage| usage >= THROTTLE_BUSY_PERCENT),
+                _ => false,
+            };
+            counted || (hot && slowed && busy)
+        })
+        .map(|(cpu, _)| cpu.clone())
+        .collect();
+    cores.sort();
+    cores
+}
+
+/// Hottest CPU package/core sensor, preferring CPU hwmon drivers over
+/// everything else so a hot GPU or NVMe drive doesn't count.
+pub fn hottest_cpu_temp(temps: &[TempReading]) -> Option<f64> {
+    const CPU_SENSORS: [&str; 4] = ["coretemp", "k10temp", "zenpower", "cpu_thermal"];
+    let cpu_max = temps
+        .iter()
+        .filter(|t| CPU_SENSORS.iter().any(|s| t.sensor.starts_with(s)))
+        .map(|t| t.value_c)
+        .fold(None, |acc: Option<f64>, v| {
+            Some(acc.map_or(v, |a| a.max(v)))
+        });
+    cpu_max.or_else(|| {
+        temps
+            .iter()
+            .map(|t| t.value_c)
+            .fold(None, |acc: Option<f64>, v| {
+                Some(acc.map_or(v, |a| a.max(v)))
+            })
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThrottleEpisode {
+    #[serde(with = "time::serde::timestamp")]
+    pub start: OffsetDateTime,
+    #[serde(with = "time::serde::timestamp")]
+    pub end: OffsetDateTime,
+    pub peak_temp_c: Option<f64>,
+    pub cores: BTreeSet<String>,
+}
+
+/// Folds per-cycle throttle observations into episodes; an episode ends on the
+/// first cycle without any throttled core.
+#[derive(Debug, Default)]
+pub struct ThrottleTracker {
+    active: Option<ThrottleEpisode>,
+}
+
+impl ThrottleTracker {
+    pub fn update(
+        &mut self,
+        now: OffsetDateTime,
+        cores: &[String],
+        cpu_temp_c: Option<f64>,
+    ) -> Option<ThrottleEpisode> {
+        if cores.is_empty() {
+            return self.active.take();
+        }
+        let episode = self.active.get_or_insert_with(|| ThrottleEpisode {
+            start: now,
+            end: now,
+            peak_temp_c: None,
+            cores: BTreeSet::new(),
+        });
+        episode.end = now;
+        episode.cores.extend(cores.iter().cloned());
+        if let Some(t) = cpu_temp_c {
+            episode.peak_temp_c = Some(episode.peak_temp_c.map_or(t, |p| p.max(t)));
+        }
+        None
+    }
+
+    /// Closes any episode still in progress, e.g. on shutdown.
+    pub fn finish(&mut self) -> Option<ThrottleEpisode> {
+        self.active.take()
+    }
+}
+
+/// CLOCK_BOOTTIME keeps counting while the machine is suspended whereas
+/// CLOCK_MONOTONIC does not, so the difference between two snapshots tells how
+/// long the system slept in between.
+#[derive(Debug, Clone, Copy)]
+pub struct ClockSnapshot {
+    pub boottime: Duration,
+    pub monotonic: Duration,
+}
+
+impl ClockSnapshot {
+    pub fn suspended_since(&self, prev: &ClockSnapshot) -> Duration {
+        let boot_delta = self.boottime.saturating_sub(prev.boottime);
+        let mono_delta = self.monotonic.saturating_sub(prev.monotonic);
+        boot_delta.saturating_sub(mono_delta)
+    }
+}
+
+pub fn read_clocks() -> Result<ClockSnapshot> {
+    use nix::time::{clock_gettime, ClockId};
+    let boot = clock_gettime(ClockId::CLOCK_BOOTTIME).context("reading CLOCK_BOOTTIME")?;
+    let mono = clock_gettime(ClockId::CLOCK_MONOTONIC).context("reading CLOCK_MONOTONIC")?;
+    Ok(ClockSnapshot {
+        boottime: Duration::from(boot),
+        monotonic: Duration::from(mono),
+    })
+}
+
+pub fn read_boot_id(roots: &HostRoots) -> Result<String> {
+    let path = roots.proc("sys/kernel/random/boot_id");
+    let id = fs::read_to_string(&path).with_context(|| format!("reading {path:?}"))?;
+    Ok(id.trim().to_string())
+}
+
+pub fn read_hostname(roots: &HostRoots) -> Result<String> {
+    let path = roots.proc("sys/kernel/hostname");
+    let name = fs::read_to_string(&path).with_context(|| format!("reading {path:?}"))?;
+    Ok(name.trim().to_string())
+}
+
 pub fn now() -> OffsetDateTime {
     now_utc()
 }
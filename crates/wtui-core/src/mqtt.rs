@@ -0,0 +1,139 @@
+use crate::metrics::{MetricKind, MetricReading};
+use anyhow::{Context, Result};
+use serde_json::json;
+
+/// Splits an `[mqtt]` broker into host and port (default 1883). IPv6
+/// addresses take a port only in brackets, `[::1]:1883`; a bare `::1` is
+/// all host.
+pub fn broker_address(broker: &str) -> Result<(String, u16)> {
+    let parse_port = |port: &str| {
+        port.parse()
+            .with_context(|| format!("invalid port in broker {broker:?}"))
+    };
+    if let Some(rest) = broker.strip_prefix('[') {
+        let (host, after) = rest
+            .split_once(']')
+            .with_context(|| format!("unclosed bracket in broker {broker:?}"))?;
+        let port = match after {
+            "" => 1883,
+            _ => match after.strip_prefix(':') {
+                Some(port) => parse_port(port)?,
+                None => anyhow::bail!("unexpected {after:?} after host in broker {broker:?}"),
+            },
+        };
+        return Ok((host.to_string(), port));
+    }
+    match broker.split_once(':') {
+        Some((host, port)) if !port.contains(':') => Ok((host.to_string(), parse_port(port)?)),
+        _ => Ok((broker.to_string(), 1883)),
+    }
+}
+
+/// Where a reading's current value is published:
+/// `<prefix>/<host>/<family>/<metric>[/<instance>]`.
+pub fn state_topic(prefix: &str, host: &str, reading: &MetricReading) -> String {
+    let mut topic = format!(
+        "{prefix}/{}/{}/{}",
+        topic_level(host),
+        reading.kind,
+        topic_level(&reading.metric)
+    );
+    if let Some(label) = &reading.label {
+        topic.push('/');
+        topic.push_str(&topic_level(label));
+    }
+    topic
+}
+
+/// Retained online/offline status of one host.
+pub fn availability_topic(prefix: &str, host: &str) -> String {
+    format!("{prefix}/{}/status", topic_level(host))
+}
+
+/// A Home Assistant MQTT discovery message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Discovery {
+    /// Identifies the sensor; announce each one once per connection.
+    pub object_id: String,
+    pub topic: String,
+    pub payload: String,
+}
+
+/// The discovery config making `reading` a Home Assistant sensor, for the
+/// families worth a dashboard tile: battery charge and health, temperatures
+/// and disk usage. `None` for everything else.
+pub fn discovery(
+    reading: &MetricReading,
+    discovery_prefix: &str,
+    topic_prefix: &str,
+    host: &str,
+) -> Option<Discovery> {
+    let (device_class, unit, what) = match (reading.kind, reading.metric.as_str()) {
+        (MetricKind::Battery, "battery_capacity") => (Some("battery"), "%", "battery"),
+        (MetricKind::Battery, "battery_health") => (None, "%", "battery health"),
+        (MetricKind::Temps, "temp") => (Some("temperature"), "°C", "temperature"),
+        (MetricKind::Disk, "disk_usage") => (None, "%", "disk usage"),
+        _ => return None,
+    };
+    let node = format!("wtui_{}", object_part(host));
+    let object_id = match &reading.label {
+        Some(label) => format!("{}_{}", object_part(&reading.metric), object_part(label)),
+        None => object_part(&reading.metric),
+    };
+    let name = match &reading.label {
+        Some(label) => format!("{label} {what}"),
+        None => what.to_string(),
+    };
+    let mut config = json!({
+        "name": name,
+        "unique_id": format!("{node}_{object_id}"),
+        "object_id": format!("{node}_{object_id}"),
+        "state_topic": state_topic(topic_prefix, host, reading),
+        "availability_topic": availability_topic(topic_prefix, host),
+        "unit_of_measurement": unit,
+        "state_class": "measurement",
+        "device": {
+            "identifiers": [node],
+            "name": host,
+            "manufacturer": "wtui",
+        },
+    });
+    if let Some(class) = device_class {
+        config["device_class"] = json!(class);
+    }
+    Some(Discovery {
+        topic: format!("{discovery_prefix}/sensor/{node}/{object_id}/config"),
+        object_id,
+        payload: config.to_string(),
+    })
+}
+
+/// A single topic level: no separators or wildcards. Mount points lose
+/// their slashes, so `/` becomes `root` and `/home` becomes `home`.
+fn topic_level(value: &str) -> String {
+    let trimmed = value.trim_matches('/');
+    if trimmed.is_empty() {
+        return "root".into();
+    }
+    trimmed
+        .chars()
+        .map(|c| match c {
+            '/' | '+' | '#' | ' ' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Home Assistant ids allow only `[a-zA-Z0-9_-]`.
+fn object_part(value: &str) -> String {
+    topic_level(value)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
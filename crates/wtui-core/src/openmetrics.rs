@@ -0,0 +1,311 @@
+use crate::control::DaemonStatus;
+use crate::metrics::{MetricKind, MetricReading};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Content type of [`Exposition::render`].
+pub const CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// Readings that are cumulative counters; everything else is a gauge.
+const COUNTERS: &[&str] = &[
+    "net_rx_bytes",
+    "net_tx_bytes",
+    "daemon_read_errors",
+    "daemon_write_errors",
+    "daemon_sink_dropped",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FamilyType {
+    Gauge,
+    Counter,
+}
+
+impl FamilyType {
+    fn as_str(self) -> &'static str {
+        match self {
+            FamilyType::Gauge => "gauge",
+            FamilyType::Counter => "counter",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Family {
+    kind: FamilyType,
+    unit: Option<&'static str>,
+    help: String,
+    /// Rendered label set (without braces) to value.
+    samples: BTreeMap<String, f64>,
+}
+
+/// One scrape's worth of metric families in the OpenMetrics text format.
+#[derive(Debug, Default)]
+pub struct Exposition {
+    families: BTreeMap<String, Family>,
+}
+
+impl Exposition {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a reading as `wtui_<metric>[_<unit>]`, labelled after what its
+    /// family measures (`interface`, `sensor`, `mount`...). Textfile
+    /// readings keep the name and labels they were written with.
+    pub fn push_reading(&mut self, reading: &MetricReading) {
+        if reading.kind == MetricKind::Textfile {
+            let help = "from the textfile collector".to_string();
+            let labels = reading.label.clone().unwrap_or_default();
+            self.insert(
+                sanitize(&reading.metric),
+                FamilyType::Gauge,
+                None,
+                help,
+                labels,
+                reading.value,
+            );
+            return;
+        }
+        let (unit, scale) = base_unit(reading.unit.as_deref());
+        let mut name = format!("wtui_{}", sanitize(&reading.metric));
+        if let Some(unit) = unit.filter(|u| !name.ends_with(&format!("_{u}"))) {
+            name = format!("{name}_{unit}");
+        }
+        let kind = if COUNTERS.contains(&reading.metric.as_str()) {
+            FamilyType::Counter
+        } else {
+            FamilyType::Gauge
+        };
+        let labels = reading
+            .label
+            .as_deref()
+            .map(|value| label(reading.kind.instance_label(), value))
+            .unwrap_or_default();
+        let help = format!("{} from the {} source", reading.metric, reading.kind);
+        self.insert(name, kind, unit, help, labels, reading.value * scale);
+    }
+
+    /// Adds the daemon's own health: uptime, the last cycle and whether each
+    /// source's latest read succeeded.
+    pub fn push_status(&mut self, status: &DaemonStatus) {
+        self.gauge(
+            "wtui_daemon_start_time_seconds",
+            "when the daemon started",
+            Some("seconds"),
+            String::new(),
+            status.started_at.unix_timestamp() as f64,
+        );
+        self.gauge(
+            "wtui_daemon_paused",
+            "1 while collection is paused",
+            None,
+            String::new(),
+            status.paused as u8 as f64,
+        );
+        if let Some(on_battery) = status.on_battery {
+            self.gauge(
+                "wtui_daemon_on_battery",
+                "1 while the AC adapter is offline",
+                None,
+                String::new(),
+                on_battery as u8 as f64,
+            );
+        }
+        if let Some(at) = status.last_cycle {
+            self.gauge(
+                "wtui_daemon_last_cycle_timestamp_seconds",
+                "when the last collection cycle ran",
+                Some("seconds"),
+                String::new(),
+                at.unix_timestamp() as f64,
+            );
+        }
+        if let Some(secs) = status.last_cycle_secs {
+            self.gauge(
+                "wtui_daemon_last_cycle_duration_seconds",
+                "how long the last collection cycle took",
+                Some("seconds"),
+                String::new(),
+                secs,
+            );
+        }
+        for source in &status.sources {
+            let labels = label("source", &source.name);
+            self.gauge(
+                "wtui_source_up",
+                "1 if the source's latest read succeeded",
+                None,
+                labels.clone(),
+                source.is_healthy() as u8 as f64,
+            );
+            self.gauge(
+                "wtui_source_failures",
+                "consecutive failed reads",
+                None,
+                labels.clone(),
+                source.failures as f64,
+            );
+            if let Some(at) = source.last_ok {
+                self.gauge(
+                    "wtui_source_last_success_timestamp_seconds",
+                    "when the source was last read successfully",
+                    Some("seconds"),
+                    labels,
+                    at.unix_timestamp() as f64,
+                );
+            }
+        }
+        for sink in &status.sinks {
+            self.gauge(
+                "wtui_sink_up",
+                "1 if the sink's latest write succeeded",
+                None,
+                label("sink", &sink.name),
+                sink.is_healthy() as u8 as f64,
+            );
+            if let Some(backlog) = &sink.backlog {
+                let labels = label("sink", &sink.name);
+                self.gauge(
+                    "wtui_sink_backlog_readings",
+                    "readings waiting for the sink's destination to recover",
+                    None,
+                    labels.clone(),
+                    backlog.queued as f64,
+                );
+                self.gauge(
+                    "wtui_sink_spool_bytes",
+                    "size of the sink's spool file",
+                    Some("bytes"),
+                    labels.clone(),
+                    backlog.spooled_bytes as f64,
+                );
+                self.insert(
+                    "wtui_sink_dropped_readings".into(),
+                    FamilyType::Counter,
+                    None,
+                    "readings dropped because the backlog was full".into(),
+                    labels,
+                    backlog.dropped as f64,
+                );
+            }
+        }
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for (name, family) in &self.families {
+            let _ = writeln!(out, "# TYPE {name} {}", family.kind.as_str());
+            if let Some(unit) = family.unit {
+                let _ = writeln!(out, "# UNIT {name} {unit}");
+            }
+            let _ = writeln!(out, "# HELP {name} {}", escape(&family.help));
+            let sample = match family.kind {
+                FamilyType::Gauge => name.clone(),
+                FamilyType::Counter => format!("{name}_total"),
+            };
+            for (labels, value) in &family.samples {
+                if labels.is_empty() {
+                    let _ = writeln!(out, "{sample} {}", number(*value));
+                } else {
+                    let _ = writeln!(out, "{sample}{{{labels}}} {}", number(*value));
+                }
+            }
+        }
+        out.push_str("# EOF\n");
+        out
+    }
+
+    fn gauge(
+        &mut self,
+        name: &str,
+        help: &str,
+        unit: Option<&'static str>,
+        labels: String,
+        value: f64,
+    ) {
+        self.insert(
+            name.to_string(),
+            FamilyType::Gauge,
+            unit,
+            help.to_string(),
+            labels,
+            value,
+        );
+    }
+
+    fn insert(
+        &mut self,
+        name: String,
+        kind: FamilyType,
+        unit: Option<&'static str>,
+        help: String,
+        labels: String,
+        value: f64,
+    ) {
+        let family = self.families.entry(name).or_insert_with(|| Family {
+            kind,
+            unit,
+            help,
+            samples: BTreeMap::new(),
+        });
+        // A family has one type and unit; a clashing reading (say a custom
+        // metric named like a builtin one) is dropped rather than corrupting it.
+        if family.kind == kind && family.unit == unit {
+            family.samples.insert(labels, value);
+        }
+    }
+}
+
+/// Maps a reading's unit to an OpenMetrics base unit and the factor to
+/// convert into it.
+fn base_unit(unit: Option<&str>) -> (Option<&'static str>, f64) {
+    match unit {
+        Some("%") => (Some("percent"), 1.0),
+        Some("C") => (Some("celsius"), 1.0),
+        Some("mW") => (Some("watts"), 0.001),
+        Some("W") => (Some("watts"), 1.0),
+        Some("B") => (Some("bytes"), 1.0),
+        Some("s") => (Some("seconds"), 1.0),
+        Some("ms") => (Some("seconds"), 0.001),
+        _ => (None, 1.0),
+    }
+}
+
+fn label(name: &str, value: &str) -> String {
+    format!("{name}=\"{}\"", escape(value))
+}
+
+fn sanitize(name: &str) -> String {
+    let mut out: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == ':' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if out.starts_with(|c: char| c.is_ascii_digit()) {
+        out.insert(0, '_');
+    }
+    out
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn number(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".into()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.into()
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,160 @@
+use crate::config::{QuotaConfig, QuotaDirection};
+use crate::db::Database;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Deserializer};
+use time::{Date, Month, OffsetDateTime};
+
+/// Parses a byte count: a plain number, or one with a decimal (`kB`, `MB`,
+/// `GB`, `TB`) or binary (`KiB`, `MiB`, `GiB`, `TiB`) suffix.
+pub fn parse_bytes(spec: &str) -> Result<u64> {
+    let spec = spec.trim();
+    let split = spec
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(spec.len());
+    let (number, unit) = spec.split_at(split);
+    let number: f64 = number
+        .parse()
+        .with_context(|| format!("invalid size {spec:?}"))?;
+    let scale: f64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "k" | "kb" => 1e3,
+        "m" | "mb" => 1e6,
+        "g" | "gb" => 1e9,
+        "t" | "tb" => 1e12,
+        "ki" | "kib" => 1024.0,
+        "mi" | "mib" => 1024.0 * 1024.0,
+        "gi" | "gib" => 1024.0 * 1024.0 * 1024.0,
+        "ti" | "tib" => 1024.0 * 1024.0 * 1024.0 * 1024.0,
+        other => anyhow::bail!("unknown size unit {other:?} in {spec:?}"),
+    };
+    Ok((number * scale).round() as u64)
+}
+
+pub(crate) fn deserialize_bytes<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(u64),
+        Text(String),
+    }
+    match Size::deserialize(deserializer)? {
+        Size::Bytes(bytes) => Ok(bytes),
+        Size::Text(text) => parse_bytes(&text).map_err(serde::de::Error::custom),
+    }
+}
+
+/// `1.5 GiB`-style sizes for humans.
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit + 1 < UNITS.len() {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+/// The billing cycle containing `now`: from midnight UTC on the reset day
+/// up to the next one.
+pub fn billing_period(now: OffsetDateTime, reset_day: u8) -> (OffsetDateTime, OffsetDateTime) {
+    let (year, month) = (now.year(), now.month());
+    let this_month = reset_in(year, month, reset_day);
+    if now >= this_month {
+        let (year, month) = if month == Month::December {
+            (year + 1, Month::January)
+        } else {
+            (year, month.next())
+        };
+        (this_month, reset_in(year, month, reset_day))
+    } else {
+        let (year, month) = if month == Month::January {
+            (year - 1, Month::December)
+        } else {
+            (year, month.previous())
+        };
+        (reset_in(year, month, reset_day), this_month)
+    }
+}
+
+fn reset_in(year: i32, month: Month, reset_day: u8) -> OffsetDateTime {
+    let day = reset_day.clamp(1, month.length(year));
+    Date::from_calendar_date(year, month, day)
+        .expect("day clamped to the month")
+        .midnight()
+        .assume_utc()
+}
+
+/// Where one quota stands within its current billing cycle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotaUsage {
+    pub interface: String,
+    pub used: u64,
+    pub limit: u64,
+    pub period_start: OffsetDateTime,
+    pub period_end: OffsetDateTime,
+    pub at: OffsetDateTime,
+}
+
+impl QuotaUsage {
+    pub fn new(quota: &QuotaConfig, (rx, tx): (u64, u64), at: OffsetDateTime) -> Self {
+        let (period_start, period_end) = billing_period(at, quota.reset_day);
+        let used = match quota.direction {
+            QuotaDirection::Total => rx + tx,
+            QuotaDirection::Rx => rx,
+            QuotaDirection::Tx => tx,
+        };
+        Self {
+            interface: quota.interface.clone(),
+            used,
+            limit: quota.limit,
+            period_start,
+            period_end,
+            at,
+        }
+    }
+
+    pub fn percent(&self) -> f64 {
+        if self.limit == 0 {
+            return 0.0;
+        }
+        self.used as f64 / self.limit as f64 * 100.0
+    }
+
+    pub fn remaining(&self) -> u64 {
+        self.limit.saturating_sub(self.used)
+    }
+
+    /// The total at the end of the cycle if traffic keeps its average rate
+    /// so far.
+    pub fn projected(&self) -> u64 {
+        let elapsed = (self.at - self.period_start).as_seconds_f64();
+        let length = (self.period_end - self.period_start).as_seconds_f64();
+        if elapsed <= 0.0 {
+            return self.used;
+        }
+        (self.used as f64 * length / elapsed) as u64
+    }
+
+    /// The highest of `warn_at` the usage has reached, if any.
+    pub fn threshold_reached(&self, warn_at: &[f64]) -> Option<f64> {
+        let percent = self.percent();
+        warn_at
+            .iter()
+            .copied()
+            .filter(|threshold| percent >= *threshold)
+            .reduce(f64::max)
+    }
+}
+
+pub fn quota_usage(db: &Database, quota: &QuotaConfig, now: OffsetDateTime) -> Result<QuotaUsage> {
+    let (start, _) = billing_period(now, quota.reset_day);
+    let totals = db.net_usage(&quota.interface, start)?;
+    Ok(QuotaUsage::new(quota, totals, now))
+}
@@ -0,0 +1,1200 @@
+use crate::config::{Config, CustomMetricConfig};
+use crate::custom::run_custom_metric;
+use crate::db::{Database, EventRow};
+use crate::metrics::{
+    cpu_usage_percent, cstate_residency, hottest_cpu_temp, read_backlights, read_batteries,
+    read_boot_id, read_clocks, read_cpu_times, read_cstate_snapshot, read_disk_usage, read_gpus,
+    read_net_snapshot, read_power_profile, read_powercap, read_ram_usage, read_temperatures,
+    read_throttle_snapshot, throttled_cores, CpuTimes, CstateSnapshot, HostRoots, MetricKind,
+    MetricReading, NetSnapshot, ThrottleEpisode, ThrottleSnapshot, ThrottleTracker,
+    PACKAGE_CSTATES,
+};
+use crate::textfile::read_textfile_dir;
+use crate::timeutils::{align_up, duration_from_std, duration_to_std, now_utc};
+use anyhow::Result;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+
+/// A collector for one metric family. Sources keep whatever state they need
+/// between calls (previous counters for deltas, episode trackers...), so the
+/// same instance must be read on every cycle.
+pub trait MetricSource: Send {
+    /// Stable identifier, e.g. `cpu` or `custom:mail_queue`.
+    fn name(&self) -> &str;
+
+    fn kind(&self) -> MetricKind;
+
+    /// Takes one reading. Delta-based sources return nothing on their first
+    /// call, while they only have a baseline.
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>>;
+
+    /// Whether the first `read` only establishes a baseline for deltas, so
+    /// a one-shot collection has to read twice.
+    fn needs_baseline(&self) -> bool {
+        false
+    }
+
+    /// Picks up the baseline a previous run stored, so a one-shot
+    /// collection's deltas cover the time since that run rather than since
+    /// its own baseline read. Anything stored before `boot_started` (or at
+    /// all, when the boot is unknown) is from an earlier boot.
+    fn resume(&mut self, _db: &Database, _boot_started: Option<OffsetDateTime>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Drains events (e.g. finished throttle episodes) produced by `read`.
+    fn take_events(&mut self) -> Vec<EventRow> {
+        Vec::new()
+    }
+
+    /// Called once on shutdown so sources can close anything still open; the
+    /// result is collected through `take_events`.
+    fn finish(&mut self) {}
+}
+
+/// The set of sources enabled by a config, each with its own sampling
+/// interval. The daemon and the viewer's live mode both collect through it,
+/// so a family only needs one implementation.
+#[derive(Default)]
+pub struct SourceRegistry {
+    entries: Vec<Scheduled>,
+}
+
+struct Scheduled {
+    source: Box<dyn MetricSource>,
+    interval: Duration,
+    /// Monotonic deadline of the next read; `None` until the first read,
+    /// which happens on the first pass.
+    next_due: Option<Instant>,
+    /// Wall-clock time `next_due` stands for, a multiple of `interval`.
+    slot: Option<OffsetDateTime>,
+}
+
+impl Scheduled {
+    /// Whether the source should be read at `at` (wall clock `wall`); if so,
+    /// books its next slot and returns the timestamp for this read.
+    ///
+    /// Slots sit on wall-clock multiples of the interval (:00, :30, ...) but
+    /// are waited for on the monotonic clock, so cycle cost never
+    /// accumulates. Slots that passed while we were busy or suspended are
+    /// skipped and logged rather than read in a burst.
+    fn take_due(&mut self, at: Instant, wall: OffsetDateTime) -> Option<OffsetDateTime> {
+        if self.next_due.is_some_and(|due| due > at) {
+            return None;
+        }
+        if self.interval.is_zero() {
+            self.next_due = Some(at);
+            return Some(wall);
+        }
+        let interval = duration_from_std(self.interval);
+        let (stamp, mut next) = match self.slot {
+            None => (wall, align_up(wall, self.interval)),
+            Some(slot) => (slot, slot + interval),
+        };
+        let mut stamp = stamp;
+        if next - wall > interval + interval / 2 {
+            // The wall clock stepped backwards; re-anchor on it.
+            next = align_up(wall, self.interval);
+        } else if next <= wall {
+            let missed = (wall - next).whole_nanoseconds() / interval.whole_nanoseconds() + 1;
+            tracing::warn!("{}: missed {missed} tick(s)", self.source.name());
+            next = align_up(wall, self.interval);
+            stamp = next - interval;
+        }
+        self.slot = Some(next);
+        self.next_due = Some(at + duration_to_std(next - wall));
+        Some(stamp)
+    }
+}
+
+impl SourceRegistry {
+    /// Sources for `daemon.metrics` at their configured intervals, plus every
+    /// `[[daemon.custom]]` entry and the textfile directory when one is
+    /// configured.
+    pub fn from_config(config: &Config) -> Self {
+        let daemon = &config.daemon;
+        let mut registry = Self::default();
+        for kind in &daemon.metrics {
+            if registry.entries.iter().any(|e| e.source.kind() == *kind) {
+                continue;
+            }
+            if let Some(source) = builtin_source(*kind, config) {
+                registry.push(source, daemon.interval_for(*kind));
+            }
+        }
+        for custom in &daemon.custom {
+            let interval = custom.interval.unwrap_or(daemon.interval);
+            registry.push(Box::new(CustomSource::new(custom.clone())), interval);
+        }
+        if let Some(dir) = &daemon.textfile_dir {
+            registry.push(
+                Box::new(TextfileSource::new(dir.clone())),
+                daemon.interval_for(MetricKind::Textfile),
+            );
+        }
+        registry
+    }
+
+    /// Sources for the given built-in families only, due on every pass, for
+    /// live views that poll at their own pace.
+    pub fn for_kinds(config: &Config, kinds: &[MetricKind]) -> Self {
+        let mut registry = Self::default();
+        for kind in kinds {
+            if registry.entries.iter().any(|e| e.source.kind() == *kind) {
+                continue;
+            }
+            if let Some(source) = builtin_source(*kind, config) {
+                registry.push(source, Duration::ZERO);
+            }
+        }
+        registry
+    }
+
+    pub fn push(&mut self, source: Box<dyn MetricSource>, interval: Duration) {
+        self.entries.push(Scheduled {
+            source,
+            interval,
+            next_due: None,
+            slot: None,
+        });
+    }
+
+    /// Replaces each source with what `wrap` makes of it, keeping its
+    /// schedule.
+    pub fn map_sources(
+        &mut self,
+        mut wrap: impl FnMut(Box<dyn MetricSource>) -> Box<dyn MetricSource>,
+    ) {
+        self.entries = std::mem::take(&mut self.entries)
+            .into_iter()
+            .map(|entry| Scheduled {
+                source: wrap(entry.source),
+                ..entry
+            })
+            .collect();
+    }
+
+    /// Switches to the sources and intervals of `next`, e.g. a registry built
+    /// for the other power profile, while keeping the sources this one
+    /// already has so delta-based ones don't lose their baseline. A kept
+    /// source whose interval changed is read on the next pass and realigns
+    /// after that. Returns the events of the sources `next` leaves out.
+    pub fn reschedule(&mut self, next: SourceRegistry) -> Vec<EventRow> {
+        let mut current = std::mem::take(&mut self.entries);
+        for entry in next.entries {
+            let kept = current
+                .iter()
+                .position(|e| e.source.name() == entry.source.name())
+                .map(|i| current.swap_remove(i));
+            self.entries.push(match kept {
+                Some(kept) if kept.interval == entry.interval => kept,
+                Some(kept) => Scheduled {
+                    interval: entry.interval,
+                    next_due: None,
+                    slot: None,
+                    ..kept
+                },
+                None => entry,
+            });
+        }
+        let mut events = Vec::new();
+        for mut dropped in current {
+            dropped.source.finish();
+            events.extend(dropped.source.take_events());
+        }
+        events
+    }
+
+    pub fn sources_mut(&mut self) -> impl Iterator<Item = &mut Box<dyn MetricSource>> {
+        self.entries.iter_mut().map(|e| &mut e.source)
+    }
+
+    /// Sources due at `at`, each with the aligned timestamp its readings
+    /// should carry; their next slots are already booked.
+    pub fn due_mut(
+        &mut self,
+        at: Instant,
+        wall: OffsetDateTime,
+    ) -> Vec<(OffsetDateTime, &mut Box<dyn MetricSource>)> {
+        self.entries
+            .iter_mut()
+            .filter_map(|e| e.take_due(at, wall).map(|stamp| (stamp, &mut e.source)))
+            .collect()
+    }
+
+    /// Makes every source due on the next pass, e.g. for an on-demand sample.
+    /// Later slots realign to the wall clock as usual.
+    pub fn mark_all_due(&mut self) {
+        for entry in &mut self.entries {
+            entry.next_due = None;
+            entry.slot = None;
+        }
+    }
+
+    /// Earliest instant at which some source becomes due; `None` when empty.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.entries
+            .iter()
+            .map(|e| e.next_due.unwrap_or_else(Instant::now))
+            .min()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|e| e.source.name().to_string())
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Reads every source once, due or not. Failing sources don't stop the
+    /// others; their errors are returned alongside the readings.
+    pub fn collect(&mut self, now: OffsetDateTime) -> Collected {
+        let mut collected = Collected::default();
+        for source in self.sources_mut() {
+            collected.read(source.as_mut(), now);
+        }
+        collected
+    }
+
+    /// Lets each source pick up its baseline from `db`; returns the sources
+    /// that failed to.
+    pub fn resume(
+        &mut self,
+        db: &Database,
+        boot_started: Option<OffsetDateTime>,
+    ) -> Vec<(String, anyhow::Error)> {
+        let mut errors = Vec::new();
+        for source in self.sources_mut() {
+            if let Err(err) = source.resume(db, boot_started) {
+                errors.push((source.name().to_string(), err));
+            }
+        }
+        errors
+    }
+
+    /// Takes the baseline reads of delta-based sources, discarding their
+    /// readings; for collecting a single cycle, where the next `collect`
+    /// should have deltas. `None` if no source needed one, else the
+    /// baseline reads that failed.
+    pub fn prime(&mut self, now: OffsetDateTime) -> Option<Vec<(String, anyhow::Error)>> {
+        let mut primed = None;
+        for source in self.sources_mut().filter(|s| s.needs_baseline()) {
+            let errors = primed.get_or_insert_with(Vec::new);
+            if let Err(err) = source.read(now) {
+                errors.push((source.name().to_string(), err));
+            }
+            source.take_events();
+        }
+        primed
+    }
+
+    /// Like `collect`, but only for the sources due now.
+    pub fn collect_due(&mut self) -> Collected {
+        let mut collected = Collected::default();
+        for (stamp, source) in self.due_mut(Instant::now(), now_utc()) {
+            collected.read(source.as_mut(), stamp);
+        }
+        collected
+    }
+
+    pub fn finish(&mut self) -> Vec<EventRow> {
+        let mut events = Vec::new();
+        for source in self.sources_mut() {
+            source.finish();
+            events.extend(source.take_events());
+        }
+        events
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Collected {
+    pub readings: Vec<MetricReading>,
+    pub events: Vec<EventRow>,
+    pub errors: Vec<(String, anyhow::Error)>,
+}
+
+impl Collected {
+    fn read(&mut self, source: &mut dyn MetricSource, now: OffsetDateTime) {
+        match source.read(now) {
+            Ok(readings) => self.readings.extend(readings),
+            Err(err) => self.errors.push((source.name().to_string(), err)),
+        }
+        self.events.extend(source.take_events());
+    }
+}
+
+fn builtin_source(kind: MetricKind, config: &Config) -> Option<Box<dyn MetricSource>> {
+    let daemon = &config.daemon;
+    let roots = daemon.host_roots();
+    let source: Box<dyn MetricSource> = match kind {
+        MetricKind::Cpu => Box::new(CpuSource { roots, prev: None }),
+        MetricKind::Ram => Box::new(RamSource { roots }),
+        MetricKind::Net => Box::new(NetSource {
+            roots,
+            interfaces: daemon.net_interfaces.clone(),
+            prev: HashMap::new(),
+            reset: HashSet::new(),
+            resumed: false,
+        }),
+        MetricKind::Battery => Box::new(BatterySource { roots }),
+        MetricKind::Temps => Box::new(TempSource { roots }),
+        MetricKind::Disk => Box::new(DiskSource {
+            mounts: if daemon.disk_devices.is_empty() {
+                vec!["/".into()]
+            } else {
+                daemon.disk_devices.clone()
+            },
+        }),
+        MetricKind::Power => Box::new(PowerSource { roots }),
+        MetricKind::Gpu => Box::new(GpuSource { roots }),
+        MetricKind::Uptime => Box::new(UptimeSource {
+            boot_id: read_boot_id(&roots).ok(),
+        }),
+        MetricKind::Backlight => Box::new(BacklightSource { roots }),
+        MetricKind::PowerProfile => Box::new(PowerProfileSource { roots }),
+        MetricKind::Cstates => Box::new(CstateSource { roots, prev: None }),
+        MetricKind::Throttle => Box::new(ThrottleSource {
+            roots,
+            hot_temp_c: daemon.throttle_temp_c,
+            prev: None,
+            tracker: ThrottleTracker::default(),
+            events: Vec::new(),
+        }),
+        // Configured per entry rather than through `daemon.metrics`.
+        MetricKind::Custom | MetricKind::Textfile => return None,
+        // Only the daemon can observe its own cycles; it adds this source.
+        MetricKind::Daemon => return None,
+    };
+    Some(source)
+}
+
+/// How a metric name used in presets, `--charts` and the viewer resolves to
+/// stored and live data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetricRef {
+    /// Prefix of the `<view>_view` SQLite view holding the history.
+    pub view: &'static str,
+    /// Family and reading that provide the value in live mode, if any.
+    pub live: Option<(MetricKind, &'static str)>,
+}
+
+const fn metric_ref(view: &'static str, kind: MetricKind, reading: &'static str) -> MetricRef {
+    MetricRef {
+        view,
+        live: Some((kind, reading)),
+    }
+}
+
+pub fn resolve_metric(name: &str) -> Option<MetricRef> {
+    use MetricKind::*;
+    let r = match name {
+        "cpu" | "cpu_usage" => metric_ref("cpu_samples", Cpu, "cpu_usage"),
+        "ram" | "ram_usage" => metric_ref("ram_samples", Ram, "ram_usage"),
+        "net" | "net_bytes" | "net_delta" => metric_ref("net_samples", Net, "net_delta"),
+        "battery_health" => metric_ref("battery_health_samples", Battery, "battery_health"),
+        "battery_power" => metric_ref("battery_power_samples", Battery, "battery_power"),
+        m if m.starts_with("battery") => metric_ref("battery_samples", Battery, "battery_capacity"),
+        "gpu_temp" => metric_ref("gpu_temp_samples", Gpu, "gpu_temp"),
+        "gpu_power" => metric_ref("gpu_power_samples", Gpu, "gpu_power"),
+        "gpu_vram" => metric_ref("gpu_vram_samples", Gpu, "gpu_vram"),
+        "gpu_freq" => metric_ref("gpu_freq_samples", Gpu, "gpu_freq"),
+        m if m.starts_with("gpu") => metric_ref("gpu_samples", Gpu, "gpu_busy"),
+        "uptime" => metric_ref("uptime_samples", Uptime, "uptime"),
+        "suspend" => MetricRef {
+            view: "suspend_samples",
+            live: None,
+        },
+        "throttle" | "throttling" => metric_ref("throttle_samples", Throttle, "throttle_cores"),
+        "backlight" => metric_ref("backlight_samples", Backlight, "backlight"),
+        "power_profile" | "profile" => {
+            metric_ref("power_profile_samples", PowerProfile, "power_profile")
+        }
+        "cstates" | "cstate" => metric_ref("cstate_samples", Cstates, "cstate_residency"),
+        "custom" => MetricRef {
+            view: "custom_samples",
+            live: None,
+        },
+        "textfile" => MetricRef {
+            view: "textfile_samples",
+            live: None,
+        },
+        "daemon" | "daemon_read" => {
+            metric_ref("daemon_read_samples", Daemon, "daemon_read_duration")
+        }
+        "daemon_samples" => metric_ref("daemon_written_samples", Daemon, "daemon_cycle_samples"),
+        "daemon_read_errors" => {
+            metric_ref("daemon_read_errors_samples", Daemon, "daemon_read_errors")
+        }
+        "daemon_write_errors" => {
+            metric_ref("daemon_write_errors_samples", Daemon, "daemon_write_errors")
+        }
+        "daemon_sink_backlog" => {
+            metric_ref("daemon_sink_backlog_samples", Daemon, "daemon_sink_backlog")
+        }
+        "daemon_sink_dropped" => {
+            metric_ref("daemon_sink_dropped_samples", Daemon, "daemon_sink_dropped")
+        }
+        "daemon_db" => metric_ref("daemon_db_samples", Daemon, "daemon_db_size"),
+        "daemon_rss" => metric_ref("daemon_rss_samples", Daemon, "daemon_rss"),
+        "daemon_cpu" => metric_ref("daemon_cpu_samples", Daemon, "daemon_cpu"),
+        m if m.contains("temp") || m == "temps" => metric_ref("temp_samples", Temps, "temp"),
+        m if m.contains("disk") => metric_ref("disk_samples", Disk, "disk_usage"),
+        m if m.contains("power") => metric_ref("power_samples", Power, "power_draw"),
+        _ => return None,
+    };
+    Some(r)
+}
+
+/// Interfaces to sample: the configured list, or every non-loopback
+/// interface present right now.
+pub fn desired_interfaces(roots: &HostRoots, user: &[String]) -> Vec<String> {
+    if !user.is_empty() {
+        return user.to_vec();
+    }
+    let base = roots.sys("class/net");
+    let mut found = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(base) {
+        for entry in entries.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name == "lo" {
+                continue;
+            }
+            found.push(name);
+        }
+    }
+    if found.is_empty() {
+        vec!["eth0".into()]
+    } else {
+        found
+    }
+}
+
+fn percent(used: f64, total: f64) -> f64 {
+    if total > 0.0 {
+        used / total * 100.0
+    } else {
+        0.0
+    }
+}
+
+struct CpuSource {
+    roots: HostRoots,
+    prev: Option<CpuTimes>,
+}
+
+impl MetricSource for CpuSource {
+    fn name(&self) -> &str {
+        "cpu"
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Cpu
+    }
+
+    fn needs_baseline(&self) -> bool {
+        true
+    }
+
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+        let current = read_cpu_times(&self.roots)?;
+        let usage = self
+            .prev
+            .replace(current)
+            .and_then(|prev| cpu_usage_percent(&prev, &current));
+        Ok(usage
+            .map(|u| MetricReading::new(now, self.kind(), "cpu_usage", Some("total"), u, Some("%")))
+            .into_iter()
+            .collect())
+    }
+}
+
+struct RamSource {
+    roots: HostRoots,
+}
+
+impl MetricSource for RamSource {
+    fn name(&self) -> &str {
+        "ram"
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Ram
+    }
+
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+        let ram = read_ram_usage(&self.roots)?;
+        let used = ram.total_bytes.saturating_sub(ram.available_bytes);
+        let kind = self.kind();
+        Ok(vec![
+            MetricReading::new(now, kind, "ram_used_bytes", None, used as f64, Some("B")),
+            MetricReading::new(
+                now,
+                kind,
+                "ram_total_bytes",
+                None,
+                ram.total_bytes as f64,
+                Some("B"),
+            ),
+            MetricReading::new(
+                now,
+                kind,
+                "ram_usage",
+                None,
+                percent(used as f64, ram.total_bytes as f64),
+                Some("%"),
+            ),
+        ])
+    }
+}
+
+struct NetSource {
+    roots: HostRoots,
+    interfaces: Vec<String>,
+    prev: HashMap<String, NetSnapshot>,
+    /// Interfaces whose stored counters are from an earlier boot; their next
+    /// read is flagged as a reset.
+    reset: HashSet<String>,
+    /// Whether `prev` was seeded from the database.
+    resumed: bool,
+}
+
+impl MetricSource for NetSource {
+    fn name(&self) -> &str {
+        "net"
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Net
+    }
+
+    fn needs_baseline(&self) -> bool {
+        !self.resumed
+    }
+
+    fn resume(&mut self, db: &Database, boot_started: Option<OffsetDateTime>) -> Result<()> {
+        for (iface, (at, snapshot)) in db.latest_net_snapshots()? {
+            if boot_started.is_some_and(|started| at >= started) {
+                self.prev.insert(iface, snapshot);
+            } else {
+                self.reset.insert(iface);
+            }
+        }
+        self.resumed = true;
+        Ok(())
+    }
+
+    /// Reports raw counters every time, and deltas once a previous snapshot
+    /// exists. A counter that went backwards is flagged with `net_reset`
+    /// instead of producing a negative delta.
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+        let kind = self.kind();
+        let mut readings = Vec::new();
+        let mut last_err = None;
+        for iface in desired_interfaces(&self.roots, &self.interfaces) {
+            let snapshot = match read_net_snapshot(&self.roots, &iface) {
+                Ok(s) => s,
+                Err(err) => {
+                    last_err = Some(err);
+                    continue;
+                }
+            };
+            let label = Some(iface.as_str());
+            readings.push(MetricReading::new(
+                now,
+                kind,
+                "net_rx_bytes",
+                label,
+                snapshot.rx_bytes as f64,
+                Some("B"),
+            ));
+            readings.push(MetricReading::new(
+                now,
+                kind,
+                "net_tx_bytes",
+                label,
+                snapshot.tx_bytes as f64,
+                Some("B"),
+            ));
+            let prev = self.prev.insert(iface.clone(), snapshot);
+            if self.reset.remove(&iface) {
+                readings.push(MetricReading::new(now, kind, "net_reset", label, 1.0, None));
+            } else if let Some(prev) = prev {
+                let rx_delta = snapshot.rx_bytes as i64 - prev.rx_bytes as i64;
+                let tx_delta = snapshot.tx_bytes as i64 - prev.tx_bytes as i64;
+                if rx_delta < 0 || tx_delta < 0 {
+                    readings.push(MetricReading::new(now, kind, "net_reset", label, 1.0, None));
+                } else {
+                    for (metric, value) in [
+                        ("net_rx_delta", rx_delta),
+                        ("net_tx_delta", tx_delta),
+                        ("net_delta", rx_delta + tx_delta),
+                    ] {
+                        readings.push(MetricReading::new(
+                            now,
+                            kind,
+                            metric,
+                            label,
+                            value as f64,
+                            Some("B"),
+                        ));
+                    }
+                }
+            }
+        }
+        match last_err {
+            Some(err) if readings.is_empty() => Err(err),
+            _ => Ok(readings),
+        }
+    }
+}
+
+struct BatterySource {
+    roots: HostRoots,
+}
+
+impl MetricSource for BatterySource {
+    fn name(&self) -> &str {
+        "battery"
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Battery
+    }
+
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+        let kind = self.kind();
+        let mut readings = Vec::new();
+        for b in read_batteries(&self.roots)? {
+            let label = Some(b.name.as_str());
+            for (metric, value, unit) in [
+                ("battery_capacity", b.capacity, "%"),
+                ("battery_health", b.health, "%"),
+                ("battery_power", b.energy_now_uw, "mW"),
+            ] {
+                if let Some(value) = value {
+                    readings.push(MetricReading::new(
+                        now,
+                        kind,
+                        metric,
+                        label,
+                        value,
+                        Some(unit),
+                    ));
+                }
+            }
+        }
+        Ok(readings)
+    }
+}
+
+struct TempSource {
+    roots: HostRoots,
+}
+
+impl MetricSource for TempSource {
+    fn name(&self) -> &str {
+        "temps"
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Temps
+    }
+
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+        Ok(read_temperatures(&self.roots)?
+            .into_iter()
+            .map(|t| {
+                MetricReading::new(
+                    now,
+                    self.kind(),
+                    "temp",
+                    Some(&t.sensor),
+                    t.value_c,
+                    Some("C"),
+                )
+            })
+            .collect())
+    }
+}
+
+struct DiskSource {
+    mounts: Vec<String>,
+}
+
+impl MetricSource for DiskSource {
+    fn name(&self) -> &str {
+        "disk"
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Disk
+    }
+
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+        let kind = self.kind();
+        let mut readings = Vec::new();
+        let mut last_err = None;
+        for mount in &self.mounts {
+            let usage = match read_disk_usage(mount) {
+                Ok(u) => u,
+                Err(err) => {
+                    last_err = Some(err.context(format!("reading disk usage for {mount}")));
+                    continue;
+                }
+            };
+            let used = usage.total_bytes.saturating_sub(usage.available_bytes);
+            let label = Some(mount.as_str());
+            readings.extend([
+                MetricReading::new(now, kind, "disk_used_bytes", label, used as f64, Some("B")),
+                MetricReading::new(
+                    now,
+                    kind,
+                    "disk_total_bytes",
+                    label,
+                    usage.total_bytes as f64,
+                    Some("B"),
+                ),
+                MetricReading::new(
+                    now,
+                    kind,
+                    "disk_usage",
+                    label,
+                    percent(used as f64, usage.total_bytes as f64),
+                    Some("%"),
+                ),
+            ]);
+        }
+        match last_err {
+            Some(err) if readings.is_empty() => Err(err),
+            _ => Ok(readings),
+        }
+    }
+}
+
+struct PowerSource {
+    roots: HostRoots,
+}
+
+impl MetricSource for PowerSource {
+    fn name(&self) -> &str {
+        "power"
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Power
+    }
+
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+        Ok(read_powercap(&self.roots)?
+            .into_iter()
+            .map(|p| {
+                MetricReading::new(
+                    now,
+                    self.kind(),
+                    "power_draw",
+                    Some(&p.domain),
+                    p.draw_mw,
+                    Some("mW"),
+                )
+            })
+            .collect())
+    }
+}
+
+struct GpuSource {
+    roots: HostRoots,
+}
+
+impl MetricSource for GpuSource {
+    fn name(&self) -> &str {
+        "gpu"
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Gpu
+    }
+
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+        let kind = self.kind();
+        let mut readings = Vec::new();
+        for g in read_gpus(&self.roots)? {
+            let vram = match (g.vram_used_bytes, g.vram_total_bytes) {
+                (Some(used), Some(total)) if total > 0 => Some(percent(used as f64, total as f64)),
+                _ => None,
+            };
+            let fields = [
+                ("gpu_busy", g.busy_percent, "%"),
+                (
+                    "gpu_vram_used_bytes",
+                    g.vram_used_bytes.map(|v| v as f64),
+                    "B",
+                ),
+                (
+                    "gpu_vram_total_bytes",
+                    g.vram_total_bytes.map(|v| v as f64),
+                    "B",
+                ),
+                ("gpu_vram", vram, "%"),
+                ("gpu_power", g.power_mw, "mW"),
+                ("gpu_cur_freq", g.cur_freq_mhz, "MHz"),
+                ("gpu_act_freq", g.act_freq_mhz, "MHz"),
+                ("gpu_freq", g.act_freq_mhz.or(g.cur_freq_mhz), "MHz"),
+                ("gpu_temp", g.temp_c, "C"),
+            ];
+            for (metric, value, unit) in fields {
+                if let Some(value) = value {
+                    readings.push(
+                        MetricReading::new(now, kind, metric, Some(&g.card), value, Some(unit))
+                            .with_detail(&g.driver),
+                    );
+                }
+            }
+        }
+        Ok(readings)
+    }
+}
+
+struct UptimeSource {
+    boot_id: Option<String>,
+}
+
+impl MetricSource for UptimeSource {
+    fn name(&self) -> &str {
+        "uptime"
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Uptime
+    }
+
+    /// `uptime` counts time spent suspended, `awake` does not; both are
+    /// labelled with the kernel boot id.
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+        let clocks = read_clocks()?;
+        let label = self.boot_id.as_deref();
+        Ok(vec![
+            MetricReading::new(
+                now,
+                self.kind(),
+                "uptime",
+                label,
+                clocks.boottime.as_secs_f64(),
+                Some("s"),
+            ),
+            MetricReading::new(
+                now,
+                self.kind(),
+                "awake",
+                label,
+                clocks.monotonic.as_secs_f64(),
+                Some("s"),
+            ),
+        ])
+    }
+}
+
+struct BacklightSource {
+    roots: HostRoots,
+}
+
+impl MetricSource for BacklightSource {
+    fn name(&self) -> &str {
+        "backlight"
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Backlight
+    }
+
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+        let kind = self.kind();
+        let mut readings = Vec::new();
+        for b in read_backlights(&self.roots)? {
+            let label = Some(b.device.as_str());
+            readings.push(MetricReading::new(
+                now,
+                kind,
+                "backlight_brightness",
+                label,
+                b.brightness as f64,
+                None,
+            ));
+            readings.push(MetricReading::new(
+                now,
+                kind,
+                "backlight_max_brightness",
+                label,
+                b.max_brightness as f64,
+                None,
+            ));
+            if let Some(pct) = b.percent() {
+                readings.push(MetricReading::new(
+                    now,
+                    kind,
+                    "backlight",
+                    label,
+                    pct,
+                    Some("%"),
+                ));
+            }
+        }
+        Ok(readings)
+    }
+}
+
+/// Orders profiles from most frugal to most aggressive, matching
+/// `power_profile_samples_view`; vendor-specific profiles map to -1.
+pub fn profile_rank(profile: &str) -> f64 {
+    match profile {
+        "low-power" | "power-saver" => 0.0,
+        "cool" | "quiet" => 1.0,
+        "balanced" => 2.0,
+        "balanced-performance" => 3.0,
+        "performance" => 4.0,
+        _ => -1.0,
+    }
+}
+
+struct PowerProfileSource {
+    roots: HostRoots,
+}
+
+impl MetricSource for PowerProfileSource {
+    fn name(&self) -> &str {
+        "power_profile"
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::PowerProfile
+    }
+
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+        Ok(read_power_profile(&self.roots)?
+            .map(|p| {
+                MetricReading::new(
+                    now,
+                    self.kind(),
+                    "power_profile",
+                    Some(&p.profile),
+                    profile_rank(&p.profile),
+                    None,
+                )
+                .with_detail(&p.source)
+            })
+            .into_iter()
+            .collect())
+    }
+}
+
+struct CstateSource {
+    roots: HostRoots,
+    prev: Option<CstateSnapshot>,
+}
+
+impl MetricSource for CstateSource {
+    fn name(&self) -> &str {
+        "cstates"
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Cstates
+    }
+
+    fn needs_baseline(&self) -> bool {
+        true
+    }
+
+    /// Labels are `<cpu>:<state>`, e.g. `cpu3:C6`, `all:C6` or `package:C6`;
+    /// package states have no entry count.
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+        let current = read_cstate_snapshot(&self.roots)?;
+        let kind = self.kind();
+        let mut readings = Vec::new();
+        if let Some(prev) = &self.prev {
+            for r in cstate_residency(prev, &current) {
+                let label = format!("{}:{}", r.cpu, r.state);
+                readings.push(MetricReading::new(
+                    now,
+                    kind,
+                    "cstate_residency",
+                    Some(&label),
+                    r.residency_pct,
+                    Some("%"),
+                ));
+                if r.cpu == PACKAGE_CSTATES {
+                    continue;
+                }
+                readings.push(MetricReading::new(
+                    now,
+                    kind,
+                    "cstate_usage",
+                    Some(&label),
+                    r.usage_delta as f64,
+                    None,
+                ));
+            }
+        }
+        self.prev = Some(current);
+        Ok(readings)
+    }
+}
+
+struct ThrottleSource {
+    roots: HostRoots,
+    hot_temp_c: f64,
+    prev: Option<ThrottleSnapshot>,
+    tracker: ThrottleTracker,
+    events: Vec<EventRow>,
+}
+
+impl ThrottleSource {
+    fn push_episode(&mut self, episode: ThrottleEpisode) {
+        match EventRow::throttle(&episode) {
+            Ok(event) => self.events.push(event),
+            Err(err) => tracing::warn!("failed to encode throttle episode: {err}"),
+        }
+    }
+}
+
+impl MetricSource for ThrottleSource {
+    fn name(&self) -> &str {
+        "throttle"
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Throttle
+    }
+
+    fn needs_baseline(&self) -> bool {
+        true
+    }
+
+    /// Reports how many cores throttled this cycle; finished episodes come
+    /// out as `throttle` events.
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+        let current = read_throttle_snapshot(&self.roots)?;
+        let cpu_temp = read_temperatures(&self.roots)
+            .ok()
+            .and_then(|temps| hottest_cpu_temp(&temps));
+        let mut readings = Vec::new();
+        if let Some(prev) = &self.prev {
+            let cores = throttled_cores(prev, &current, cpu_temp, self.hot_temp_c);
+            readings.push(MetricReading::new(
+                now,
+                self.kind(),
+                "throttle_cores",
+                None,
+                cores.len() as f64,
+                None,
+            ));
+            if let Some(episode) = self.tracker.update(now, &cores, cpu_temp) {
+                tracing::info!(
+                    "thermal throttling on {} cores for {}s",
+                    episode.cores.len(),
+                    (episode.end - episode.start).whole_seconds()
+                );
+                self.push_episode(episode);
+            }
+        }
+        self.prev = Some(current);
+        Ok(readings)
+    }
+
+    fn take_events(&mut self) -> Vec<EventRow> {
+        std::mem::take(&mut self.events)
+    }
+
+    fn finish(&mut self) {
+        if let Some(episode) = self.tracker.finish() {
+            self.push_episode(episode);
+        }
+    }
+}
+
+/// Runs one `[[daemon.custom]]` command; how often is up to the registry.
+pub struct CustomSource {
+    name: String,
+    config: CustomMetricConfig,
+}
+
+impl CustomSource {
+    pub fn new(config: CustomMetricConfig) -> Self {
+        Self {
+            name: format!("custom:{}", config.name),
+            config,
+        }
+    }
+}
+
+impl MetricSource for CustomSource {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Custom
+    }
+
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+        Ok(run_custom_metric(&self.config)?
+            .into_iter()
+            .map(|r| MetricReading {
+                timestamp: now,
+                kind: MetricKind::Custom,
+                metric: r.name,
+                label: r.label,
+                value: r.value,
+                unit: r.unit,
+                detail: None,
+            })
+            .collect())
+    }
+}
+
+/// Ingests node_exporter-style `*.prom` files from a directory.
+pub struct TextfileSource {
+    dir: PathBuf,
+}
+
+impl TextfileSource {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+}
+
+impl MetricSource for TextfileSource {
+    fn name(&self) -> &str {
+        "textfile"
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Textfile
+    }
+
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+        let scan = read_textfile_dir(&self.dir)?;
+        for (path, err) in scan.errors {
+            tracing::warn!("skipping textfile {path:?}: {err:#}");
+        }
+        Ok(scan
+            .readings
+            .into_iter()
+            .map(|r| MetricReading {
+                timestamp: now,
+                kind: MetricKind::Textfile,
+                metric: r.name,
+                label: r.label,
+                value: r.value,
+                unit: r.unit,
+                detail: None,
+            })
+            .collect())
+    }
+}
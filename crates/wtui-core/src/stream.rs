@@ -0,0 +1,105 @@
+use crate::metrics::MetricReading;
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+/// Recent readings, oldest first, bounded both by age (relative to the
+/// newest reading) and by count.
+#[derive(Debug, Clone)]
+pub struct HistoryRing {
+    window: Duration,
+    capacity: usize,
+    readings: VecDeque<MetricReading>,
+}
+
+impl HistoryRing {
+    pub fn new(window: Duration, capacity: usize) -> Self {
+        Self {
+            window,
+            capacity,
+            readings: VecDeque::new(),
+        }
+    }
+
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = window;
+        self.evict();
+    }
+
+    pub fn push(&mut self, reading: MetricReading) {
+        self.readings.push_back(reading);
+        self.evict();
+    }
+
+    pub fn extend(&mut self, readings: impl IntoIterator<Item = MetricReading>) {
+        self.readings.extend(readings);
+        self.evict();
+    }
+
+    pub fn iter(&self) -> std::collections::vec_deque::Iter<'_, MetricReading> {
+        self.readings.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.readings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.readings.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.readings.clear();
+    }
+
+    fn evict(&mut self) {
+        while self.readings.len() > self.capacity {
+            self.readings.pop_front();
+        }
+        let Some(newest) = self.readings.back().map(|r| r.timestamp) else {
+            return;
+        };
+        let cutoff = newest - crate::timeutils::duration_from_std(self.window);
+        while self.readings.front().is_some_and(|r| r.timestamp < cutoff) {
+            self.readings.pop_front();
+        }
+    }
+}
+
+/// Encodes one reading as a line of the stream protocol: a JSON
+/// `MetricReading` per line.
+pub fn encode_line(reading: &MetricReading) -> Result<String> {
+    let mut line = serde_json::to_string(reading)?;
+    line.push('\n');
+    Ok(line)
+}
+
+/// Connects to the daemon's reading stream. The daemon first replays its
+/// history, then sends every new reading; the channel disconnects when the
+/// daemon goes away.
+pub fn subscribe(socket: &Path) -> Result<Receiver<MetricReading>> {
+    let stream = UnixStream::connect(socket)
+        .with_context(|| format!("connecting to reading stream {socket:?}"))?;
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            match serde_json::from_str(&line) {
+                Ok(reading) => {
+                    if tx.send(reading).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => tracing::debug!("skipping malformed stream line: {err}"),
+            }
+        }
+    });
+    Ok(rx)
+}
@@ -0,0 +1,179 @@
+use crate::custom::CustomReading;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// One sample line from a Prometheus text exposition file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PromSample {
+    pub name: String,
+    pub labels: Vec<(String, String)>,
+    pub value: f64,
+}
+
+impl PromSample {
+    /// Canonical `key="value",...` rendering of the label set, sorted by key so
+    /// the same series always maps to the same label string.
+    pub fn label_string(&self) -> Option<String> {
+        if self.labels.is_empty() {
+            return None;
+        }
+        let mut labels = self.labels.clone();
+        labels.sort();
+        Some(
+            labels
+                .iter()
+                .map(|(k, v)| format!("{k}=\"{}\"", escape_label_value(v)))
+                .collect::<Vec<_>>()
+                .join(","),
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TextfileScan {
+    pub readings: Vec<CustomReading>,
+    pub errors: Vec<(PathBuf, anyhow::Error)>,
+}
+
+/// Reads every `*.prom` file in `dir`, following node_exporter's textfile
+/// collector convention. A malformed file is reported in `errors` without
+/// discarding the others.
+pub fn read_textfile_dir(dir: &Path) -> Result<TextfileScan> {
+    let mut scan = TextfileScan::default();
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("reading textfile directory {dir:?}"))?
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.extension().map(|e| e == "prom").unwrap_or(false))
+        .collect();
+    files.sort();
+
+    for path in files {
+        let parsed = fs::read_to_string(&path)
+            .with_context(|| format!("reading {path:?}"))
+            .and_then(|content| parse_exposition(&content));
+        match parsed {
+            Ok(samples) => scan
+                .readings
+                .extend(samples.into_iter().map(|s| CustomReading {
+                    label: s.label_string(),
+                    name: s.name,
+                    value: s.value,
+                    unit: None,
+                })),
+            Err(err) => scan.errors.push((path, err)),
+        }
+    }
+    Ok(scan)
+}
+
+/// Parses the Prometheus text exposition format. Comment, `# HELP` and
+/// `# TYPE` lines are skipped, as are non-finite values, which have no useful
+/// representation in SQLite.
+pub fn parse_exposition(content: &str) -> Result<Vec<PromSample>> {
+    let mut samples = Vec::new();
+    for (idx, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let sample =
+            parse_sample_line(line).with_context(|| format!("line {}: {line:?}", idx + 1))?;
+        if sample.value.is_finite() {
+            samples.push(sample);
+        }
+    }
+    Ok(samples)
+}
+
+fn parse_sample_line(line: &str) -> Result<PromSample> {
+    let name_end = line
+        .find(|c: char| c == '{' || c.is_whitespace())
+        .context("missing value")?;
+    let name = &line[..name_end];
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+    {
+        anyhow::bail!("invalid metric name {name:?}");
+    }
+
+    let mut rest = &line[name_end..];
+    let mut labels = Vec::new();
+    if let Some(after) = rest.strip_prefix('{') {
+        let (parsed, remaining) = parse_labels(after)?;
+        labels = parsed;
+        rest = remaining;
+    }
+
+    // An optional millisecond timestamp may follow the value; the daemon
+    // stamps samples with its own cycle time, so it is ignored.
+    let value = rest.split_whitespace().next().context("missing value")?;
+    Ok(PromSample {
+        name: name.to_string(),
+        labels,
+        value: parse_prom_value(value)?,
+    })
+}
+
+/// Splits a label string from [`PromSample::label_string`] back into pairs.
+pub fn split_label_string(labels: &str) -> Result<Vec<(String, String)>> {
+    let closed = format!("{labels}}}");
+    Ok(parse_labels(&closed)?.0)
+}
+
+fn parse_labels(input: &str) -> Result<(Vec<(String, String)>, &str)> {
+    let mut labels = Vec::new();
+    let mut rest = input.trim_start();
+    loop {
+        if let Some(after) = rest.strip_prefix('}') {
+            return Ok((labels, after));
+        }
+        let eq = rest.find('=').context("label without value")?;
+        let key = rest[..eq].trim().to_string();
+        rest = rest[eq + 1..]
+            .trim_start()
+            .strip_prefix('"')
+            .context("label value must be quoted")?;
+
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        let end = loop {
+            match chars.next() {
+                Some((i, '"')) => break i,
+                Some((_, '\\')) => match chars.next() {
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, c)) => value.push(c),
+                    None => anyhow::bail!("unterminated escape"),
+                },
+                Some((_, c)) => value.push(c),
+                None => anyhow::bail!("unterminated label value"),
+            }
+        };
+        labels.push((key, value));
+        rest = rest[end + 1..].trim_start();
+        if let Some(after) = rest.strip_prefix(',') {
+            rest = after.trim_start();
+        }
+    }
+}
+
+fn parse_prom_value(value: &str) -> Result<f64> {
+    match value {
+        "+Inf" | "Inf" => Ok(f64::INFINITY),
+        "-Inf" => Ok(f64::NEG_INFINITY),
+        "NaN" => Ok(f64::NAN),
+        v => v
+            .parse::<f64>()
+            .with_context(|| format!("invalid value {v:?}")),
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -29,3 +29,11 @@ pub fn duration_to_std(duration: Duration) -> StdDuration {
         )
     }
 }
+
+/// The first multiple of `interval` since the Unix epoch strictly after
+/// `wall`, e.g. the next :00 or :30 for a 30s interval.
+pub fn align_up(wall: OffsetDateTime, interval: StdDuration) -> OffsetDateTime {
+    let step = interval.as_nanos().max(1) as i128;
+    let next = (wall.unix_timestamp_nanos().div_euclid(step) + 1) * step;
+    OffsetDateTime::from_unix_timestamp_nanos(next).unwrap_or(wall)
+}
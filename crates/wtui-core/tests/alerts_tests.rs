@@ -0,0 +1,136 @@
+use std::time::Duration;
+use wtui_core::alerts::{AlertEngine, AlertState, Condition};
+use wtui_core::{utc_from_timestamp, AlertAction, AlertConfig, MetricKind, MetricReading};
+
+fn temp(at: i64, sensor: &str, value: f64) -> MetricReading {
+    MetricReading::new(
+        utc_from_timestamp(at),
+        MetricKind::Temps,
+        "temp",
+        Some(sensor),
+        value,
+        Some("C"),
+    )
+}
+
+#[test]
+fn rules_parse_and_match_labels() {
+    let rule: Condition = r#"temps{sensor=~"k10temp.*"} > 90 for 2m"#.parse().unwrap();
+    assert_eq!(rule.threshold, 90.0);
+    assert_eq!(rule.hold, Duration::from_secs(120));
+    assert!(rule.matches(&temp(0, "k10temp Tctl", 50.0)));
+    assert!(!rule.matches(&temp(0, "nvme Composite", 50.0)));
+
+    let disk: Condition = r#"disk{mount="/"} > 95"#.parse().unwrap();
+    let root = MetricReading::new(
+        utc_from_timestamp(0),
+        MetricKind::Disk,
+        "disk_usage",
+        Some("/"),
+        97.0,
+        Some("%"),
+    );
+    assert!(disk.matches(&root));
+    assert_eq!(disk.hold, Duration::ZERO);
+
+    let backup: Condition = r#"backup_age_seconds{job!="scratch"} >= 86400"#.parse().unwrap();
+    let textfile = MetricReading::new(
+        utc_from_timestamp(0),
+        MetricKind::Textfile,
+        "backup_age_seconds",
+        Some("job=\"home\""),
+        90_000.0,
+        None,
+    );
+    assert!(backup.matches(&textfile));
+
+    assert!("temps >".parse::<Condition>().is_err());
+    assert!(r#"temps{sensor="x} > 1"#.parse::<Condition>().is_err());
+    assert!("temps > 90 during 2m".parse::<Condition>().is_err());
+}
+
+#[test]
+fn alerts_wait_out_the_hold_time_and_resolve_with_hysteresis() {
+    let mut engine = AlertEngine::new(&[AlertConfig {
+        name: "hot".into(),
+        rule: r#"temps{sensor=~"k10temp.*"} > 90 for 1m"#.into(),
+        hysteresis: 5.0,
+        actions: vec![AlertAction::Log],
+        command: None,
+    }])
+    .unwrap();
+
+    assert!(engine.evaluate(&[temp(0, "k10temp Tctl", 95.0)]).is_empty());
+    assert!(engine
+        .evaluate(&[temp(30, "k10temp Tctl", 95.0)])
+        .is_empty());
+    let fired = engine.evaluate(&[temp(60, "k10temp Tctl", 93.0)]);
+    assert_eq!(fired.len(), 1);
+    assert_eq!(fired[0].state, AlertState::Firing);
+    assert_eq!(fired[0].since, utc_from_timestamp(0));
+
+    // Below the threshold but within the hysteresis band: still firing.
+    assert!(engine
+        .evaluate(&[temp(90, "k10temp Tctl", 88.0)])
+        .is_empty());
+    let resolved = engine.evaluate(&[temp(120, "k10temp Tctl", 84.0)]);
+    assert_eq!(resolved.len(), 1);
+    assert_eq!(resolved[0].state, AlertState::Resolved);
+
+    // A dip before the hold time is up starts the wait over.
+    assert!(engine
+        .evaluate(&[temp(200, "k10temp Tctl", 95.0)])
+        .is_empty());
+    assert!(engine
+        .evaluate(&[temp(230, "k10temp Tctl", 80.0)])
+        .is_empty());
+    assert!(engine
+        .evaluate(&[temp(260, "k10temp Tctl", 95.0)])
+        .is_empty());
+
+    assert!(AlertEngine::new(&[AlertConfig {
+        name: "bad".into(),
+        rule: "cpu > 90".into(),
+        hysteresis: 0.0,
+        actions: vec![AlertAction::Command],
+        command: None,
+    }])
+    .is_err());
+}
+
+#[test]
+fn series_missing_from_their_family_resolve() {
+    let mut engine = AlertEngine::new(&[AlertConfig {
+        name: "hot".into(),
+        rule: "temps > 90".into(),
+        hysteresis: 0.0,
+        actions: vec![AlertAction::Log],
+        command: None,
+    }])
+    .unwrap();
+    let fired = engine.evaluate(&[temp(0, "usb", 95.0), temp(0, "cpu", 50.0)]);
+    assert_eq!(fired.len(), 1);
+
+    // Cycles that didn't read temperatures don't count.
+    let other = MetricReading::new(
+        utc_from_timestamp(10),
+        MetricKind::Cpu,
+        "cpu_usage",
+        Some("total"),
+        5.0,
+        Some("%"),
+    );
+    for _ in 0..5 {
+        assert!(engine.evaluate(std::slice::from_ref(&other)).is_empty());
+    }
+    // The usb sensor is gone; the third read without it resolves it.
+    assert!(engine.evaluate(&[temp(60, "cpu", 50.0)]).is_empty());
+    assert!(engine.evaluate(&[temp(120, "cpu", 50.0)]).is_empty());
+    let resolved = engine.evaluate(&[temp(180, "cpu", 50.0)]);
+    assert_eq!(resolved.len(), 1);
+    assert_eq!(resolved[0].state, AlertState::Resolved);
+    assert_eq!(resolved[0].label.as_deref(), Some("usb"));
+    assert_eq!(resolved[0].value, 95.0);
+    assert_eq!(resolved[0].at, utc_from_timestamp(180));
+    assert!(engine.evaluate(&[temp(240, "cpu", 50.0)]).is_empty());
+}
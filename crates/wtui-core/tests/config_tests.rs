@@ -1,4 +1,5 @@
-use wtui_core::{parse_range, Config};
+use std::time::Duration;
+use wtui_core::{parse_range, Config, MetricKind, SinkConfig};
 
 #[test]
 fn defaults_expand_paths() {
@@ -16,3 +17,114 @@ fn parse_range_supports_shortcuts() {
     let dur2 = parse_range("30s").expect("parse duration");
     assert_eq!(dur2.whole_seconds(), 30);
 }
+
+#[test]
+fn host_roots_from_config_and_env() {
+    let mut cfg: Config = toml::from_str(
+        "[database]\npath = \"/tmp/wtui.db\"\n\n[daemon]\nproc_root = \"/host/proc\"\n",
+    )
+    .expect("parse config");
+    cfg.apply_env_from(|name| (name == "WTUI_SYS_ROOT").then(|| "/host/sys".into()));
+    let roots = cfg.daemon.host_roots();
+    assert_eq!(roots.proc("stat"), std::path::Path::new("/host/proc/stat"));
+    assert_eq!(
+        roots.sys("class/hwmon"),
+        std::path::Path::new("/host/sys/class/hwmon")
+    );
+}
+
+#[test]
+fn per_family_intervals_fall_back_to_base() {
+    let cfg: Config = toml::from_str(
+        "[database]\npath = \"/tmp/wtui.db\"\n\n[daemon]\ninterval = \"30s\"\nintervals = { temps = \"5s\", battery = \"1d\" }\n",
+    )
+    .expect("parse config");
+    let daemon = &cfg.daemon;
+    assert_eq!(
+        daemon.interval_for(MetricKind::Temps),
+        Duration::from_secs(5)
+    );
+    assert_eq!(
+        daemon.interval_for(MetricKind::Battery),
+        Duration::from_secs(86400)
+    );
+    assert_eq!(
+        daemon.interval_for(MetricKind::Net),
+        Duration::from_secs(30)
+    );
+}
+
+#[test]
+fn sub_second_intervals_are_rejected_at_load() {
+    for (daemon, field) in [
+        ("interval = \"0s\"", "daemon.interval"),
+        (
+            "intervals = { temps = \"500ms\" }",
+            "daemon.intervals.temps",
+        ),
+        (
+            "[daemon.battery]\nintervals = { net = \"0s\" }",
+            "daemon.battery.intervals.net",
+        ),
+    ] {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        let content = format!("[database]\npath = \"/tmp/wtui.db\"\n\n[daemon]\n{daemon}\n");
+        std::io::Write::write_all(&mut file, content.as_bytes()).unwrap();
+        let err = Config::load(Some(file.path())).unwrap_err();
+        assert!(format!("{err:#}").contains(field), "{err:#}");
+    }
+}
+
+#[test]
+fn battery_profile_applies_only_on_battery() {
+    let cfg: Config = toml::from_str(
+        "[database]\npath = \"/tmp/wtui.db\"\n\n[daemon]\ninterval = \"30s\"\nmetrics = [\"cpu\", \"gpu\", \"temps\"]\n\n[daemon.battery]\ninterval = \"2m\"\nintervals = { temps = \"1m\" }\ndisabled = [\"gpu\"]\n",
+    )
+    .expect("parse config");
+    let ac = cfg.daemon.for_power_source(false);
+    assert_eq!(ac.metrics.len(), 3);
+    assert_eq!(ac.interval_for(MetricKind::Cpu), Duration::from_secs(30));
+
+    let battery = cfg.daemon.for_power_source(true);
+    assert_eq!(battery.metrics, vec![MetricKind::Cpu, MetricKind::Temps]);
+    assert_eq!(
+        battery.interval_for(MetricKind::Cpu),
+        Duration::from_secs(120)
+    );
+    assert_eq!(
+        battery.interval_for(MetricKind::Temps),
+        Duration::from_secs(60)
+    );
+}
+
+#[test]
+fn sinks_default_to_sqlite_and_expand_paths() {
+    let cfg = Config::load(None).expect("load default config");
+    assert_eq!(cfg.daemon.sinks, vec![SinkConfig::Sqlite]);
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    std::io::Write::write_all(
+        &mut file,
+        b"[database]\npath = \"/tmp/wtui.db\"\n\n[[daemon.sinks]]\ntype = \"jsonl\"\npath = \"~/wtui.jsonl\"\n\n[[daemon.sinks]]\ntype = \"stdout\"\n",
+    )
+    .unwrap();
+    let cfg = Config::load(Some(file.path())).expect("load config");
+    match &cfg.daemon.sinks[..] {
+        [SinkConfig::Jsonl { path }, SinkConfig::Stdout] => {
+            assert!(!path.to_string_lossy().contains('~'))
+        }
+        other => panic!("unexpected sinks {other:?}"),
+    }
+}
+
+#[test]
+fn spool_defaults_to_next_to_the_database() {
+    let mut cfg: Config =
+        toml::from_str("[database]\npath = \"/var/lib/wtui/data.db\"\n").expect("parse config");
+    assert_eq!(
+        cfg.spool_path(),
+        std::path::Path::new("/var/lib/wtui/data.db.spool")
+    );
+    cfg.daemon.spool.path = Some("/srv/spool.jsonl".into());
+    assert_eq!(cfg.spool_path(), std::path::Path::new("/srv/spool.jsonl"));
+}
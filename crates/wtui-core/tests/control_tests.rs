@@ -0,0 +1,30 @@
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixListener;
+use wtui_core::control::{send_request, ControlRequest, ControlResponse};
+
+#[test]
+fn requests_round_trip_over_socket() {
+    assert_eq!(
+        serde_json::to_string(&ControlRequest::SampleNow).unwrap(),
+        r#"{"cmd":"sample-now"}"#
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("daemon.sock");
+    let listener = UnixListener::bind(&path).unwrap();
+    let server = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let mut line = String::new();
+        BufReader::new(&stream).read_line(&mut line).unwrap();
+        let request: ControlRequest = serde_json::from_str(&line).unwrap();
+        let reply = serde_json::to_string(&ControlResponse::ok(format!("{request:?}"))).unwrap();
+        (&stream)
+            .write_all(format!("{reply}\n").as_bytes())
+            .unwrap();
+    });
+
+    let response = send_request(&path, ControlRequest::PruneNow).unwrap();
+    server.join().unwrap();
+    assert!(response.ok);
+    assert_eq!(response.message.as_deref(), Some("PruneNow"));
+}
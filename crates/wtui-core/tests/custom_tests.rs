@@ -0,0 +1,56 @@
+use std::time::Duration;
+use wtui_core::custom::{parse_custom_output, run_custom_metric};
+use wtui_core::CustomMetricConfig;
+
+#[test]
+fn parses_single_number_and_labelled_lines() {
+    assert_eq!(parse_custom_output("42\n").unwrap(), vec![(None, 42.0)]);
+    let labelled = parse_custom_output("# queues\nmail=3\nprint = 0.5\n\n").unwrap();
+    assert_eq!(
+        labelled,
+        vec![(Some("mail".into()), 3.0), (Some("print".into()), 0.5)]
+    );
+    assert!(parse_custom_output("not a number").is_err());
+    assert!(parse_custom_output("").is_err());
+}
+
+#[test]
+fn command_is_killed_after_timeout() {
+    let cfg = CustomMetricConfig {
+        name: "slow".into(),
+        command: "sleep 5; echo 1".into(),
+        interval: None,
+        timeout: Duration::from_millis(200),
+        unit: None,
+    };
+    let err = run_custom_metric(&cfg).unwrap_err();
+    assert!(format!("{err:#}").contains("timed out"));
+
+    let cfg = CustomMetricConfig {
+        name: "licenses".into(),
+        command: "printf 'used=7\\nfree=3\\n'".into(),
+        interval: None,
+        timeout: Duration::from_secs(5),
+        unit: Some("seats".into()),
+    };
+    let readings = run_custom_metric(&cfg).unwrap();
+    assert_eq!(readings.len(), 2);
+    assert_eq!(readings[0].label.as_deref(), Some("used"));
+    assert_eq!(readings[1].unit.as_deref(), Some("seats"));
+}
+
+#[test]
+fn background_jobs_dont_outlive_the_timeout() {
+    // `sh` exits at once, but the sleep keeps stdout open.
+    let cfg = CustomMetricConfig {
+        name: "leaky".into(),
+        command: "sleep 30 & echo 1".into(),
+        interval: None,
+        timeout: Duration::from_millis(300),
+        unit: None,
+    };
+    let started = std::time::Instant::now();
+    let err = run_custom_metric(&cfg).unwrap_err();
+    assert!(format!("{err:#}").contains("timed out"));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
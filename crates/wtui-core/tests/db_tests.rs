@@ -1,6 +1,6 @@
 use tempfile::NamedTempFile;
 use time::OffsetDateTime;
-use wtui_core::metrics::NetSnapshot;
+use wtui_core::metrics::{GpuReading, NetSnapshot};
 use wtui_core::Database;
 
 #[test]
@@ -29,3 +29,151 @@ fn aggregates_network_bytes() {
     assert_eq!(rows.len(), 1);
     assert_eq!(rows[0].value as i64, 300);
 }
+
+#[test]
+fn gpu_views_split_fields() {
+    let tmp = NamedTempFile::new().unwrap();
+    let db = Database::connect(tmp.path()).unwrap();
+    let now = OffsetDateTime::now_utc();
+    let gpu = GpuReading {
+        card: "card0".into(),
+        driver: "amdgpu".into(),
+        busy_percent: Some(37.0),
+        vram_used_bytes: Some(512),
+        vram_total_bytes: Some(2048),
+        temp_c: Some(55.0),
+        ..Default::default()
+    };
+    db.insert_gpu_sample(now, &gpu).unwrap();
+    let busy = db.fetch_series("gpu_samples", None).unwrap();
+    assert_eq!(busy.len(), 1);
+    assert_eq!(busy[0].label.as_deref(), Some("card0"));
+    let vram = db.fetch_series("gpu_vram_samples", None).unwrap();
+    assert!((vram[0].value - 25.0).abs() < f64::EPSILON);
+    assert!(db
+        .fetch_series("gpu_power_samples", None)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn events_round_trip() {
+    let tmp = NamedTempFile::new().unwrap();
+    let db = Database::connect(tmp.path()).unwrap();
+    let now = OffsetDateTime::now_utc();
+    db.insert_event(now, "boot", None, Some("abc")).unwrap();
+    db.insert_event(now, "suspend", Some(600.0), None).unwrap();
+    assert!(db.has_event("boot", "abc").unwrap());
+    assert!(!db.has_event("boot", "def").unwrap());
+    let suspends = db.fetch_events(None, Some("suspend")).unwrap();
+    assert_eq!(suspends.len(), 1);
+    assert_eq!(suspends[0].duration_secs, Some(600.0));
+    let rows = db.fetch_series("suspend_samples", None).unwrap();
+    assert_eq!(rows.len(), 1);
+}
+
+#[test]
+fn pruning_keeps_events_a_restart_dedupes_on() {
+    let tmp = NamedTempFile::new().unwrap();
+    let db = Database::connect(tmp.path()).unwrap();
+    let now = OffsetDateTime::now_utc();
+    let old = now - time::Duration::days(400);
+    db.insert_event(old, "boot", None, Some("abc")).unwrap();
+    db.insert_event(old, "quota_warning", None, Some("{}"))
+        .unwrap();
+    db.insert_event(old, "suspend", Some(600.0), None).unwrap();
+    db.prune_older_than(now - time::Duration::days(30)).unwrap();
+    let kinds: Vec<String> = db
+        .fetch_events(None, None)
+        .unwrap()
+        .into_iter()
+        .map(|e| e.kind)
+        .collect();
+    assert_eq!(kinds, vec!["boot", "quota_warning"]);
+    assert!(db.has_event("boot", "abc").unwrap());
+}
+
+#[test]
+fn readings_are_grouped_into_rows() {
+    use wtui_core::{MetricKind, MetricReading};
+    let tmp = NamedTempFile::new().unwrap();
+    let db = Database::connect(tmp.path()).unwrap();
+    let now = OffsetDateTime::now_utc();
+    let bat = |metric, value| {
+        MetricReading::new(now, MetricKind::Battery, metric, Some("BAT0"), value, None)
+    };
+    let readings = vec![
+        bat("battery_capacity", 80.0),
+        bat("battery_health", 93.5),
+        MetricReading::new(now, MetricKind::Cpu, "cpu_usage", Some("total"), 12.0, None),
+    ];
+    assert_eq!(db.insert_readings(&readings).unwrap(), 2);
+    // Uptime without a known boot id is skipped rather than filed under "".
+    let uptime = |metric| MetricReading::new(now, MetricKind::Uptime, metric, None, 60.0, None);
+    assert_eq!(
+        db.insert_readings(&[uptime("uptime"), uptime("awake")])
+            .unwrap(),
+        0
+    );
+    let health = db.fetch_series("battery_health_samples", None).unwrap();
+    assert_eq!(health.len(), 1);
+    assert!((health[0].value - 93.5).abs() < f64::EPSILON);
+    assert_eq!(db.fetch_series("cpu_samples", None).unwrap().len(), 1);
+}
+
+#[test]
+fn textfile_and_custom_series_stay_apart() {
+    use wtui_core::{MetricKind, MetricReading};
+    let tmp = NamedTempFile::new().unwrap();
+    let db = Database::connect(tmp.path()).unwrap();
+    let now = OffsetDateTime::now_utc();
+    let readings = vec![
+        MetricReading::new(now, MetricKind::Custom, "foo", None, 1.0, None),
+        MetricReading::new(now, MetricKind::Textfile, "foo", Some("a=\"b\""), 2.0, None),
+    ];
+    assert_eq!(db.insert_readings(&readings).unwrap(), 2);
+    let values = |kind| -> Vec<f64> {
+        db.fetch_custom_series(kind, "foo", None)
+            .unwrap()
+            .into_iter()
+            .map(|r| r.value)
+            .collect()
+    };
+    assert_eq!(values(MetricKind::Custom), vec![1.0]);
+    assert_eq!(values(MetricKind::Textfile), vec![2.0]);
+    assert_eq!(db.fetch_series("custom_samples", None).unwrap().len(), 1);
+    assert_eq!(db.fetch_series("textfile_samples", None).unwrap().len(), 1);
+}
+
+#[test]
+fn daemon_readings_chart_through_their_views() {
+    use wtui_core::{resolve_metric, MetricKind, MetricReading};
+    let tmp = NamedTempFile::new().unwrap();
+    let db = Database::connect(tmp.path()).unwrap();
+    let now = OffsetDateTime::now_utc();
+    let own = |metric, label, value| {
+        MetricReading::new(now, MetricKind::Daemon, metric, label, value, None)
+    };
+    let readings = vec![
+        own("daemon_read_duration", Some("cpu"), 0.002),
+        own("daemon_read_duration", Some("temps"), 0.015),
+        own("daemon_read_errors", Some("temps"), 3.0),
+        own("daemon_db_size", Some("db"), 4096.0),
+        own("daemon_db_size", Some("wal"), 8192.0),
+        own("daemon_rss", None, 9_000_000.0),
+    ];
+    assert_eq!(db.insert_readings(&readings).unwrap(), 6);
+
+    let view = |metric| resolve_metric(metric).unwrap().view;
+    let reads = db.fetch_series(view("daemon"), None).unwrap();
+    assert_eq!(reads.len(), 2);
+    assert_eq!(reads[1].label.as_deref(), Some("temps"));
+    let errors = db.fetch_series(view("daemon_read_errors"), None).unwrap();
+    assert_eq!(errors[0].value, 3.0);
+    assert_eq!(db.fetch_series(view("daemon_db"), None).unwrap().len(), 2);
+    assert_eq!(db.fetch_series(view("daemon_rss"), None).unwrap().len(), 1);
+    assert!(db
+        .fetch_series(view("daemon_write_errors"), None)
+        .unwrap()
+        .is_empty());
+}
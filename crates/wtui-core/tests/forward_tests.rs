@@ -0,0 +1,78 @@
+use wtui_core::forward::{graphite_line, influx_line};
+use wtui_core::{utc_from_timestamp, Config, ForwardTarget, MetricKind, MetricReading};
+
+#[test]
+fn readings_encode_as_influx_and_graphite_lines() {
+    let at = utc_from_timestamp(1_700_000_000);
+    let temp = MetricReading::new(
+        at,
+        MetricKind::Temps,
+        "temp",
+        Some("coretemp Package id 0"),
+        51.0,
+        Some("C"),
+    );
+    assert_eq!(
+        influx_line(&temp, "box").unwrap(),
+        "wtui_temps,host=box,sensor=coretemp\\ Package\\ id\\ 0 temp=51 1700000000000000000"
+    );
+    assert_eq!(
+        graphite_line(&temp, "lab.wtui", "box.lan").unwrap(),
+        "lab.wtui.box_lan.temps.coretemp_Package_id_0.temp 51 1700000000"
+    );
+
+    let textfile = MetricReading::new(
+        at,
+        MetricKind::Textfile,
+        "backup_age_seconds",
+        Some("job=\"home\""),
+        3600.0,
+        None,
+    );
+    assert_eq!(
+        influx_line(&textfile, "box").unwrap(),
+        "backup_age_seconds,host=box,job=home value=3600 1700000000000000000"
+    );
+
+    let nan = MetricReading::new(at, MetricKind::Custom, "probe", None, f64::NAN, None);
+    assert!(influx_line(&nan, "box").is_none());
+}
+
+#[test]
+fn forward_targets_parse_by_protocol() {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    std::io::Write::write_all(
+        &mut file,
+        br#"
+[database]
+path = "/tmp/wtui.db"
+
+[[daemon.forward]]
+protocol = "influx-http"
+url = "http://tsdb:8086/api/v2/write?org=lab&bucket=wtui"
+token = "secret"
+
+[[daemon.forward]]
+protocol = "graphite"
+address = "carbon:2003"
+queue_size = 100
+"#,
+    )
+    .unwrap();
+    let cfg = Config::load(Some(file.path())).expect("load config");
+    let forward = &cfg.daemon.forward;
+    assert_eq!(forward.len(), 2);
+    assert!(matches!(
+        &forward[0].target,
+        ForwardTarget::InfluxHttp { token: Some(t), .. } if t == "secret"
+    ));
+    assert_eq!(
+        forward[1].target,
+        ForwardTarget::Graphite {
+            address: "carbon:2003".into(),
+            prefix: "wtui".into(),
+        }
+    );
+    assert_eq!(forward[1].queue_size, 100);
+    assert_eq!(forward[1].batch_size, 500);
+}
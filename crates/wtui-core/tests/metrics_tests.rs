@@ -1,4 +1,20 @@
-use wtui_core::metrics::{cpu_usage_percent, CpuTimes};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+use wtui_core::metrics::{
+    cpu_usage_percent, cstate_residency, parse_ppd_state, read_ac_online, read_batteries,
+    read_boot_id, read_cpu_times, read_cstate_snapshot, read_net_snapshot, read_power_profile,
+    read_powercap, read_ram_usage, read_temperatures, read_throttle_snapshot, throttled_cores,
+    ClockSnapshot, CpuTimes, CstateSnapshot, HostRoots, ThrottleCounters, ThrottleTracker,
+    PACKAGE_CSTATES,
+};
+
+fn fixture(machine: &str) -> HostRoots {
+    let base = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(machine);
+    HostRoots::new(base.join("proc"), base.join("sys"))
+}
 
 #[test]
 fn cpu_usage_calculates_delta() {
@@ -25,3 +41,194 @@ fn cpu_usage_calculates_delta() {
     let usage = cpu_usage_percent(&prev, &curr).unwrap();
     assert!((usage - 66.6).abs() < 1.0);
 }
+
+#[test]
+fn suspend_is_boottime_minus_monotonic() {
+    let prev = ClockSnapshot {
+        boottime: Duration::from_secs(1000),
+        monotonic: Duration::from_secs(900),
+    };
+    let curr = ClockSnapshot {
+        boottime: Duration::from_secs(1630),
+        monotonic: Duration::from_secs(930),
+    };
+    assert_eq!(curr.suspended_since(&prev), Duration::from_secs(600));
+    assert_eq!(prev.suspended_since(&prev), Duration::ZERO);
+}
+
+#[test]
+fn ppd_state_profile_is_parsed() {
+    let content =
+        "[State]\nProfile=power-saver\nCpuDriver=amd_pstate\n\n[Other]\nProfile=ignored\n";
+    assert_eq!(parse_ppd_state(content).as_deref(), Some("power-saver"));
+    assert_eq!(parse_ppd_state("[Other]\nProfile=x\n"), None);
+}
+
+#[test]
+fn cstate_residency_averages_cores() {
+    let start = Instant::now();
+    let key = |cpu: &str, state: &str| (cpu.to_string(), state.to_string());
+    let prev = CstateSnapshot {
+        taken_at: start,
+        states: HashMap::from([
+            (key("cpu0", "C6"), (1_000_000, 10)),
+            (key("cpu1", "C6"), (2_000_000, 20)),
+        ]),
+    };
+    let curr = CstateSnapshot {
+        taken_at: start + Duration::from_secs(1),
+        states: HashMap::from([
+            (key("cpu0", "C6"), (1_800_000, 15)),
+            (key("cpu1", "C6"), (2_400_000, 22)),
+        ]),
+    };
+    let rows = cstate_residency(&prev, &curr);
+    let all = rows.iter().find(|r| r.cpu == "all").unwrap();
+    assert!((all.residency_pct - 60.0).abs() < 1e-9);
+    assert_eq!(all.usage_delta, 7);
+    assert_eq!(rows.len(), 3);
+}
+
+#[test]
+fn cstate_snapshot_reads_package_residency() {
+    let snapshot = read_cstate_snapshot(&fixture("laptop")).unwrap();
+    let key = |cpu: &str, state: &str| (cpu.to_string(), state.to_string());
+    assert_eq!(snapshot.states[&key("cpu0", "C6")], (123_456_789, 4321));
+    assert_eq!(
+        snapshot.states[&key(PACKAGE_CSTATES, "C6")],
+        (98_765_432, 0)
+    );
+    assert_eq!(snapshot.states.len(), 5);
+
+    // Package rows are reported as they are, not folded into `all`.
+    let later = CstateSnapshot {
+        taken_at: snapshot.taken_at + Duration::from_secs(1),
+        states: snapshot
+            .states
+            .iter()
+            .map(|(k, (time, usage))| (k.clone(), (time + 500_000, *usage)))
+            .collect(),
+    };
+    let rows = cstate_residency(&snapshot, &later);
+    let all = rows
+        .iter()
+        .find(|r| r.cpu == "all" && r.state == "C6")
+        .unwrap();
+    assert!((all.residency_pct - 50.0).abs() < 1e-9);
+    assert!(rows.iter().all(|r| r.cpu != "all" || r.state == "C6"));
+    assert_eq!(rows.iter().filter(|r| r.cpu == PACKAGE_CSTATES).count(), 4);
+}
+
+#[test]
+fn idle_low_clocked_cores_are_not_throttled() {
+    // Both fixture cores sit at 800 MHz of 4 GHz.
+    let prev = read_throttle_snapshot(&fixture("laptop")).unwrap();
+    assert_eq!(prev["cpu1"].cur_freq_khz, Some(800_000.0));
+    let mut curr = prev.clone();
+    let advance = |cpu: &str, user, idle| {
+        let times = prev[cpu].times.unwrap();
+        Some(CpuTimes {
+            user: times.user + user,
+            idle: times.idle + idle,
+            ..times
+        })
+    };
+    curr.get_mut("cpu0").unwrap().times = advance("cpu0", 2, 98);
+    curr.get_mut("cpu1").unwrap().times = advance("cpu1", 95, 5);
+    assert_eq!(
+        throttled_cores(&prev, &curr, Some(95.0), 90.0),
+        vec!["cpu1"]
+    );
+    assert!(throttled_cores(&prev, &curr, Some(70.0), 90.0).is_empty());
+}
+
+#[test]
+fn throttle_episode_spans_throttled_cycles() {
+    let counters = |core_count, cur, user| ThrottleCounters {
+        core_count,
+        package_count: 0,
+        cur_freq_khz: Some(cur),
+        max_freq_khz: Some(4_000_000.0),
+        times: Some(CpuTimes {
+            user,
+            idle: 100,
+            ..Default::default()
+        }),
+    };
+    let prev = HashMap::from([
+        ("cpu0".to_string(), counters(5, 3_900_000.0, 0)),
+        ("cpu1".to_string(), counters(0, 3_900_000.0, 0)),
+    ]);
+    let curr = HashMap::from([
+        ("cpu0".to_string(), counters(7, 3_900_000.0, 300)),
+        ("cpu1".to_string(), counters(0, 1_000_000.0, 300)),
+    ]);
+    assert_eq!(
+        throttled_cores(&prev, &curr, Some(70.0), 90.0),
+        vec!["cpu0"]
+    );
+    assert_eq!(
+        throttled_cores(&prev, &curr, Some(95.0), 90.0),
+        vec!["cpu0", "cpu1"]
+    );
+
+    let start = OffsetDateTime::now_utc();
+    let mut tracker = ThrottleTracker::default();
+    assert!(tracker
+        .update(start, &["cpu0".into()], Some(92.0))
+        .is_none());
+    let later = start + time::Duration::seconds(30);
+    assert!(tracker
+        .update(later, &["cpu1".into()], Some(97.0))
+        .is_none());
+    let episode = tracker
+        .update(later + time::Duration::seconds(30), &[], Some(60.0))
+        .expect("episode closes");
+    assert_eq!(episode.start, start);
+    assert_eq!(episode.end, later);
+    assert_eq!(episode.peak_temp_c, Some(97.0));
+    assert_eq!(episode.cores.len(), 2);
+}
+
+#[test]
+fn procfs_readers_use_fixture_root() {
+    let roots = fixture("laptop");
+    let cpu = read_cpu_times(&roots).unwrap();
+    assert_eq!(cpu.idle, 46828483);
+    let ram = read_ram_usage(&roots).unwrap();
+    assert_eq!(ram.total_bytes, 16067140 * 1024);
+    assert_eq!(ram.available_bytes, 9712864 * 1024);
+    assert!(read_boot_id(&roots).unwrap().starts_with("6f3c5e2a"));
+}
+
+#[test]
+fn sysfs_readers_use_fixture_root() {
+    let roots = fixture("laptop");
+
+    let mut temps = read_temperatures(&roots).unwrap();
+    temps.sort_by(|a, b| a.sensor.cmp(&b.sensor));
+    let sensors: Vec<_> = temps.iter().map(|t| t.sensor.as_str()).collect();
+    assert_eq!(
+        sensors,
+        vec!["acpitz:temp1", "coretemp:Core 0", "coretemp:Package id 0"]
+    );
+    assert!((temps[2].value_c - 61.0).abs() < f64::EPSILON);
+
+    let batteries = read_batteries(&roots).unwrap();
+    assert_eq!(batteries.len(), 1, "mains supply is skipped");
+    let bat = &batteries[0];
+    assert_eq!(bat.name, "BAT0");
+    assert_eq!(bat.capacity, Some(83.0));
+    assert!((bat.health.unwrap() - 89.37).abs() < 0.01);
+    assert_eq!(bat.energy_now_uw, Some(7621.0));
+    assert_eq!(read_ac_online(&roots).unwrap(), Some(false));
+
+    let power = read_powercap(&roots).unwrap();
+    assert_eq!(power[0].domain, "package-0");
+
+    let net = read_net_snapshot(&roots, "wlp3s0").unwrap();
+    assert_eq!((net.rx_bytes, net.tx_bytes), (1873264, 409812));
+
+    let profile = read_power_profile(&roots).unwrap().unwrap();
+    assert_eq!(profile.profile, "balanced");
+}
@@ -0,0 +1,63 @@
+use wtui_core::mqtt::{broker_address, discovery, state_topic};
+use wtui_core::{now_utc, MetricKind, MetricReading};
+
+#[test]
+fn readings_map_to_topics_and_discovery_configs() {
+    let usage = MetricReading::new(
+        now_utc(),
+        MetricKind::Disk,
+        "disk_usage",
+        Some("/"),
+        70.5,
+        Some("%"),
+    );
+    assert_eq!(
+        state_topic("wtui", "box", &usage),
+        "wtui/box/disk/disk_usage/root"
+    );
+
+    let config =
+        discovery(&usage, "homeassistant", "wtui", "box").expect("disk usage is announced");
+    assert_eq!(
+        config.topic,
+        "homeassistant/sensor/wtui_box/disk_usage_root/config"
+    );
+    let payload: serde_json::Value = serde_json::from_str(&config.payload).unwrap();
+    assert_eq!(payload["state_topic"], "wtui/box/disk/disk_usage/root");
+    assert_eq!(payload["availability_topic"], "wtui/box/status");
+    assert_eq!(payload["unit_of_measurement"], "%");
+
+    let temp = MetricReading::new(
+        now_utc(),
+        MetricKind::Temps,
+        "temp",
+        Some("coretemp Package id 0"),
+        48.0,
+        Some("C"),
+    );
+    let config = discovery(&temp, "homeassistant", "wtui", "box").unwrap();
+    let payload: serde_json::Value = serde_json::from_str(&config.payload).unwrap();
+    assert_eq!(payload["device_class"], "temperature");
+
+    let cpu = MetricReading::new(
+        now_utc(),
+        MetricKind::Cpu,
+        "cpu_usage",
+        Some("total"),
+        3.0,
+        Some("%"),
+    );
+    assert!(discovery(&cpu, "homeassistant", "wtui", "box").is_none());
+}
+
+#[test]
+fn broker_addresses_split_host_and_port() {
+    let parse = |broker| broker_address(broker).unwrap();
+    assert_eq!(parse("homeserver"), ("homeserver".into(), 1883));
+    assert_eq!(parse("homeserver:8883"), ("homeserver".into(), 8883));
+    assert_eq!(parse("[::1]:8883"), ("::1".into(), 8883));
+    assert_eq!(parse("[fd00::2]"), ("fd00::2".into(), 1883));
+    assert_eq!(parse("fd00::2"), ("fd00::2".into(), 1883));
+    assert!(broker_address("homeserver:mqtt").is_err());
+    assert!(broker_address("[::1:8883").is_err());
+}
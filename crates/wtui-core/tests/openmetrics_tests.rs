@@ -0,0 +1,86 @@
+use time::OffsetDateTime;
+use wtui_core::control::{Backlog, DaemonStatus, SourceHealth};
+use wtui_core::openmetrics::Exposition;
+use wtui_core::{MetricKind, MetricReading};
+
+#[test]
+fn readings_render_as_openmetrics_families() {
+    let now = OffsetDateTime::now_utc();
+    let mut exposition = Exposition::new();
+    for reading in [
+        MetricReading::new(
+            now,
+            MetricKind::Net,
+            "net_rx_bytes",
+            Some("wlp3s0"),
+            1024.0,
+            Some("B"),
+        ),
+        MetricReading::new(
+            now,
+            MetricKind::Temps,
+            "temp",
+            Some("coretemp \"pkg\""),
+            48.5,
+            Some("C"),
+        ),
+        MetricReading::new(
+            now,
+            MetricKind::Power,
+            "power_draw",
+            Some("package-0"),
+            4500.0,
+            Some("mW"),
+        ),
+        MetricReading::new(
+            now,
+            MetricKind::Textfile,
+            "node_backup_ok",
+            Some("job=\"home\""),
+            1.0,
+            None,
+        ),
+    ] {
+        exposition.push_reading(&reading);
+    }
+    let text = exposition.render();
+
+    assert!(text.contains("# TYPE wtui_net_rx_bytes counter\n# UNIT wtui_net_rx_bytes bytes\n"));
+    assert!(text.contains("wtui_net_rx_bytes_total{interface=\"wlp3s0\"} 1024\n"));
+    assert!(text.contains("wtui_temp_celsius{sensor=\"coretemp \\\"pkg\\\"\"} 48.5\n"));
+    assert!(text.contains("wtui_power_draw_watts{domain=\"package-0\"} 4.5\n"));
+    assert!(text.contains("node_backup_ok{job=\"home\"} 1\n"));
+    assert!(text.ends_with("# EOF\n"));
+}
+
+#[test]
+fn sink_backlogs_render_with_the_daemon_status() {
+    let status = DaemonStatus {
+        pid: 42,
+        started_at: OffsetDateTime::now_utc(),
+        paused: false,
+        on_battery: None,
+        last_cycle: None,
+        last_cycle_secs: None,
+        sources: Vec::new(),
+        sinks: vec![SourceHealth {
+            name: "sqlite".into(),
+            failures: 3,
+            backlog: Some(Backlog {
+                queued: 120,
+                spooled_bytes: 4096,
+                dropped: 7,
+            }),
+            ..Default::default()
+        }],
+    };
+    let mut exposition = Exposition::new();
+    exposition.push_status(&status);
+    let text = exposition.render();
+
+    assert!(text.contains("wtui_sink_up{sink=\"sqlite\"} 0\n"));
+    assert!(text.contains("wtui_sink_backlog_readings{sink=\"sqlite\"} 120\n"));
+    assert!(text.contains("wtui_sink_spool_bytes{sink=\"sqlite\"} 4096\n"));
+    assert!(text.contains("# TYPE wtui_sink_dropped_readings counter\n"));
+    assert!(text.contains("wtui_sink_dropped_readings_total{sink=\"sqlite\"} 7\n"));
+}
@@ -0,0 +1,96 @@
+use tempfile::NamedTempFile;
+use time::macros::datetime;
+use wtui_core::metrics::NetSnapshot;
+use wtui_core::quota::{billing_period, parse_bytes, quota_usage};
+use wtui_core::{Config, Database, QuotaDirection};
+
+#[test]
+fn billing_periods_follow_the_reset_day() {
+    let (start, end) = billing_period(datetime!(2024-03-20 12:00 UTC), 15);
+    assert_eq!(start, datetime!(2024-03-15 0:00 UTC));
+    assert_eq!(end, datetime!(2024-04-15 0:00 UTC));
+
+    // Before the reset day the cycle started last month, across a new year.
+    let (start, end) = billing_period(datetime!(2024-01-03 12:00 UTC), 15);
+    assert_eq!(start, datetime!(2023-12-15 0:00 UTC));
+    assert_eq!(end, datetime!(2024-01-15 0:00 UTC));
+
+    // A reset on the 31st falls on the last day of short months.
+    let (start, end) = billing_period(datetime!(2023-02-28 8:00 UTC), 31);
+    assert_eq!(start, datetime!(2023-02-28 0:00 UTC));
+    assert_eq!(end, datetime!(2023-03-31 0:00 UTC));
+}
+
+#[test]
+fn quotas_sum_deltas_of_the_current_cycle() {
+    let mut file = NamedTempFile::new().unwrap();
+    std::io::Write::write_all(
+        &mut file,
+        br#"
+[database]
+path = "/tmp/wtui.db"
+
+[[quotas]]
+interface = "wwan0"
+limit = "10 GB"
+reset_day = 10
+"#,
+    )
+    .unwrap();
+    let cfg = Config::load(Some(file.path())).expect("load config");
+    let quota = &cfg.quotas[0];
+    assert_eq!(quota.limit, 10_000_000_000);
+    assert_eq!(quota.direction, QuotaDirection::Total);
+    assert_eq!(quota.warn_at, vec![80.0, 90.0, 100.0]);
+    assert_eq!(parse_bytes("1.5GiB").unwrap(), 1_610_612_736);
+    assert!(parse_bytes("12 parsecs").is_err());
+
+    let tmp = NamedTempFile::new().unwrap();
+    let db = Database::connect(tmp.path()).unwrap();
+    let snap = NetSnapshot {
+        rx_bytes: 0,
+        tx_bytes: 0,
+    };
+    // Last cycle, another interface and a counter reset don't count.
+    db.insert_net_sample(
+        datetime!(2024-05-09 23:00 UTC),
+        "wwan0",
+        snap,
+        Some((1_000_000_000, 0)),
+        false,
+    )
+    .unwrap();
+    db.insert_net_sample(
+        datetime!(2024-05-11 0:00 UTC),
+        "eth0",
+        snap,
+        Some((5_000_000_000, 0)),
+        false,
+    )
+    .unwrap();
+    db.insert_net_sample(
+        datetime!(2024-05-11 0:00 UTC),
+        "wwan0",
+        snap,
+        Some((3_000_000_000, 1_000_000_000)),
+        false,
+    )
+    .unwrap();
+    db.insert_net_sample(
+        datetime!(2024-05-12 0:00 UTC),
+        "wwan0",
+        snap,
+        Some((-500, 0)),
+        true,
+    )
+    .unwrap();
+
+    let usage = quota_usage(&db, quota, datetime!(2024-05-13 0:00 UTC)).unwrap();
+    assert_eq!(usage.used, 4_000_000_000);
+    assert_eq!(usage.remaining(), 6_000_000_000);
+    assert_eq!(usage.percent(), 40.0);
+    // 4 GB in 3 of the cycle's 31 days.
+    assert_eq!(usage.projected(), 41_333_333_333);
+    assert_eq!(usage.threshold_reached(&quota.warn_at), None);
+    assert_eq!(usage.threshold_reached(&[25.0, 30.0, 50.0]), Some(30.0));
+}
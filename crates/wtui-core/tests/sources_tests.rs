@@ -0,0 +1,189 @@
+use anyhow::Context;
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+use wtui_core::metrics::NetSnapshot;
+use wtui_core::{Config, Database, MetricKind, MetricReading, MetricSource, SourceRegistry};
+
+struct Fixed(&'static str);
+
+impl MetricSource for Fixed {
+    fn name(&self) -> &str {
+        self.0
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Custom
+    }
+
+    fn read(&mut self, now: OffsetDateTime) -> anyhow::Result<Vec<MetricReading>> {
+        Ok(vec![MetricReading::new(
+            now,
+            MetricKind::Custom,
+            self.0,
+            None,
+            1.0,
+            None,
+        )])
+    }
+}
+
+#[test]
+fn sources_run_on_their_own_intervals() {
+    let mut registry = SourceRegistry::default();
+    registry.push(Box::new(Fixed("fast")), Duration::from_secs(5));
+    registry.push(Box::new(Fixed("slow")), Duration::from_secs(30));
+    // Monotonic and wall clock advance together; 1_700_000_040 is a
+    // multiple of 30s.
+    let start = Instant::now();
+    let wall0 = OffsetDateTime::from_unix_timestamp(1_700_000_022).unwrap();
+    let due = |registry: &mut SourceRegistry, secs: u64| -> Vec<(i64, String)> {
+        let at = start + Duration::from_secs(secs);
+        let wall = wall0 + time::Duration::seconds(secs as i64);
+        registry
+            .due_mut(at, wall)
+            .into_iter()
+            .map(|(stamp, s)| (stamp.unix_timestamp() - 1_700_000_000, s.name().to_string()))
+            .collect()
+    };
+    let names = |due: Vec<(i64, String)>| -> Vec<String> { due.into_iter().map(|d| d.1).collect() };
+
+    // The first read is immediate, later ones land on wall-clock multiples.
+    assert_eq!(names(due(&mut registry, 0)), vec!["fast", "slow"]);
+    assert_eq!(registry.next_due(), Some(start + Duration::from_secs(3)));
+    assert!(due(&mut registry, 2).is_empty());
+    assert_eq!(due(&mut registry, 3), vec![(25, "fast".to_string())]);
+    assert_eq!(registry.next_due(), Some(start + Duration::from_secs(8)));
+    // A slow cycle doesn't push later slots back.
+    assert_eq!(due(&mut registry, 9), vec![(30, "fast".to_string())]);
+    assert_eq!(registry.next_due(), Some(start + Duration::from_secs(13)));
+    // Running far behind skips the missed slots instead of bursting.
+    assert_eq!(
+        due(&mut registry, 41),
+        vec![(60, "fast".to_string()), (40, "slow".to_string())]
+    );
+    assert_eq!(registry.next_due(), Some(start + Duration::from_secs(43)));
+}
+
+/// Counts its reads and, like delta sources, reports nothing on the first.
+/// Fails once the count would overflow.
+struct Counter(u32);
+
+impl MetricSource for Counter {
+    fn name(&self) -> &str {
+        "counter"
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Custom
+    }
+
+    fn read(&mut self, now: OffsetDateTime) -> anyhow::Result<Vec<MetricReading>> {
+        self.0 = self.0.checked_add(1).context("counter wrapped")?;
+        let value = self.0 as f64;
+        Ok((self.0 > 1)
+            .then(|| MetricReading::new(now, MetricKind::Custom, "counter", None, value, None))
+            .into_iter()
+            .collect())
+    }
+
+    fn needs_baseline(&self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn priming_reads_only_delta_sources() {
+    let now = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+    let mut registry = SourceRegistry::default();
+    registry.push(Box::new(Fixed("plain")), Duration::from_secs(30));
+    registry.push(Box::new(Counter(0)), Duration::from_secs(30));
+    assert!(registry.prime(now).is_some_and(|errors| errors.is_empty()));
+
+    let collected = registry.collect(now);
+    let values: Vec<(&str, f64)> = collected
+        .readings
+        .iter()
+        .map(|r| (r.metric.as_str(), r.value))
+        .collect();
+    assert_eq!(values, vec![("plain", 1.0), ("counter", 2.0)]);
+
+    let mut plain = SourceRegistry::default();
+    plain.push(Box::new(Fixed("plain")), Duration::from_secs(30));
+    assert!(plain.prime(now).is_none());
+
+    let mut failing = SourceRegistry::default();
+    failing.push(Box::new(Counter(u32::MAX)), Duration::from_secs(30));
+    let errors = failing.prime(now).unwrap();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].0, "counter");
+}
+
+#[test]
+fn rescheduling_keeps_existing_sources() {
+    let now = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+    let mut registry = SourceRegistry::default();
+    registry.push(Box::new(Counter(0)), Duration::from_secs(5));
+    registry.push(Box::new(Fixed("dropped")), Duration::from_secs(5));
+    registry.collect(now);
+
+    let mut next = SourceRegistry::default();
+    next.push(Box::new(Fixed("added")), Duration::from_secs(60));
+    next.push(Box::new(Counter(0)), Duration::from_secs(60));
+    assert!(registry.reschedule(next).is_empty());
+    assert_eq!(registry.names(), vec!["added", "counter"]);
+
+    // The counter kept its baseline, and its new interval makes it due now.
+    let due = registry.due_mut(Instant::now(), now);
+    assert_eq!(due.len(), 2);
+    let collected = registry.collect(now);
+    let values: Vec<(&str, f64)> = collected
+        .readings
+        .iter()
+        .map(|r| (r.metric.as_str(), r.value))
+        .collect();
+    assert_eq!(values, vec![("added", 1.0), ("counter", 2.0)]);
+}
+
+#[test]
+fn resumed_net_deltas_start_from_the_stored_counters() {
+    let fixture = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/laptop");
+    let mut config = Config::default();
+    config.daemon.metrics = vec![MetricKind::Net];
+    config.daemon.proc_root = fixture.join("proc");
+    config.daemon.sys_root = fixture.join("sys");
+    let now = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+    let stored_at = now - time::Duration::hours(1);
+    let tmp = tempfile::NamedTempFile::new().unwrap();
+    let db = Database::connect(tmp.path()).unwrap();
+    let snap = NetSnapshot {
+        rx_bytes: 1_000_000,
+        tx_bytes: 400_000,
+    };
+    db.insert_net_sample(stored_at, "wlp3s0", snap, None, false)
+        .unwrap();
+
+    let values = |boot_started| -> Vec<(String, f64)> {
+        let mut registry = SourceRegistry::from_config(&config);
+        assert!(registry.resume(&db, boot_started).is_empty());
+        assert!(registry.prime(now).is_none());
+        registry
+            .collect(now)
+            .readings
+            .into_iter()
+            .filter(|r| !r.metric.ends_with("_bytes"))
+            .map(|r| (r.metric, r.value))
+            .collect()
+    };
+    assert_eq!(
+        values(Some(stored_at - time::Duration::days(1))),
+        vec![
+            ("net_rx_delta".to_string(), 873_264.0),
+            ("net_tx_delta".to_string(), 9_812.0),
+            ("net_delta".to_string(), 883_076.0),
+        ]
+    );
+    // Counters from before this boot can't be subtracted from.
+    let reset = vec![("net_reset".to_string(), 1.0)];
+    assert_eq!(values(Some(now - time::Duration::minutes(5))), reset);
+    assert_eq!(values(None), reset);
+}
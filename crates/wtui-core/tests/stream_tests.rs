@@ -0,0 +1,53 @@
+use std::io::Write;
+use std::os::unix::net::UnixListener;
+use std::time::Duration;
+use time::OffsetDateTime;
+use wtui_core::stream::{encode_line, subscribe, HistoryRing};
+use wtui_core::{MetricKind, MetricReading};
+
+fn reading(at: OffsetDateTime, value: f64) -> MetricReading {
+    MetricReading::new(
+        at,
+        MetricKind::Cpu,
+        "cpu_usage",
+        Some("total"),
+        value,
+        Some("%"),
+    )
+}
+
+#[test]
+fn history_ring_evicts_by_age_and_count() {
+    let start = OffsetDateTime::now_utc();
+    let mut ring = HistoryRing::new(Duration::from_secs(60), 3);
+    for secs in [0, 30, 50] {
+        ring.push(reading(start + time::Duration::seconds(secs), secs as f64));
+    }
+    assert_eq!(ring.len(), 3);
+    ring.push(reading(start + time::Duration::seconds(100), 100.0));
+    let values: Vec<f64> = ring.iter().map(|r| r.value).collect();
+    assert_eq!(values, vec![50.0, 100.0]);
+}
+
+#[test]
+fn subscriber_receives_streamed_readings() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("stream.sock");
+    let listener = UnixListener::bind(&path).unwrap();
+    let sent = reading(
+        OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap(),
+        42.0,
+    );
+    let line = encode_line(&sent).unwrap();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        stream.write_all(line.as_bytes()).unwrap();
+    });
+
+    let rx = subscribe(&path).unwrap();
+    let received = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    server.join().unwrap();
+    assert_eq!(received, sent);
+    // The channel disconnects once the publisher goes away.
+    assert!(rx.recv_timeout(Duration::from_secs(5)).is_err());
+}
@@ -0,0 +1,41 @@
+use std::fs;
+use tempfile::tempdir;
+use wtui_core::textfile::{parse_exposition, read_textfile_dir};
+
+#[test]
+fn parses_exposition_lines() {
+    let content = r#"
+# HELP backup_last_success_seconds Last successful backup.
+# TYPE backup_last_success_seconds gauge
+backup_last_success_seconds 1.7e9
+backup_size_bytes{host="nas",path="/srv"} 1024 1700000000000
+escaped{msg="say \"hi\"\\n"} 1
+nan_metric NaN
+"#;
+    let samples = parse_exposition(content).unwrap();
+    assert_eq!(samples.len(), 3);
+    assert_eq!(samples[0].name, "backup_last_success_seconds");
+    assert_eq!(samples[0].label_string(), None);
+    assert_eq!(
+        samples[1].label_string().as_deref(),
+        Some(r#"host="nas",path="/srv""#)
+    );
+    assert_eq!(samples[2].labels[0].1, "say \"hi\"\\n");
+    assert!(parse_exposition("bad metric{ 1").is_err());
+}
+
+#[test]
+fn scans_only_prom_files_and_reports_bad_ones() {
+    let dir = tempdir().unwrap();
+    fs::write(
+        dir.path().join("a.prom"),
+        "jobs_pending{queue=\"mail\"} 3\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("b.prom"), "broken{ 1\n").unwrap();
+    fs::write(dir.path().join("c.prom.tmp"), "ignored 1\n").unwrap();
+    let scan = read_textfile_dir(dir.path()).unwrap();
+    assert_eq!(scan.readings.len(), 1);
+    assert_eq!(scan.readings[0].name, "jobs_pending");
+    assert_eq!(scan.errors.len(), 1);
+}
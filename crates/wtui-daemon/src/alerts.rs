@@ -0,0 +1,80 @@
+use tracing::{info, warn};
+use wtui_core::alerts::{AlertEngine, AlertState, AlertTransition};
+use wtui_core::{AlertAction, EventRow};
+
+use crate::hook;
+
+/// Carries out the configured actions of every transition, returning the
+/// events to record.
+pub fn dispatch(engine: &AlertEngine, transitions: Vec<AlertTransition>) -> Vec<EventRow> {
+    let mut events = Vec::new();
+    for transition in transitions {
+        let Some(config) = engine.config(&transition.rule) else {
+            continue;
+        };
+        for action in &config.actions {
+            match action {
+                AlertAction::Log => log(&transition),
+                AlertAction::Event => events.push(event(&transition)),
+                AlertAction::Command => {
+                    if let Some(command) = &config.command {
+                        run(command, &config.rule, &transition);
+                    }
+                }
+            }
+        }
+    }
+    events
+}
+
+fn describe(transition: &AlertTransition) -> String {
+    match &transition.label {
+        Some(label) => format!("{}{{{label}}} = {}", transition.metric, transition.value),
+        None => format!("{} = {}", transition.metric, transition.value),
+    }
+}
+
+fn log(transition: &AlertTransition) {
+    match transition.state {
+        AlertState::Firing => warn!("alert {} firing: {}", transition.rule, describe(transition)),
+        AlertState::Resolved => info!(
+            "alert {} resolved: {}",
+            transition.rule,
+            describe(transition)
+        ),
+    }
+}
+
+/// `alert` when a rule starts firing, `alert_resolved` (with how long it
+/// fired) when it stops.
+fn event(transition: &AlertTransition) -> EventRow {
+    let (kind, duration) = match transition.state {
+        AlertState::Firing => ("alert", None),
+        AlertState::Resolved => (
+            "alert_resolved",
+            Some((transition.at - transition.since).as_seconds_f64()),
+        ),
+    };
+    let detail = serde_json::to_string(transition).ok();
+    EventRow::new(transition.at, kind, duration, detail.as_deref())
+}
+
+fn run(command: &str, rule: &str, transition: &AlertTransition) {
+    let env = [
+        ("WTUI_ALERT_NAME", transition.rule.clone()),
+        ("WTUI_ALERT_STATE", transition.state.as_str().to_string()),
+        ("WTUI_ALERT_RULE", rule.to_string()),
+        ("WTUI_ALERT_METRIC", transition.metric.clone()),
+        (
+            "WTUI_ALERT_LABEL",
+            transition.label.clone().unwrap_or_default(),
+        ),
+        ("WTUI_ALERT_VALUE", transition.value.to_string()),
+        ("WTUI_ALERT_THRESHOLD", transition.threshold.to_string()),
+        (
+            "WTUI_ALERT_SINCE",
+            transition.since.unix_timestamp().to_string(),
+        ),
+    ];
+    hook::spawn(format!("alert {}", transition.rule), command, &env);
+}
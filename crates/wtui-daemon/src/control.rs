@@ -0,0 +1,96 @@
+use anyhow::{Context, Result};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, warn};
+use wtui_core::control::{ControlRequest, ControlResponse};
+
+use crate::Wakeup;
+
+/// How long a client waits for the main loop to pick up its command.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A command received on the socket, waiting for the main loop to answer.
+pub struct PendingCommand {
+    pub request: ControlRequest,
+    reply: Sender<ControlResponse>,
+}
+
+impl PendingCommand {
+    pub fn respond(self, response: ControlResponse) {
+        let _ = self.reply.send(response);
+    }
+}
+
+/// Listens on the control socket and hands commands to the main loop, which
+/// owns the database and sources. The socket file is removed on drop.
+pub struct ControlServer {
+    path: PathBuf,
+}
+
+impl ControlServer {
+    pub fn spawn(
+        path: &Path,
+        commands: Sender<PendingCommand>,
+        wakeup: Arc<Wakeup>,
+    ) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        // The PID guard already ensures we are the only daemon, so anything
+        // left here is a stale socket from an unclean exit.
+        let _ = std::fs::remove_file(path);
+        let listener =
+            UnixListener::bind(path).with_context(|| format!("binding control socket {path:?}"))?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        if let Err(err) = serve(stream, &commands, &wakeup) {
+                            debug!("control client error: {err:#}");
+                        }
+                    }
+                    Err(err) => warn!("control socket accept failed: {err}"),
+                }
+            }
+        });
+        Ok(Self {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for ControlServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+fn serve(stream: UnixStream, commands: &Sender<PendingCommand>, wakeup: &Wakeup) -> Result<()> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let mut line = String::new();
+    BufReader::new(&stream).read_line(&mut line)?;
+    let response = match serde_json::from_str::<ControlRequest>(&line) {
+        Ok(request) => {
+            let (reply, replies) = mpsc::channel();
+            commands
+                .send(PendingCommand { request, reply })
+                .context("daemon is shutting down")?;
+            wakeup.notify();
+            replies
+                .recv_timeout(REPLY_TIMEOUT)
+                .unwrap_or_else(|_| ControlResponse::error("daemon did not answer in time"))
+        }
+        Err(err) => ControlResponse::error(format!("invalid command: {err}")),
+    };
+    let mut out = serde_json::to_string(&response)?;
+    out.push('\n');
+    (&stream).write_all(out.as_bytes())?;
+    Ok(())
+}
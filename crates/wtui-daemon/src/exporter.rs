@@ -0,0 +1,103 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use wtui_core::control::DaemonStatus;
+use wtui_core::metrics::MetricReading;
+
+#[derive(Default)]
+struct Shared {
+    /// Latest readings per source, replaced wholesale on each read so
+    /// vanished instances (an unplugged interface) drop out.
+    latest: HashMap<String, Vec<MetricReading>>,
+    status: Option<DaemonStatus>,
+}
+
+/// Serves the latest readings and the daemon's health on `/metrics` in the
+/// OpenMetrics text format.
+pub struct Exporter {
+    shared: Arc<Mutex<Shared>>,
+    #[cfg(feature = "prometheus")]
+    server: Arc<tiny_http::Server>,
+    #[cfg(feature = "prometheus")]
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl Exporter {
+    #[cfg(feature = "prometheus")]
+    pub fn spawn(addr: SocketAddr) -> Result<Self> {
+        let server = tiny_http::Server::http(addr)
+            .map_err(|err| anyhow::anyhow!("binding exporter to {addr}: {err}"))?;
+        let server = Arc::new(server);
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let (serve_server, serve_shared) = (server.clone(), shared.clone());
+        let thread = std::thread::spawn(move || {
+            for request in serve_server.incoming_requests() {
+                if let Err(err) = serve(request, &serve_shared) {
+                    tracing::debug!("exporter client error: {err}");
+                }
+            }
+        });
+        tracing::info!("serving metrics on http://{addr}/metrics");
+        Ok(Self {
+            shared,
+            server,
+            thread: Some(thread),
+        })
+    }
+
+    #[cfg(not(feature = "prometheus"))]
+    pub fn spawn(_addr: SocketAddr) -> Result<Self> {
+        anyhow::bail!("wtui-daemon was built without the `prometheus` feature")
+    }
+
+    pub fn publish(&self, source: &str, readings: &[MetricReading]) {
+        let mut shared = self.shared.lock().unwrap();
+        shared.latest.insert(source.to_string(), readings.to_vec());
+    }
+
+    /// Updates the self-metrics and forgets sources no longer scheduled.
+    pub fn set_status(&self, status: DaemonStatus) {
+        let mut shared = self.shared.lock().unwrap();
+        shared
+            .latest
+            .retain(|name, _| status.sources.iter().any(|s| &s.name == name));
+        shared.status = Some(status);
+    }
+}
+
+#[cfg(feature = "prometheus")]
+impl Drop for Exporter {
+    /// Stops serving and releases the port before the daemon lets go of
+    /// its PID file, so a replacement can bind it straight away.
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+#[cfg(feature = "prometheus")]
+fn serve(request: tiny_http::Request, shared: &Mutex<Shared>) -> std::io::Result<()> {
+    use tiny_http::{Header, Method, Response};
+    use wtui_core::openmetrics::{Exposition, CONTENT_TYPE};
+
+    let path = request.url().split('?').next().unwrap_or_default();
+    if *request.method() != Method::Get || path != "/metrics" {
+        return request.respond(Response::from_string("not found\n").with_status_code(404));
+    }
+    let body = {
+        let shared = shared.lock().unwrap();
+        let mut exposition = Exposition::new();
+        for reading in shared.latest.values().flatten() {
+            exposition.push_reading(reading);
+        }
+        if let Some(status) = &shared.status {
+            exposition.push_status(status);
+        }
+        exposition.render()
+    };
+    let header = Header::from_bytes("Content-Type", CONTENT_TYPE).expect("valid header");
+    request.respond(Response::from_string(body).with_header(header))
+}
@@ -0,0 +1,519 @@
+use anyhow::{Context, Result};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tracing::{debug, warn};
+use wtui_core::control::Backlog;
+use wtui_core::forward::{graphite_line, influx_line};
+use wtui_core::metrics::MetricReading;
+use wtui_core::{ForwardConfig, ForwardTarget};
+
+use crate::sink::Sink;
+
+/// Connect, write and HTTP timeout for one batch.
+const IO_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+/// Largest UDP payload; InfluxDB reads datagrams into a 64 KiB buffer, but
+/// smaller ones survive fragmentation better.
+const UDP_PAYLOAD: usize = 8 * 1024;
+
+#[derive(Default)]
+struct Queue {
+    lines: VecDeque<String>,
+    /// Lines discarded since the last warning because the queue was full.
+    dropped: u64,
+    /// Lines discarded since the forwarder started.
+    dropped_total: u64,
+    /// Why the last batch failed to send; cleared by the next success.
+    last_error: Option<String>,
+    closed: bool,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    ready: Condvar,
+    capacity: usize,
+}
+
+impl Shared {
+    /// Appends lines, dropping the oldest beyond capacity.
+    fn push_back(&self, lines: impl IntoIterator<Item = String>) {
+        let mut queue = self.queue.lock().unwrap();
+        queue.lines.extend(lines);
+        let excess = queue.lines.len().saturating_sub(self.capacity);
+        queue.lines.drain(..excess);
+        queue.dropped += excess as u64;
+        queue.dropped_total += excess as u64;
+        self.ready.notify_one();
+    }
+
+    /// Puts a failed batch back in front, as far as room allows.
+    fn requeue(&self, batch: Vec<String>) {
+        let mut queue = self.queue.lock().unwrap();
+        let room = self.capacity.saturating_sub(queue.lines.len());
+        let skip = batch.len().saturating_sub(room);
+        queue.dropped += skip as u64;
+        queue.dropped_total += skip as u64;
+        for line in batch.into_iter().skip(skip).rev() {
+            queue.lines.push_front(line);
+        }
+    }
+}
+
+/// Feeds one `[[daemon.forward]]` target from a background thread, so a
+/// slow or unreachable endpoint never holds up collection. Lines wait in a
+/// bounded queue and go out in batches, retried with backoff; dropping the
+/// forwarder makes one last attempt to flush what is queued.
+pub struct Forwarder {
+    name: String,
+    target: ForwardTarget,
+    host: String,
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Forwarder {
+    pub fn spawn(config: &ForwardConfig, default_host: &str) -> Result<Self> {
+        let mut connection = Connection::new(&config.target)?;
+        let name = connection.describe();
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue::default()),
+            ready: Condvar::new(),
+            capacity: config.queue_size.max(1),
+        });
+        let batch_size = config.batch_size.max(1);
+        let thread_shared = shared.clone();
+        let thread_name = name.clone();
+        let thread = thread::Builder::new()
+            .name(format!("forward {name}"))
+            .spawn(move || run(&thread_shared, &mut connection, batch_size, &thread_name))?;
+        Ok(Self {
+            name,
+            target: config.target.clone(),
+            host: config
+                .host
+                .clone()
+                .unwrap_or_else(|| default_host.to_string()),
+            shared,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Sink for Forwarder {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    /// Only queues the lines, but fails while the sending thread can't
+    /// deliver, so the endpoint's state shows up in the sink's health.
+    fn write(&mut self, readings: &[MetricReading]) -> Result<()> {
+        let lines = readings.iter().filter_map(|r| match &self.target {
+            ForwardTarget::InfluxHttp { .. } | ForwardTarget::InfluxUdp { .. } => {
+                influx_line(r, &self.host)
+            }
+            ForwardTarget::Graphite { prefix, .. } => graphite_line(r, prefix, &self.host),
+        });
+        self.shared.push_back(lines);
+        match &self.shared.queue.lock().unwrap().last_error {
+            Some(err) => anyhow::bail!("{err}"),
+            None => Ok(()),
+        }
+    }
+
+    fn backlog(&self) -> Option<Backlog> {
+        let queue = self.shared.queue.lock().unwrap();
+        Some(Backlog {
+            queued: queue.lines.len() as u64,
+            spooled_bytes: 0,
+            dropped: queue.dropped_total,
+        })
+    }
+}
+
+impl Drop for Forwarder {
+    fn drop(&mut self) {
+        self.shared.queue.lock().unwrap().closed = true;
+        self.shared.ready.notify_one();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn run(shared: &Shared, connection: &mut Connection, batch_size: usize, name: &str) {
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        let (batch, closed) = {
+            let mut queue = shared.queue.lock().unwrap();
+            while queue.lines.is_empty() && !queue.closed {
+                queue = shared.ready.wait(queue).unwrap();
+            }
+            if queue.dropped > 0 {
+                warn!("{name}: queue full, dropped {} lines", queue.dropped);
+                queue.dropped = 0;
+            }
+            if queue.lines.is_empty() {
+                return;
+            }
+            let take = batch_size.min(queue.lines.len());
+            let batch: Vec<String> = queue.lines.drain(..take).collect();
+            (batch, queue.closed)
+        };
+        match connection.send(&batch) {
+            Ok(()) => {
+                debug!("{name}: sent {} lines", batch.len());
+                shared.queue.lock().unwrap().last_error = None;
+                backoff = Duration::from_secs(1);
+            }
+            Err(err) if closed => {
+                let pending = batch.len() + shared.queue.lock().unwrap().lines.len();
+                warn!("{name}: {err:#}; dropping {pending} unsent lines on shutdown");
+                return;
+            }
+            Err(err) => {
+                warn!("{name}: {err:#}; retrying in {backoff:?}");
+                shared.requeue(batch);
+                let mut queue = shared.queue.lock().unwrap();
+                queue.last_error = Some(format!("{err:#}"));
+                let _ = shared
+                    .ready
+                    .wait_timeout_while(queue, backoff, |q| !q.closed)
+                    .unwrap();
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+enum Connection {
+    InfluxHttp {
+        agent: ureq::Agent,
+        url: String,
+        token: Option<String>,
+    },
+    InfluxUdp {
+        address: String,
+        socket: Option<UdpSocket>,
+    },
+    Graphite {
+        address: String,
+        stream: Option<TcpStream>,
+    },
+}
+
+impl Connection {
+    fn new(target: &ForwardTarget) -> Result<Self> {
+        Ok(match target {
+            ForwardTarget::InfluxHttp { url, token } => {
+                if !url.starts_with("http://") {
+                    anyhow::bail!(
+                        "unsupported InfluxDB URL {url:?}: only http:// is built in, \
+                         put a local TLS proxy in front of https endpoints"
+                    );
+                }
+                Connection::InfluxHttp {
+                    agent: ureq::AgentBuilder::new().timeout(IO_TIMEOUT).build(),
+                    url: url.clone(),
+                    token: token.clone(),
+                }
+            }
+            ForwardTarget::InfluxUdp { address } => Connection::InfluxUdp {
+                address: address.clone(),
+                socket: None,
+            },
+            ForwardTarget::Graphite { address, .. } => Connection::Graphite {
+                address: address.clone(),
+                stream: None,
+            },
+        })
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Connection::InfluxHttp { url, .. } => format!("influx {url}"),
+            Connection::InfluxUdp { address, .. } => format!("influx udp://{address}"),
+            Connection::Graphite { address, .. } => format!("graphite {address}"),
+        }
+    }
+
+    fn send(&mut self, lines: &[String]) -> Result<()> {
+        match self {
+            Connection::InfluxHttp { agent, url, token } => {
+                let mut request = agent
+                    .post(url)
+                    .set("Content-Type", "text/plain; charset=utf-8");
+                if let Some(token) = token {
+                    request = request.set("Authorization", &format!("Token {token}"));
+                }
+                match request.send_string(&lines.join("\n")) {
+                    Ok(_) => Ok(()),
+                    // Retrying malformed or unauthorised writes would wedge
+                    // the queue forever; drop the batch instead.
+                    Err(ureq::Error::Status(code, response))
+                        if (400..500).contains(&code) && code != 429 =>
+                    {
+                        let body = response.into_string().unwrap_or_default();
+                        warn!(
+                            "InfluxDB rejected {} lines ({code}): {}",
+                            lines.len(),
+                            body.trim()
+                        );
+                        Ok(())
+                    }
+                    Err(err) => Err(err).context("writing to InfluxDB"),
+                }
+            }
+            Connection::InfluxUdp { address, socket } => {
+                let target = resolve(address)?;
+                if socket.is_none() {
+                    let local: SocketAddr = if target.is_ipv4() {
+                        ([0, 0, 0, 0], 0).into()
+                    } else {
+                        ([0u16; 8], 0).into()
+                    };
+                    *socket = Some(UdpSocket::bind(local)?);
+                }
+                let socket = socket.as_ref().expect("bound above");
+                for payload in datagrams(lines) {
+                    socket
+                        .send_to(payload.as_bytes(), target)
+                        .with_context(|| format!("sending to {address}"))?;
+                }
+                Ok(())
+            }
+            Connection::Graphite { address, stream } => {
+                if stream.is_none() {
+                    let conn = TcpStream::connect_timeout(&resolve(address)?, IO_TIMEOUT)
+                        .with_context(|| format!("connecting to {address}"))?;
+                    conn.set_write_timeout(Some(IO_TIMEOUT))?;
+                    *stream = Some(conn);
+                }
+                let mut payload = lines.join("\n");
+                payload.push('\n');
+                let result = stream
+                    .as_mut()
+                    .expect("connected above")
+                    .write_all(payload.as_bytes());
+                if result.is_err() {
+                    *stream = None;
+                }
+                result.with_context(|| format!("writing to {address}"))
+            }
+        }
+    }
+}
+
+fn resolve(address: &str) -> Result<SocketAddr> {
+    address
+        .to_socket_addrs()
+        .with_context(|| format!("resolving {address}"))?
+        .next()
+        .with_context(|| format!("{address} has no addresses"))
+}
+
+/// Packs lines into newline-separated payloads of at most `UDP_PAYLOAD`
+/// bytes (a single longer line still goes out alone).
+fn datagrams(lines: &[String]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut current = String::new();
+    for line in lines {
+        if !current.is_empty() && current.len() + line.len() + 1 > UDP_PAYLOAD {
+            out.push(std::mem::take(&mut current));
+        }
+        current.push_str(line);
+        current.push('\n');
+    }
+    if !current.is_empty() {
+        out.push(current);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read};
+    use std::net::TcpListener;
+    use std::sync::mpsc;
+    use std::time::Instant;
+    use wtui_core::{utc_from_timestamp, MetricKind};
+
+    fn temp(value: f64) -> MetricReading {
+        MetricReading::new(
+            utc_from_timestamp(1_700_000_000),
+            MetricKind::Temps,
+            "temp",
+            Some("cpu"),
+            value,
+            Some("C"),
+        )
+    }
+
+    fn forwarder(target: ForwardTarget) -> Forwarder {
+        let config = ForwardConfig {
+            target,
+            host: Some("box".into()),
+            queue_size: 100,
+            batch_size: 100,
+        };
+        Forwarder::spawn(&config, "localhost").unwrap()
+    }
+
+    /// Answers one HTTP request per connection with each of `statuses` in
+    /// turn, passing on the request bodies and when they arrived.
+    fn http_server(statuses: &'static [u16]) -> (String, mpsc::Receiver<(Instant, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/write?db=wtui", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+        thread::spawn(move || {
+            for status in statuses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                let _ = tx.send((Instant::now(), String::from_utf8(body).unwrap()));
+                let response = format!(
+                    "HTTP/1.1 {status} X\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"
+                );
+                reader.get_mut().write_all(response.as_bytes()).unwrap();
+            }
+        });
+        (url, rx)
+    }
+
+    #[test]
+    fn full_queue_drops_the_oldest_lines() {
+        let shared = Shared {
+            queue: Mutex::new(Queue::default()),
+            ready: Condvar::new(),
+            capacity: 3,
+        };
+        shared.push_back(["a", "b", "c", "d", "e"].map(String::from));
+        {
+            let queue = shared.queue.lock().unwrap();
+            assert_eq!(queue.lines, ["c", "d", "e"]);
+            assert_eq!(queue.dropped, 2);
+        }
+
+        // A failed batch goes back in front of what queued meanwhile, minus
+        // its oldest lines if they no longer fit.
+        let batch: Vec<String> = shared.queue.lock().unwrap().lines.drain(..2).collect();
+        shared.push_back(["f".to_string()]);
+        shared.requeue(batch);
+        let queue = shared.queue.lock().unwrap();
+        assert_eq!(queue.lines, ["d", "e", "f"]);
+        assert_eq!(queue.dropped, 3);
+    }
+
+    #[test]
+    fn failed_http_batches_are_retried_after_a_backoff() {
+        let (url, requests) = http_server(&[500, 204]);
+        let mut forwarder = forwarder(ForwardTarget::InfluxHttp { url, token: None });
+        forwarder.write(&[temp(51.0), temp(52.0)]).unwrap();
+
+        let timeout = Duration::from_secs(5);
+        let (failed_at, first) = requests.recv_timeout(timeout).unwrap();
+        let (retried_at, second) = requests.recv_timeout(timeout).unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.lines().count(), 2);
+        assert!(first.starts_with("wtui_temps,host=box,sensor=cpu temp=51 "));
+        assert!(retried_at - failed_at >= Duration::from_millis(900));
+    }
+
+    #[test]
+    fn rejected_http_batches_are_dropped() {
+        let (url, requests) = http_server(&[400, 204]);
+        let mut forwarder = forwarder(ForwardTarget::InfluxHttp { url, token: None });
+        let timeout = Duration::from_secs(5);
+        forwarder.write(&[temp(51.0)]).unwrap();
+        let (_, rejected) = requests.recv_timeout(timeout).unwrap();
+        forwarder.write(&[temp(52.0)]).unwrap();
+        let (_, next) = requests.recv_timeout(timeout).unwrap();
+        assert!(rejected.contains("temp=51 "));
+        assert!(next.contains("temp=52 ") && !next.contains("temp=51 "));
+    }
+
+    #[test]
+    fn udp_lines_arrive_as_datagrams() {
+        let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+        listener
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let mut forwarder = forwarder(ForwardTarget::InfluxUdp { address });
+        forwarder.write(&[temp(51.0), temp(52.0)]).unwrap();
+
+        let mut buf = [0; UDP_PAYLOAD];
+        let len = listener.recv(&mut buf).unwrap();
+        let payload = std::str::from_utf8(&buf[..len]).unwrap();
+        assert_eq!(payload.lines().count(), 2);
+        assert!(payload.contains("temp=52 "));
+    }
+
+    #[test]
+    fn send_failures_show_in_write_and_backlog() {
+        // Nothing listens on the port once the listener is gone.
+        let address = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .to_string();
+        let mut forwarder = forwarder(ForwardTarget::Graphite {
+            address,
+            prefix: "wtui".into(),
+        });
+        forwarder.write(&[temp(51.0), temp(52.0)]).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while forwarder.shared.queue.lock().unwrap().last_error.is_none() {
+            assert!(Instant::now() < deadline, "send never failed");
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        let err = forwarder.write(&[temp(53.0)]).unwrap_err();
+        assert!(format!("{err:#}").contains("connecting to"), "{err:#}");
+        let backlog = forwarder.backlog().unwrap();
+        assert_eq!((backlog.queued, backlog.dropped), (3, 0));
+    }
+
+    #[test]
+    fn dropping_flushes_queued_lines() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        let mut forwarder = forwarder(ForwardTarget::Graphite {
+            address,
+            prefix: "wtui".into(),
+        });
+        for value in [51.0, 52.0, 53.0] {
+            forwarder.write(&[temp(value)]).unwrap();
+        }
+        drop(forwarder);
+
+        // The sending thread is gone, so its connection is closed.
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut received = String::new();
+        stream.read_to_string(&mut received).unwrap();
+        assert_eq!(
+            received,
+            "wtui.box.temps.cpu.temp 51 1700000000\n\
+             wtui.box.temps.cpu.temp 52 1700000000\n\
+             wtui.box.temps.cpu.temp 53 1700000000\n"
+        );
+    }
+}
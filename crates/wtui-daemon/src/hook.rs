@@ -0,0 +1,95 @@
+use nix::sys::signal::{killpg, Signal};
+use nix::unistd::Pid;
+use std::os::unix::process::CommandExt;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+/// Hook commands still running after this are killed.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Runs a user command through `sh -c` without holding up the cycle; a
+/// thread reaps it and kills it past the timeout. `what` names it in logs.
+pub fn spawn(what: String, command: &str, env: &[(&str, String)]) {
+    spawn_with_timeout(what, command, env, TIMEOUT);
+}
+
+/// The command gets a process group of its own, so the timeout kills
+/// everything it started rather than just `sh`.
+fn spawn_with_timeout(what: String, command: &str, env: &[(&str, String)], timeout: Duration) {
+    let spawned = Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(env.iter().map(|(key, value)| (key, value)))
+        .stdin(Stdio::null())
+        .process_group(0)
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(err) => {
+            warn!("{what}: command failed to start: {err}");
+            return;
+        }
+    };
+    thread::spawn(move || {
+        let started = Instant::now();
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) if !status.success() => {
+                    warn!("{what}: command exited with {status}");
+                    return;
+                }
+                Ok(Some(_)) => return,
+                Ok(None) if started.elapsed() > timeout => {
+                    warn!("{what}: command timed out; killing it");
+                    let _ = killpg(Pid::from_raw(child.id() as i32), Signal::SIGKILL);
+                    let _ = child.wait();
+                    return;
+                }
+                Ok(None) => thread::sleep(Duration::from_millis(100)),
+                Err(err) => {
+                    warn!("{what}: waiting for command: {err}");
+                    return;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nix::sys::signal::kill;
+
+    #[test]
+    fn timeout_kills_what_the_command_started() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("pid");
+        let command = format!("sleep 30 & echo $! > {}; wait", pid_file.display());
+        spawn_with_timeout("test".into(), &command, &[], Duration::from_millis(200));
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let pid = loop {
+            if let Some(pid) = std::fs::read_to_string(&pid_file)
+                .ok()
+                .and_then(|s| s.trim().parse::<i32>().ok())
+            {
+                break Pid::from_raw(pid);
+            }
+            assert!(Instant::now() < deadline, "command never started");
+            thread::sleep(Duration::from_millis(10));
+        };
+        // Once `sh` is gone the killed sleep is init's to reap; until then
+        // it lingers as a zombie.
+        let alive = || {
+            kill(pid, None).is_ok()
+                && !std::fs::read_to_string(format!("/proc/{pid}/stat"))
+                    .is_ok_and(|stat| stat.contains(") Z "))
+        };
+        while alive() {
+            assert!(Instant::now() < deadline, "background job survived");
+            thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
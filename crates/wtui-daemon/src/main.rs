@@ -1,20 +1,52 @@
+mod alerts;
+mod control;
+mod exporter;
+mod forward;
+mod hook;
+mod monitor;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+mod notify;
+mod offload;
+mod pidfile;
+mod quota;
+mod sink;
+mod spool;
+mod stream;
+
 use anyhow::{Context, Result};
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+use control::{ControlServer, PendingCommand};
+use exporter::Exporter;
+use monitor::{DaemonSource, SharedStats};
+use notify::{Notifier, NotifyEnv};
+use offload::OffThread;
 use once_cell::sync::OnceCell;
+use pidfile::PidGuard;
+use quota::QuotaWatch;
+use sink::Sinks;
 use std::collections::HashMap;
+use std::io::Write;
 use std::path::PathBuf;
+use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
+use stream::StreamServer;
 use tracing::{info, warn};
 use tracing_subscriber::fmt::writer::BoxMakeWriter;
 use tracing_subscriber::util::SubscriberInitExt;
+use wtui_core::alerts::AlertEngine;
+use wtui_core::control::{
+    send_request, ControlRequest, ControlResponse, DaemonStatus, SourceHealth,
+};
 use wtui_core::metrics::{
-    cpu_usage_percent, read_batteries, read_cpu_times, read_disk_usage, read_net_snapshot,
-    read_powercap, read_ram_usage, read_temperatures, CpuTimes, MetricKind, NetSnapshot,
+    read_ac_online, read_boot_id, read_clocks, ClockSnapshot, MetricKind, MetricReading,
 };
-use wtui_core::{Config, Database};
+use wtui_core::stream::encode_line;
+use wtui_core::{Config, Database, EventRow, SourceRegistry};
 
 #[derive(Parser, Debug)]
 #[command(author, version, about = "wtui-daemon: metrics collector")]
@@ -31,197 +63,770 @@ struct Args {
     /// Comma separated metrics list
     #[arg(long)]
     metrics: Option<String>,
+    /// Stop the running daemon and take over from it
+    #[arg(long)]
+    replace: bool,
+    /// Collect one full cycle, write it to the sinks and exit
+    #[arg(long)]
+    once: bool,
+    /// Collect one full cycle and print it without writing anything
+    #[arg(long, conflicts_with = "once")]
+    dry_run: bool,
+    /// How --dry-run prints readings
+    #[arg(long, value_enum, default_value_t = Format::Table, requires = "dry_run")]
+    format: Format,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Format {
+    /// One JSON reading per line, as on the reading stream
+    Json,
+    /// Aligned columns for reading by eye
+    Table,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Send a command to the running daemon over its control socket
+    Ctl {
+        #[command(subcommand)]
+        command: CtlCommand,
+        /// Print the daemon's raw JSON reply
+        #[arg(long, global = true)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone, Copy)]
+enum CtlCommand {
+    /// Show uptime, the last cycle and per-source health
+    Status,
+    /// Re-read the config file
+    Reload,
+    /// Stop collecting until `resume`
+    Pause,
+    /// Continue collecting after `pause`
+    Resume,
+    /// Read every source right away
+    SampleNow,
+    /// Apply the retention policy right away
+    PruneNow,
 }
 
+impl From<CtlCommand> for ControlRequest {
+    fn from(command: CtlCommand) -> Self {
+        match command {
+            CtlCommand::Status => ControlRequest::Status,
+            CtlCommand::Reload => ControlRequest::Reload,
+            CtlCommand::Pause => ControlRequest::Pause,
+            CtlCommand::Resume => ControlRequest::Resume,
+            CtlCommand::SampleNow => ControlRequest::SampleNow,
+            CtlCommand::PruneNow => ControlRequest::PruneNow,
+        }
+    }
+}
+
+/// How long `--once` and `--dry-run` wait between the baseline and the real
+/// read of delta-based sources (CPU, network...).
+const BASELINE_GAP: Duration = Duration::from_secs(1);
+
+/// Gaps between CLOCK_BOOTTIME and CLOCK_MONOTONIC shorter than this are
+/// scheduling noise rather than a suspend.
+const SUSPEND_THRESHOLD: Duration = Duration::from_secs(2);
+
 struct DaemonState {
-    prev_cpu: Option<CpuTimes>,
-    prev_net: HashMap<String, NetSnapshot>,
+    registry: SourceRegistry,
+    prev_clocks: Option<ClockSnapshot>,
+    /// Last observed power source; `None` without an AC adapter.
+    on_battery: Option<bool>,
+    boot_id: Option<String>,
     last_retention: Instant,
+    started_at: time::OffsetDateTime,
+    paused: bool,
+    last_cycle: Option<(time::OffsetDateTime, Duration)>,
+    health: HashMap<String, SourceHealth>,
+    stream: Option<StreamServer>,
+    exporter: Option<Exporter>,
+    sinks: Sinks,
+    alerts: AlertEngine,
+    quotas: QuotaWatch,
+    /// Feeds the `daemon` family.
+    stats: SharedStats,
 }
 
 fn main() -> Result<()> {
+    let notify_env = NotifyEnv::take();
     let args = Args::parse();
     let mut config = Config::load(args.config.as_deref())?;
-    apply_overrides(&mut config, &args);
+    apply_overrides(&mut config, &args)?;
+
+    if let Some(Command::Ctl { command, json }) = args.command {
+        return run_ctl(&config, command, json);
+    }
 
     init_logging(&config)?;
+    if args.dry_run {
+        return dry_run(&config, args.format);
+    }
     info!("starting wtui-daemon");
+    let mut notifier = notify_env.and_then(|env| {
+        Notifier::connect(env)
+            .map_err(|err| warn!("systemd notification disabled: {err:#}"))
+            .ok()
+    });
+    let pid_guard = PidGuard::acquire(config.daemon.pid_file.clone(), args.replace)?;
 
-    let db = Database::connect(&config.database.path)?;
+    let db = Rc::new(Database::connect(&config.database.path)?);
+    let on_battery = read_ac_online(&config.daemon.host_roots())
+        .ok()
+        .flatten()
+        .map(|online| !online);
+    let stats = SharedStats::default();
     let mut state = DaemonState {
-        prev_cpu: None,
-        prev_net: HashMap::new(),
+        registry: build_registry(&config, on_battery == Some(true), &stats),
+        prev_clocks: None,
+        on_battery,
+        boot_id: read_boot_id(&config.daemon.host_roots()).ok(),
         last_retention: Instant::now(),
+        started_at: wtui_core::timeutils::now_utc(),
+        paused: false,
+        last_cycle: None,
+        health: HashMap::new(),
+        stream: None,
+        exporter: None,
+        sinks: Sinks::default(),
+        alerts: AlertEngine::new(&config.alerts)?,
+        quotas: QuotaWatch::default(),
+        stats,
     };
+    state.sinks = Sinks::build(&config, &db, Sinks::default());
+    record_boot(&db, &mut state);
+    if args.once {
+        return run_once(&db, state, &config);
+    }
+    state.registry.map_sources(OffThread::custom);
 
     let running = Arc::new(AtomicBool::new(true));
     let reload = Arc::new(AtomicBool::new(false));
-    setup_signals(running.clone(), reload.clone());
+    let wakeup = Arc::new(Wakeup::default());
+    setup_signals(running.clone(), reload.clone(), wakeup.clone())?;
+
+    let (control_tx, control_rx) = mpsc::channel();
+    let socket = config.daemon.control_socket_path();
+    let control = match ControlServer::spawn(&socket, control_tx, wakeup.clone()) {
+        Ok(server) => Some(server),
+        Err(err) => {
+            warn!("control socket unavailable: {err:#}");
+            None
+        }
+    };
+    let stream_socket = config.daemon.stream_socket_path();
+    state.stream = match StreamServer::spawn(&stream_socket, config.daemon.stream_history) {
+        Ok(server) => Some(server),
+        Err(err) => {
+            warn!("reading stream unavailable: {err:#}");
+            None
+        }
+    };
+    if let Some(exporter) = &config.daemon.exporter {
+        state.exporter = match Exporter::spawn(exporter.listen) {
+            Ok(server) => Some(server),
+            Err(err) => {
+                warn!("metrics exporter unavailable: {err:#}");
+                None
+            }
+        };
+    }
 
-    let interval = config.daemon.interval;
-    let pid_guard = PidGuard::new(config.daemon.pid_file.clone())?;
+    if let Some(notifier) = &notifier {
+        notifier.notify("READY=1\nSTATUS=starting");
+    }
 
     while running.load(Ordering::SeqCst) {
+        handle_commands(&control_rx, &db, &mut state, &mut config, &args);
         if reload.swap(false, Ordering::SeqCst) {
-            info!("reloading config");
-            match Config::load(args.config.as_deref()) {
-                Ok(mut new_cfg) => {
-                    apply_overrides(&mut new_cfg, &args);
-                    config = new_cfg;
-                }
-                Err(err) => warn!("failed to reload config: {err}",),
+            if let Err(err) = reload_config(&db, &mut state, &mut config, &args) {
+                warn!("failed to reload config: {err:#}");
             }
         }
 
         let now = wtui_core::timeutils::now_utc();
-        collect_cycle(&db, &config, &mut state, now);
+        detect_suspend(&db, &mut state, now);
+        check_power_source(&db, &mut state, &config, now);
+        if !state.paused {
+            collect_cycle(&db, &mut state, now);
+            let warnings = state.quotas.check(&db, &config.quotas, now);
+            write_events(&db, &mut state.sinks, warnings);
+        }
+        if let Some(exporter) = &state.exporter {
+            exporter.set_status(status(&state));
+        }
+        if let Some(notifier) = &mut notifier {
+            notifier.status(status_line(&state));
+            notifier.ping_watchdog();
+        }
 
-        if let Some(days) = config.database.retention_days {
-            if state.last_retention.elapsed() > Duration::from_secs(600) {
-                let cutoff = now - time::Duration::days(days as i64);
-                if let Err(err) = db.prune_older_than(cutoff) {
-                    warn!("retention prune failed: {err}");
-                }
-                state.last_retention = Instant::now();
+        if state.last_retention.elapsed() > Duration::from_secs(600) {
+            if let Err(err) = prune(&db, &mut state, &config) {
+                warn!("retention prune failed: {err}");
             }
         }
 
-        thread::sleep(interval);
+        // Sleep until the next source is due; an idle or paused daemon still
+        // wakes once per base interval to run retention.
+        let wake = state
+            .registry
+            .next_due()
+            .filter(|_| !state.paused)
+            .unwrap_or_else(|| Instant::now() + config.daemon.interval);
+        let wake = match notifier.as_ref().and_then(Notifier::next_ping) {
+            Some(ping) => wake.min(ping),
+            None => wake,
+        };
+        wakeup.wait_until(wake);
+    }
+
+    if let Some(notifier) = &notifier {
+        notifier.notify("STOPPING=1");
     }
 
+    write_events(&db, &mut state.sinks, state.registry.finish());
+
+    drop(std::mem::take(&mut state.sinks));
+    drop(state.stream.take());
+    drop(state.exporter.take());
+    drop(control);
     drop(pid_guard);
     info!("wtui-daemon stopped");
     Ok(())
 }
 
-fn collect_cycle(
-    db: &Database,
-    config: &Config,
+fn reload_config(
+    db: &Rc<Database>,
     state: &mut DaemonState,
-    now: time::OffsetDateTime,
-) {
-    let metrics = &config.daemon.metrics;
-
-    if metrics.contains(&MetricKind::Cpu) {
-        match read_cpu_times() {
-            Ok(current) => {
-                if let Some(prev) = &state.prev_cpu {
-                    if let Some(usage) = cpu_usage_percent(prev, &current) {
-                        if let Err(err) = db.insert_cpu_usage(now, usage, Some("total")) {
-                            warn!("failed to write cpu sample: {err}");
-                        }
-                    }
-                }
-                state.prev_cpu = Some(current);
+    config: &mut Config,
+    args: &Args,
+) -> Result<()> {
+    info!("reloading config");
+    let mut new_cfg = Config::load(args.config.as_deref())?;
+    apply_overrides(&mut new_cfg, args)?;
+    let mut alerts = AlertEngine::new(&new_cfg.alerts)?;
+    alerts.carry_over(std::mem::take(&mut state.alerts));
+    state.alerts = alerts;
+    *config = new_cfg;
+    write_events(db, &mut state.sinks, state.registry.finish());
+    state.registry = build_registry(config, state.on_battery == Some(true), &state.stats);
+    state.registry.map_sources(OffThread::custom);
+    if let Some(stream) = &state.stream {
+        stream.set_history(config.daemon.stream_history);
+    }
+    let previous = std::mem::take(&mut state.sinks);
+    state.sinks = Sinks::build(config, db, previous);
+    Ok(())
+}
+
+/// `--once`: a single cycle through the sinks, alerts and quota checks, for
+/// running from cron. Fails when a source couldn't be read. Network deltas
+/// run from the counters the previous run stored, so traffic totals and
+/// quotas cover the time between runs.
+fn run_once(db: &Rc<Database>, mut state: DaemonState, config: &Config) -> Result<()> {
+    let boot_started = boot_started(db, &state);
+    for (name, err) in state.registry.resume(db, boot_started) {
+        warn!("{name}: no stored baseline: {err:#}");
+    }
+    let mut failed = prime(&mut state.registry);
+    let now = wtui_core::timeutils::now_utc();
+    collect_cycle(db, &mut state, now);
+    let warnings = state.quotas.check(db, &config.quotas, now);
+    write_events(db, &mut state.sinks, warnings);
+    write_events(db, &mut state.sinks, state.registry.finish());
+    failed.extend(
+        state
+            .health
+            .values()
+            .filter(|h| !h.is_healthy())
+            .map(|h| format!("{}: {}", h.name, h.last_error.as_deref().unwrap_or("?"))),
+    );
+    let unwritten: Vec<String> = state
+        .sinks
+        .health()
+        .into_iter()
+        .filter(|h| h.last_error_at == Some(now))
+        .map(|h| format!("{}: {}", h.name, h.last_error.as_deref().unwrap_or("?")))
+        .collect();
+    // Flushes forwarders and spills anything the database refused.
+    drop(std::mem::take(&mut state.sinks));
+    anyhow::ensure!(failed.is_empty(), "read failed: {}", failed.join("; "));
+    anyhow::ensure!(
+        unwritten.is_empty(),
+        "write failed: {}",
+        unwritten.join("; ")
+    );
+    Ok(())
+}
+
+/// Takes the baseline reads for a single cycle, waiting [`BASELINE_GAP`]
+/// after them; returns the sources whose baseline failed.
+fn prime(registry: &mut SourceRegistry) -> Vec<String> {
+    let Some(errors) = registry.prime(wtui_core::timeutils::now_utc()) else {
+        return Vec::new();
+    };
+    thread::sleep(BASELINE_GAP);
+    errors
+        .iter()
+        .map(|(name, err)| format!("{name} (baseline): {err:#}"))
+        .collect()
+}
+
+/// `--dry-run`: a single cycle printed to stdout. Doesn't open the database
+/// or any sink, so it is safe to point at a config before deploying it.
+fn dry_run(config: &Config, format: Format) -> Result<()> {
+    let stats = SharedStats::default();
+    let on_battery = read_ac_online(&config.daemon.host_roots())
+        .ok()
+        .flatten()
+        .is_some_and(|online| !online);
+    let mut registry = build_registry(config, on_battery, &stats);
+    let mut failed = prime(&mut registry);
+    let collected = registry.collect(wtui_core::timeutils::now_utc());
+    match format {
+        Format::Json => {
+            let mut out = std::io::stdout().lock();
+            for reading in &collected.readings {
+                out.write_all(encode_line(reading)?.as_bytes())?;
             }
-            Err(err) => warn!("cpu read failed: {err}"),
         }
+        Format::Table => print_readings(&collected.readings),
     }
+    failed.extend(
+        collected
+            .errors
+            .iter()
+            .map(|(name, err)| format!("{name}: {err:#}")),
+    );
+    anyhow::ensure!(failed.is_empty(), "read failed: {}", failed.join("; "));
+    Ok(())
+}
 
-    if metrics.contains(&MetricKind::Ram) {
-        match read_ram_usage() {
-            Ok(ram) => {
-                let used = ram.total_bytes.saturating_sub(ram.available_bytes);
-                if let Err(err) = db.insert_ram_usage(now, used, ram.total_bytes) {
-                    warn!("failed to write ram sample: {err}");
-                }
-            }
-            Err(err) => warn!("ram read failed: {err}"),
+fn print_readings(readings: &[MetricReading]) {
+    let rows: Vec<[String; 5]> = readings
+        .iter()
+        .map(|r| {
+            [
+                r.kind.to_string(),
+                r.metric.clone(),
+                r.label.clone().unwrap_or_default(),
+                format_value(r.value),
+                r.unit.clone().unwrap_or_default(),
+            ]
+        })
+        .collect();
+    let header = ["FAMILY", "METRIC", "LABEL", "VALUE", "UNIT"].map(String::from);
+    let mut widths = header.clone().map(|h| h.len());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
         }
     }
+    for row in std::iter::once(&header).chain(&rows) {
+        let line = format!(
+            "{:<w0$}  {:<w1$}  {:<w2$}  {:>w3$}  {}",
+            row[0],
+            row[1],
+            row[2],
+            row[3],
+            row[4],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+            w3 = widths[3],
+        );
+        println!("{}", line.trim_end());
+    }
+}
+
+/// Up to three decimals, without trailing zeros.
+fn format_value(value: f64) -> String {
+    let text = format!("{value:.3}");
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
 
-    if metrics.contains(&MetricKind::Net) {
-        let interfaces = desired_interfaces(&config.daemon.net_interfaces);
-        for iface in interfaces {
-            match read_net_snapshot(&iface) {
-                Ok(snapshot) => {
-                    let prev = state.prev_net.insert(iface.clone(), snapshot);
-                    let mut delta = None;
-                    let mut reset = false;
-                    if let Some(prev) = prev {
-                        let rx_delta = snapshot.rx_bytes as i64 - prev.rx_bytes as i64;
-                        let tx_delta = snapshot.tx_bytes as i64 - prev.tx_bytes as i64;
-                        if rx_delta < 0 || tx_delta < 0 {
-                            reset = true;
-                        } else {
-                            delta = Some((rx_delta, tx_delta));
-                        }
-                    }
-                    if let Err(err) = db.insert_net_sample(now, &iface, snapshot, delta, reset) {
-                        warn!("failed to write net sample for {iface}: {err}");
-                    }
+/// Applies the retention policy; a no-op without `retention_days`.
+fn prune(db: &Database, state: &mut DaemonState, config: &Config) -> Result<Option<u32>> {
+    state.last_retention = Instant::now();
+    let Some(days) = config.database.retention_days else {
+        return Ok(None);
+    };
+    let cutoff = wtui_core::timeutils::now_utc() - time::Duration::days(days as i64);
+    db.prune_older_than(cutoff)?;
+    Ok(Some(days))
+}
+
+/// Answers everything that arrived on the control socket since the last
+/// pass. Runs on the main loop so commands never race a collection cycle.
+fn handle_commands(
+    commands: &Receiver<PendingCommand>,
+    db: &Rc<Database>,
+    state: &mut DaemonState,
+    config: &mut Config,
+    args: &Args,
+) {
+    for pending in commands.try_iter() {
+        let response = match pending.request {
+            ControlRequest::Status => ControlResponse {
+                ok: true,
+                status: Some(status(state)),
+                ..Default::default()
+            },
+            ControlRequest::Reload => match reload_config(db, state, config, args) {
+                Ok(()) => ControlResponse::ok("config reloaded"),
+                Err(err) => ControlResponse::error(format!("reload failed: {err:#}")),
+            },
+            ControlRequest::Pause => {
+                state.paused = true;
+                info!("collection paused");
+                ControlResponse::ok("collection paused")
+            }
+            ControlRequest::Resume => {
+                state.paused = false;
+                state.registry.mark_all_due();
+                info!("collection resumed");
+                ControlResponse::ok("collection resumed")
+            }
+            ControlRequest::SampleNow => {
+                state.registry.mark_all_due();
+                let read = collect_cycle(db, state, wtui_core::timeutils::now_utc());
+                ControlResponse::ok(format!("read {read} sources"))
+            }
+            ControlRequest::PruneNow => match prune(db, state, config) {
+                Ok(Some(days)) => {
+                    ControlResponse::ok(format!("pruned samples older than {days} days"))
                 }
-                Err(err) => warn!("net read failed for {iface}: {err}"),
+                Ok(None) => ControlResponse::error("no retention_days configured"),
+                Err(err) => ControlResponse::error(format!("prune failed: {err:#}")),
+            },
+        };
+        pending.respond(response);
+    }
+}
+
+fn status(state: &DaemonState) -> DaemonStatus {
+    DaemonStatus {
+        pid: std::process::id(),
+        started_at: state.started_at,
+        paused: state.paused,
+        on_battery: state.on_battery,
+        last_cycle: state.last_cycle.map(|(at, _)| at),
+        last_cycle_secs: state.last_cycle.map(|(_, took)| took.as_secs_f64()),
+        sources: state
+            .registry
+            .names()
+            .into_iter()
+            .map(|name| {
+                state.health.get(&name).cloned().unwrap_or(SourceHealth {
+                    name,
+                    ..Default::default()
+                })
+            })
+            .collect(),
+        sinks: state.sinks.health(),
+    }
+}
+
+/// One-line summary for systemd's `STATUS=`, shown by `systemctl status`.
+fn status_line(state: &DaemonState) -> String {
+    if state.paused {
+        return "paused".into();
+    }
+    let Some((at, took)) = state.last_cycle else {
+        return "starting".into();
+    };
+    let failing = state
+        .registry
+        .names()
+        .iter()
+        .filter(|name| state.health.get(*name).is_some_and(|h| h.failures > 0))
+        .count();
+    let mut line = format!(
+        "last cycle {} UTC took {}ms",
+        at.time()
+            .format(&time::macros::format_description!(
+                "[hour]:[minute]:[second]"
+            ))
+            .unwrap_or_default(),
+        took.as_millis()
+    );
+    if failing > 0 {
+        line.push_str(&format!(", {failing} sources failing"));
+    }
+    line
+}
+
+fn run_ctl(config: &Config, command: CtlCommand, json: bool) -> Result<()> {
+    let response = send_request(&config.daemon.control_socket_path(), command.into())?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&response)?);
+    } else if let Some(status) = &response.status {
+        print_status(status);
+    } else if let Some(message) = &response.message {
+        println!("{message}");
+    }
+    if let Some(error) = response.error {
+        anyhow::bail!(error);
+    }
+    Ok(())
+}
+
+fn print_status(status: &DaemonStatus) {
+    let clock = time::macros::format_description!("[hour]:[minute]:[second]");
+    let fmt = |t: Option<time::OffsetDateTime>| {
+        t.and_then(|t| t.format(&clock).ok())
+            .unwrap_or_else(|| "-".into())
+    };
+    let up = wtui_core::timeutils::now_utc() - status.started_at;
+    println!(
+        "pid {}, up {}, {}{}",
+        status.pid,
+        humantime::format_duration(Duration::from_secs(up.whole_seconds().max(0) as u64)),
+        if status.paused {
+            "paused"
+        } else {
+            "collecting"
+        },
+        match status.on_battery {
+            Some(true) => ", on battery",
+            Some(false) => ", on AC",
+            None => "",
+        }
+    );
+    println!(
+        "last cycle: {} UTC ({})",
+        fmt(status.last_cycle),
+        status
+            .last_cycle_secs
+            .map(|s| format!("{:.0}ms", s * 1000.0))
+            .unwrap_or_else(|| "-".into())
+    );
+    for (title, entries) in [("SOURCE", &status.sources), ("SINK", &status.sinks)] {
+        println!("{title:<24} {:<8} {:<10} ERROR", "STATE", "LAST OK");
+        for entry in entries {
+            let state = if entry.is_healthy() { "ok" } else { "failing" };
+            println!(
+                "{:<24} {:<8} {:<10} {}",
+                entry.name,
+                state,
+                fmt(entry.last_ok),
+                entry.last_error.as_deref().unwrap_or("")
+            );
+            if let Some(backlog) = entry.backlog.filter(|b| b.queued > 0 || b.dropped > 0) {
+                println!(
+                    "{:<24} {} queued ({} spooled), {} dropped",
+                    "",
+                    backlog.queued,
+                    wtui_core::quota::format_bytes(backlog.spooled_bytes),
+                    backlog.dropped
+                );
             }
         }
     }
+}
 
-    if metrics.contains(&MetricKind::Battery) {
-        match read_batteries() {
-            Ok(batteries) => {
-                for b in batteries {
-                    if let Err(err) = db.insert_battery_sample(
-                        now,
-                        &b.name,
-                        b.capacity,
-                        b.health,
-                        b.energy_now_uw,
-                    ) {
-                        warn!("failed to write battery sample: {err}");
-                    }
-                }
-            }
-            Err(err) => warn!("battery read failed: {err}"),
+/// Records a `boot` event the first time the daemon runs within a boot,
+/// keyed on the kernel's boot id so restarts of the daemon don't duplicate it.
+fn record_boot(db: &Database, state: &mut DaemonState) {
+    let Some(boot_id) = state.boot_id.clone() else {
+        return;
+    };
+    match db.has_event("boot", &boot_id) {
+        Ok(true) => {}
+        Ok(false) => {
+            let booted_at = read_clocks()
+                .map(|c| wtui_core::timeutils::now_utc() - c.boottime)
+                .unwrap_or_else(|_| wtui_core::timeutils::now_utc());
+            let event = EventRow::new(booted_at, "boot", None, Some(&boot_id));
+            write_events(db, &mut state.sinks, vec![event]);
         }
+        Err(err) => warn!("failed to look up boot event: {err}"),
     }
+}
 
-    if metrics.contains(&MetricKind::Temps) {
-        match read_temperatures() {
-            Ok(temps) => {
-                for t in temps {
-                    if let Err(err) = db.insert_temp_sample(now, &t.sensor, t.value_c) {
-                        warn!("failed to write temp sample: {err}");
-                    }
-                }
-            }
-            Err(err) => warn!("temp read failed: {err}"),
+/// When the current boot started, from the `boot` event of its boot id.
+fn boot_started(db: &Database, state: &DaemonState) -> Option<time::OffsetDateTime> {
+    let boot_id = state.boot_id.as_deref()?;
+    db.fetch_events(None, Some("boot"))
+        .ok()?
+        .into_iter()
+        .find(|event| event.detail.as_deref() == Some(boot_id))
+        .map(|event| event.timestamp)
+}
+
+/// Compares CLOCK_BOOTTIME against CLOCK_MONOTONIC since the previous tick;
+/// any surplus on the boot clock is time spent suspended.
+fn detect_suspend(db: &Database, state: &mut DaemonState, now: time::OffsetDateTime) {
+    let current = match read_clocks() {
+        Ok(c) => c,
+        Err(err) => {
+            warn!("clock read failed: {err}");
+            return;
+        }
+    };
+    if let Some(prev) = state.prev_clocks {
+        let suspended = current.suspended_since(&prev);
+        if suspended >= SUSPEND_THRESHOLD {
+            let secs = suspended.as_secs_f64();
+            info!(
+                "resumed after {} suspended",
+                humantime::format_duration(suspended)
+            );
+            let started = now - suspended;
+            let events = vec![
+                EventRow::new(started, "suspend", Some(secs), None),
+                EventRow::new(now, "resume", Some(secs), None),
+            ];
+            write_events(db, &mut state.sinks, events);
         }
     }
+    state.prev_clocks = Some(current);
+}
 
-    if metrics.contains(&MetricKind::Disk) {
-        let mounts = if config.daemon.disk_devices.is_empty() {
-            vec!["/".into()]
-        } else {
-            config.daemon.disk_devices.clone()
-        };
-        for mount in mounts {
-            match read_disk_usage(&mount) {
-                Ok(usage) => {
-                    let used = usage.total_bytes.saturating_sub(usage.available_bytes);
-                    if let Err(err) = db.insert_disk_sample(now, &mount, used, usage.total_bytes) {
-                        warn!("failed to write disk sample for {mount}: {err}");
-                    }
-                }
-                Err(err) => warn!("disk read failed for {mount}: {err}"),
-            }
+/// Sources for `config`, with the `[daemon.battery]` overrides applied when
+/// running on battery. The `daemon` family goes last so it reports on the
+/// reads of its own cycle.
+fn build_registry(config: &Config, on_battery: bool, stats: &SharedStats) -> SourceRegistry {
+    let mut effective = config.clone();
+    effective.daemon = config.daemon.for_power_source(on_battery);
+    let mut registry = SourceRegistry::from_config(&effective);
+    if effective.daemon.metrics.contains(&MetricKind::Daemon) {
+        registry.push(
+            Box::new(DaemonSource::new(
+                stats.clone(),
+                config.database.path.clone(),
+            )),
+            effective.daemon.interval_for(MetricKind::Daemon),
+        );
+    }
+    stats
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .retain_sources(&registry.names());
+    registry
+}
+
+/// Records AC adapter transitions as `on_battery`/`on_ac` events and moves
+/// the registry to the matching profile when one is configured, keeping the
+/// sources both profiles collect.
+fn check_power_source(
+    db: &Database,
+    state: &mut DaemonState,
+    config: &Config,
+    now: time::OffsetDateTime,
+) {
+    let on_battery = match read_ac_online(&config.daemon.host_roots()) {
+        Ok(Some(online)) => !online,
+        Ok(None) => return,
+        Err(err) => {
+            warn!("power supply read failed: {err}");
+            return;
         }
+    };
+    let previous = state.on_battery.replace(on_battery);
+    if previous == Some(on_battery) {
+        return;
+    }
+    if previous.is_some() {
+        let kind = if on_battery { "on_battery" } else { "on_ac" };
+        info!("power source changed: {kind}");
+        let event = EventRow::new(now, kind, None, None);
+        write_events(db, &mut state.sinks, vec![event]);
+    }
+    if config.daemon.battery.is_some() && (previous.is_some() || on_battery) {
+        let mut next = build_registry(config, on_battery, &state.stats);
+        next.map_sources(OffThread::custom);
+        let dropped = state.registry.reschedule(next);
+        write_events(db, &mut state.sinks, dropped);
     }
+}
 
-    if metrics.contains(&MetricKind::Power) {
-        match read_powercap() {
-            Ok(domains) => {
-                for d in domains {
-                    if let Err(err) = db.insert_power_sample(now, &d.domain, d.draw_mw) {
-                        warn!("failed to write power sample: {err}");
-                    }
+/// Reads every due source and writes its samples; returns how many were read.
+fn collect_cycle(db: &Database, state: &mut DaemonState, now: time::OffsetDateTime) -> usize {
+    let started = Instant::now();
+    let mut read = 0;
+    let mut cycle = Vec::new();
+    let mut events = Vec::new();
+    for (stamp, source) in state.registry.due_mut(Instant::now(), now) {
+        read += 1;
+        let name = source.name().to_string();
+        let health = state
+            .health
+            .entry(name.clone())
+            .or_insert_with(|| SourceHealth {
+                name: name.clone(),
+                ..Default::default()
+            });
+        let read_started = Instant::now();
+        let result = source.read(stamp);
+        state
+            .stats
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .record_read(&name, read_started.elapsed(), result.is_ok());
+        match result {
+            Ok(readings) => {
+                health.last_ok = Some(now);
+                health.failures = 0;
+                if let Some(stream) = &state.stream {
+                    stream.publish(&readings);
+                }
+                if let Some(exporter) = &state.exporter {
+                    exporter.publish(&name, &readings);
                 }
+                cycle.extend(readings);
+            }
+            Err(err) => {
+                warn!("{name} read failed: {err:#}");
+                health.last_error = Some(format!("{err:#}"));
+                health.last_error_at = Some(now);
+                health.failures += 1;
             }
-            Err(err) => warn!("power read failed: {err}"),
         }
+        events.extend(source.take_events());
+    }
+    state.sinks.write(&cycle, now);
+    if read > 0 {
+        state
+            .stats
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .record_write(cycle.len(), &state.sinks.health(), now);
+    }
+    let transitions = state.alerts.evaluate(&cycle);
+    events.extend(alerts::dispatch(&state.alerts, transitions));
+    write_events(db, &mut state.sinks, events);
+    if read > 0 {
+        state.last_cycle = Some((now, started.elapsed()));
     }
+    read
 }
 
-fn apply_overrides(config: &mut Config, args: &Args) {
+/// Writes events through the sinks, so they are spooled with the readings
+/// while the database is unavailable. Without a `sqlite` sink they go
+/// straight to the database.
+fn write_events(db: &Database, sinks: &mut Sinks, events: Vec<EventRow>) {
+    if events.is_empty() || sinks.write_events(&events, wtui_core::timeutils::now_utc()) {
+        return;
+    }
+    for event in events {
+        if let Err(err) = db.insert_event_row(&event) {
+            warn!("failed to write {} event: {err}", event.kind);
+        }
+    }
+}
+
+/// Applies the command-line overrides, then re-checks the intervals since
+/// `--interval` bypasses the check `Config::load` runs on the file.
+fn apply_overrides(config: &mut Config, args: &Args) -> Result<()> {
     if let Some(db) = &args.db {
         config.database.path = db.clone();
     }
@@ -237,41 +842,55 @@ fn apply_overrides(config: &mut Config, args: &Args) {
             config.daemon.metrics = kinds;
         }
     }
+    config.daemon.check_intervals()
 }
 
-fn desired_interfaces(user: &[String]) -> Vec<String> {
-    if !user.is_empty() {
-        return user.to_vec();
-    }
-    let base = std::path::PathBuf::from("/sys/class/net");
-    let mut found = Vec::new();
-    if let Ok(entries) = std::fs::read_dir(base) {
-        for entry in entries.flatten() {
-            let name = entry.file_name().to_string_lossy().to_string();
-            if name == "lo" {
-                continue;
+/// Lets signal handlers cut the main loop's sleep short.
+#[derive(Default)]
+struct Wakeup {
+    pending: Mutex<bool>,
+    cond: Condvar,
+}
+
+impl Wakeup {
+    fn notify(&self) {
+        *self.pending.lock().unwrap() = true;
+        self.cond.notify_all();
+    }
+
+    /// Sleeps until `deadline` (monotonic) or the next `notify`, whichever
+    /// comes first.
+    fn wait_until(&self, deadline: Instant) {
+        let mut pending = self.pending.lock().unwrap();
+        while !*pending {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
             }
-            found.push(name);
+            pending = self.cond.wait_timeout(pending, deadline - now).unwrap().0;
         }
-    }
-    if found.is_empty() {
-        vec!["eth0".into()]
-    } else {
-        found
+        *pending = false;
     }
 }
 
-fn setup_signals(running: Arc<AtomicBool>, reload: Arc<AtomicBool>) {
-    let r1 = running.clone();
-    ctrlc::set_handler(move || {
-        r1.store(false, Ordering::SeqCst);
-    })
-    .expect("failed to set ctrlc handler");
-
-    let r2 = running.clone();
-    let reload_flag = reload.clone();
-    let _ = signal_hook::flag::register(signal_hook::consts::SIGHUP, reload_flag);
-    let _ = signal_hook::flag::register(signal_hook::consts::SIGTERM, r2.clone());
+fn setup_signals(
+    running: Arc<AtomicBool>,
+    reload: Arc<AtomicBool>,
+    wakeup: Arc<Wakeup>,
+) -> Result<()> {
+    use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+    let mut signals = signal_hook::iterator::Signals::new([SIGINT, SIGTERM, SIGHUP])
+        .context("installing signal handlers")?;
+    thread::spawn(move || {
+        for signal in signals.forever() {
+            match signal {
+                SIGHUP => reload.store(true, Ordering::SeqCst),
+                _ => running.store(false, Ordering::SeqCst),
+            }
+            wakeup.notify();
+        }
+    });
+    Ok(())
 }
 
 fn init_logging(config: &Config) -> Result<()> {
@@ -304,34 +923,3 @@ fn init_logging(config: &Config) -> Result<()> {
         .ok();
     Ok(())
 }
-
-struct PidGuard {
-    path: Option<PathBuf>,
-}
-
-impl PidGuard {
-    fn new(path: Option<PathBuf>) -> Result<Self> {
-        if let Some(path) = &path {
-            if let Some(parent) = path.parent() {
-                std::fs::create_dir_all(parent)?;
-            }
-            if let Ok(pid_str) = std::fs::read_to_string(path) {
-                if let Ok(pid) = pid_str.trim().parse::<u32>() {
-                    if std::path::Path::new(&format!("/proc/{pid}")).exists() {
-                        anyhow::bail!("another wtui-daemon seems to be running with pid {pid}");
-                    }
-                }
-            }
-            std::fs::write(path, format!("{}\n", std::process::id()))?;
-        }
-        Ok(Self { path })
-    }
-}
-
-impl Drop for PidGuard {
-    fn drop(&mut self) {
-        if let Some(path) = &self.path {
-            let _ = std::fs::remove_file(path);
-        }
-    }
-}
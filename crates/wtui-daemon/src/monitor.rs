@@ -0,0 +1,293 @@
+use anyhow::{Context, Result};
+use nix::time::{clock_gettime, ClockId};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+use wtui_core::control::{Backlog, SourceHealth};
+use wtui_core::metrics::{MetricKind, MetricReading};
+use wtui_core::sources::MetricSource;
+
+/// What the main loop observes about its own cycles, kept for
+/// [`DaemonSource`] to report.
+#[derive(Debug, Default)]
+pub struct CycleStats {
+    /// How long each source read since the last report took; drained by
+    /// [`DaemonSource`] so a source that wasn't due isn't reported again.
+    read_durations: BTreeMap<String, Duration>,
+    /// Failures since the daemon started, per source and per sink.
+    read_errors: BTreeMap<String, u64>,
+    write_errors: BTreeMap<String, u64>,
+    /// What each queueing sink held back after the last cycle.
+    sink_backlogs: BTreeMap<String, Backlog>,
+    /// Readings handed to the sinks by the last cycle that read something.
+    cycle_samples: Option<usize>,
+}
+
+pub type SharedStats = Arc<Mutex<CycleStats>>;
+
+impl CycleStats {
+    pub fn record_read(&mut self, source: &str, took: Duration, ok: bool) {
+        self.read_durations.insert(source.to_string(), took);
+        let errors = self.read_errors.entry(source.to_string()).or_default();
+        if !ok {
+            *errors += 1;
+        }
+    }
+
+    /// Counts the readings of a finished cycle and the sinks that failed to
+    /// write them, i.e. those whose last error is from `now`, and keeps the
+    /// sinks' backlogs.
+    pub fn record_write(&mut self, samples: usize, sinks: &[SourceHealth], now: OffsetDateTime) {
+        self.cycle_samples = Some(samples);
+        self.sink_backlogs = sinks
+            .iter()
+            .filter_map(|sink| Some((sink.name.clone(), sink.backlog?)))
+            .collect();
+        for sink in sinks {
+            let errors = self.write_errors.entry(sink.name.clone()).or_default();
+            if sink.last_error_at == Some(now) {
+                *errors += 1;
+            }
+        }
+    }
+
+    /// Drops the sources a rebuilt registry no longer has.
+    pub fn retain_sources(&mut self, names: &[String]) {
+        self.read_durations.retain(|name, _| names.contains(name));
+        self.read_errors.retain(|name, _| names.contains(name));
+    }
+}
+
+/// The `daemon` family: the stats above plus the database's size and the
+/// process's own memory and CPU use. Registered last, so it sees the reads
+/// of the cycle it runs in; sample counts and write errors are from the
+/// cycle before.
+pub struct DaemonSource {
+    stats: SharedStats,
+    db_path: PathBuf,
+    /// Process CPU time at the previous read, for the usage delta.
+    prev_cpu: Option<(Instant, Duration)>,
+}
+
+impl DaemonSource {
+    pub fn new(stats: SharedStats, db_path: PathBuf) -> Self {
+        Self {
+            stats,
+            db_path,
+            prev_cpu: None,
+        }
+    }
+}
+
+impl MetricSource for DaemonSource {
+    fn name(&self) -> &str {
+        "daemon"
+    }
+
+    fn kind(&self) -> MetricKind {
+        MetricKind::Daemon
+    }
+
+    fn needs_baseline(&self) -> bool {
+        true
+    }
+
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+        let reading = |metric: &str, label: Option<&str>, value: f64, unit: Option<&str>| {
+            MetricReading::new(now, MetricKind::Daemon, metric, label, value, unit)
+        };
+        let mut readings = Vec::new();
+        {
+            let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+            for (source, took) in std::mem::take(&mut stats.read_durations) {
+                readings.push(reading(
+                    "daemon_read_duration",
+                    Some(&source),
+                    took.as_secs_f64() * 1000.0,
+                    Some("ms"),
+                ));
+            }
+            for (source, errors) in &stats.read_errors {
+                readings.push(reading(
+                    "daemon_read_errors",
+                    Some(source),
+                    *errors as f64,
+                    None,
+                ));
+            }
+            for (sink, errors) in &stats.write_errors {
+                readings.push(reading(
+                    "daemon_write_errors",
+                    Some(sink),
+                    *errors as f64,
+                    None,
+                ));
+            }
+            for (sink, backlog) in &stats.sink_backlogs {
+                readings.push(reading(
+                    "daemon_sink_backlog",
+                    Some(sink),
+                    backlog.queued as f64,
+                    None,
+                ));
+                readings.push(reading(
+                    "daemon_sink_dropped",
+                    Some(sink),
+                    backlog.dropped as f64,
+                    None,
+                ));
+            }
+            if let Some(samples) = stats.cycle_samples {
+                readings.push(reading("daemon_cycle_samples", None, samples as f64, None));
+            }
+        }
+
+        if let Ok(meta) = std::fs::metadata(&self.db_path) {
+            readings.push(reading(
+                "daemon_db_size",
+                Some("db"),
+                meta.len() as f64,
+                Some("B"),
+            ));
+        }
+        let mut wal = self.db_path.clone().into_os_string();
+        wal.push("-wal");
+        if let Ok(meta) = std::fs::metadata(wal) {
+            readings.push(reading(
+                "daemon_db_size",
+                Some("wal"),
+                meta.len() as f64,
+                Some("B"),
+            ));
+        }
+
+        readings.push(reading("daemon_rss", None, read_rss()? as f64, Some("B")));
+        let cpu = Duration::from(
+            clock_gettime(ClockId::CLOCK_PROCESS_CPUTIME_ID).context("reading process CPU time")?,
+        );
+        let at = Instant::now();
+        if let Some((prev_at, prev_cpu)) = self.prev_cpu {
+            let wall = at.duration_since(prev_at).as_secs_f64();
+            if wall > 0.0 {
+                let used = cpu.saturating_sub(prev_cpu).as_secs_f64();
+                readings.push(reading("daemon_cpu", None, used / wall * 100.0, Some("%")));
+            }
+        }
+        self.prev_cpu = Some((at, cpu));
+        Ok(readings)
+    }
+}
+
+/// Resident set size of this process, from `VmRSS` in `/proc/self/status`.
+fn read_rss() -> Result<u64> {
+    let status =
+        std::fs::read_to_string("/proc/self/status").context("reading /proc/self/status")?;
+    let kib = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .and_then(|rest| rest.split_whitespace().next()?.parse::<u64>().ok())
+        .context("no VmRSS in /proc/self/status")?;
+    Ok(kib * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wtui_core::now_utc;
+
+    fn values(readings: &[MetricReading], metric: &str) -> Vec<(Option<String>, f64)> {
+        readings
+            .iter()
+            .filter(|r| r.metric == metric)
+            .map(|r| (r.label.clone(), r.value))
+            .collect()
+    }
+
+    #[test]
+    fn reports_each_read_duration_once() {
+        let stats = SharedStats::default();
+        let mut source = DaemonSource::new(stats.clone(), "/nonexistent/wtui.db".into());
+        {
+            let mut stats = stats.lock().unwrap();
+            stats.record_read("cpu", Duration::from_millis(3), true);
+            stats.record_read("temps", Duration::from_millis(12), false);
+        }
+        let readings = source.read(now_utc()).unwrap();
+        assert_eq!(
+            values(&readings, "daemon_read_duration"),
+            [(Some("cpu".into()), 3.0), (Some("temps".into()), 12.0)]
+        );
+        assert_eq!(
+            values(&readings, "daemon_read_errors"),
+            [(Some("cpu".into()), 0.0), (Some("temps".into()), 1.0)]
+        );
+        assert!(values(&readings, "daemon_cpu").is_empty());
+
+        // Spin, so the CPU usage between the two reads is well above zero.
+        let spin = Instant::now();
+        while spin.elapsed() < Duration::from_millis(50) {
+            std::hint::black_box(0);
+        }
+        // Only cpu was due this time; temps keeps its error count.
+        stats
+            .lock()
+            .unwrap()
+            .record_read("cpu", Duration::from_millis(4), true);
+        let readings = source.read(now_utc()).unwrap();
+        assert_eq!(
+            values(&readings, "daemon_read_duration"),
+            [(Some("cpu".into()), 4.0)]
+        );
+        assert_eq!(values(&readings, "daemon_read_errors").len(), 2);
+        let cpu = values(&readings, "daemon_cpu");
+        assert_eq!(cpu.len(), 1);
+        assert!(cpu[0].1 > 10.0, "{cpu:?}");
+    }
+
+    #[test]
+    fn write_errors_count_sinks_failing_this_cycle() {
+        let now = now_utc();
+        let earlier = now - time::Duration::minutes(1);
+        let sink = |name: &str, last_error_at| SourceHealth {
+            name: name.into(),
+            last_error_at,
+            ..Default::default()
+        };
+        let spooling = SourceHealth {
+            backlog: Some(Backlog {
+                queued: 40,
+                spooled_bytes: 2048,
+                dropped: 3,
+            }),
+            ..sink("sqlite", Some(now))
+        };
+        let mut stats = CycleStats::default();
+        stats.record_write(
+            12,
+            &[spooling, sink("jsonl", Some(earlier)), sink("stdout", None)],
+            now,
+        );
+        assert_eq!(stats.cycle_samples, Some(12));
+        assert_eq!(
+            stats.write_errors.clone().into_iter().collect::<Vec<_>>(),
+            [
+                ("jsonl".to_string(), 0),
+                ("sqlite".to_string(), 1),
+                ("stdout".to_string(), 0)
+            ]
+        );
+        let stats = SharedStats::new(Mutex::new(stats));
+        let mut source = DaemonSource::new(stats, "/nonexistent/wtui.db".into());
+        let readings = source.read(now).unwrap();
+        assert_eq!(
+            values(&readings, "daemon_sink_backlog"),
+            [(Some("sqlite".into()), 40.0)]
+        );
+        assert_eq!(
+            values(&readings, "daemon_sink_dropped"),
+            [(Some("sqlite".into()), 3.0)]
+        );
+    }
+}
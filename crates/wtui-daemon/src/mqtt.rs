@@ -0,0 +1,165 @@
+use anyhow::Result;
+use rumqttc::{Client, Event, LastWill, MqttOptions, Outgoing, Packet, QoS};
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+use tracing::{info, warn};
+use wtui_core::metrics::MetricReading;
+use wtui_core::mqtt::{availability_topic, broker_address, discovery, state_topic};
+use wtui_core::MqttSinkConfig;
+
+use crate::sink::Sink;
+
+/// Publishes waiting in the client before writes start failing.
+const QUEUE: usize = 1_000;
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Publishes each reading, retained, to its own topic. The broker marks the
+/// host `offline` through a retained last will if the daemon dies; with
+/// `discovery`, Home Assistant configs are (re)sent after every connect and
+/// whenever Home Assistant announces it came online.
+pub struct MqttSink {
+    config: MqttSinkConfig,
+    host: String,
+    client: Client,
+    /// Set by the connection thread when discovery must be sent again.
+    rediscover: Arc<AtomicBool>,
+    announced: HashSet<String>,
+    stopping: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MqttSink {
+    pub fn connect(config: &MqttSinkConfig, default_host: &str) -> Result<Self> {
+        let host = config
+            .host
+            .clone()
+            .unwrap_or_else(|| default_host.to_string());
+        let (broker, port) = broker_address(&config.broker)?;
+        let client_id = config
+            .client_id
+            .clone()
+            .unwrap_or_else(|| format!("wtui-{host}"));
+        let status = availability_topic(&config.topic_prefix, &host);
+        let mut options = MqttOptions::new(client_id, broker, port);
+        options
+            .set_keep_alive(Duration::from_secs(30))
+            .set_last_will(LastWill::new(&status, "offline", QoS::AtLeastOnce, true));
+        if let Some(username) = &config.username {
+            options.set_credentials(username, config.password.clone().unwrap_or_default());
+        }
+        let (client, mut connection) = Client::new(options, QUEUE);
+
+        let rediscover = Arc::new(AtomicBool::new(true));
+        let stopping = Arc::new(AtomicBool::new(false));
+        let ha_status = format!("{}/status", config.discovery_prefix);
+        let discovery_enabled = config.discovery;
+        let (thread_client, thread_rediscover, thread_stopping) =
+            (client.clone(), rediscover.clone(), stopping.clone());
+        let name = config.broker.clone();
+        let thread = thread::Builder::new()
+            .name(format!("mqtt {name}"))
+            .spawn(move || {
+                let mut failing = false;
+                for event in connection.iter() {
+                    match event {
+                        Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                            info!("mqtt {name}: connected");
+                            failing = false;
+                            let _ = thread_client.try_publish(
+                                &status,
+                                QoS::AtLeastOnce,
+                                true,
+                                "online",
+                            );
+                            if discovery_enabled {
+                                let _ = thread_client.try_subscribe(&ha_status, QoS::AtMostOnce);
+                                thread_rediscover.store(true, Ordering::SeqCst);
+                            }
+                        }
+                        Ok(Event::Incoming(Packet::Publish(publish)))
+                            if publish.topic == ha_status && &publish.payload[..] == b"online" =>
+                        {
+                            thread_rediscover.store(true, Ordering::SeqCst);
+                        }
+                        Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+                        Ok(_) => {}
+                        Err(_) if thread_stopping.load(Ordering::SeqCst) => break,
+                        Err(err) => {
+                            if !failing {
+                                warn!("mqtt {name}: {err}; reconnecting");
+                                failing = true;
+                            }
+                            thread::sleep(RECONNECT_DELAY);
+                        }
+                    }
+                }
+            })?;
+
+        Ok(Self {
+            config: config.clone(),
+            host,
+            client,
+            rediscover,
+            announced: HashSet::new(),
+            stopping,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Sink for MqttSink {
+    fn name(&self) -> String {
+        format!("mqtt {}", self.config.broker)
+    }
+
+    fn write(&mut self, readings: &[MetricReading]) -> Result<()> {
+        if self.rediscover.swap(false, Ordering::SeqCst) {
+            self.announced.clear();
+        }
+        for reading in readings {
+            if self.config.discovery {
+                if let Some(config) = discovery(
+                    reading,
+                    &self.config.discovery_prefix,
+                    &self.config.topic_prefix,
+                    &self.host,
+                )
+                .filter(|d| !self.announced.contains(&d.object_id))
+                {
+                    self.client.try_publish(
+                        config.topic,
+                        QoS::AtLeastOnce,
+                        true,
+                        config.payload,
+                    )?;
+                    self.announced.insert(config.object_id);
+                }
+            }
+            self.client.try_publish(
+                state_topic(&self.config.topic_prefix, &self.host, reading),
+                QoS::AtMostOnce,
+                true,
+                reading.value.to_string(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for MqttSink {
+    /// A clean shutdown doesn't trigger the last will, so say goodbye.
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        let status = availability_topic(&self.config.topic_prefix, &self.host);
+        let _ = self
+            .client
+            .try_publish(status, QoS::AtLeastOnce, true, "offline");
+        let _ = self.client.try_disconnect();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::time::{Duration, Instant};
+use tracing::{debug, info};
+
+/// Talks to systemd over `$NOTIFY_SOCKET` (see sd_notify(3)), so the daemon
+/// works as a `Type=notify` unit with `WatchdogSec=`.
+pub struct Notifier {
+    socket: UnixDatagram,
+    addr: SocketAddr,
+    /// Half of `WATCHDOG_USEC`, as systemd recommends.
+    watchdog: Option<Duration>,
+    last_ping: Instant,
+    last_status: String,
+}
+
+/// What systemd passed in the environment for [`Notifier`].
+pub struct NotifyEnv {
+    socket: OsString,
+    watchdog: Option<Duration>,
+}
+
+impl NotifyEnv {
+    /// `None` unless systemd passed a notify socket. Takes the variables
+    /// out of the environment so hook commands don't inherit them, which is
+    /// only sound before the process starts any thread: call it first thing
+    /// in `main`.
+    pub fn take() -> Option<Self> {
+        let socket = std::env::var_os("NOTIFY_SOCKET");
+        let watchdog = watchdog_interval();
+        for var in ["NOTIFY_SOCKET", "WATCHDOG_USEC", "WATCHDOG_PID"] {
+            std::env::remove_var(var);
+        }
+        Some(Self {
+            socket: socket?,
+            watchdog,
+        })
+    }
+}
+
+impl Notifier {
+    pub fn connect(env: NotifyEnv) -> Result<Self> {
+        let path = env.socket.to_string_lossy().into_owned();
+        let addr = match path.strip_prefix('@') {
+            Some(name) => SocketAddr::from_abstract_name(name),
+            None => SocketAddr::from_pathname(&path),
+        }
+        .with_context(|| format!("invalid NOTIFY_SOCKET {path:?}"))?;
+        let socket = UnixDatagram::unbound().context("creating notify socket")?;
+        if let Some(interval) = env.watchdog {
+            info!("systemd watchdog enabled; pinging every {interval:?}");
+        }
+        Ok(Self {
+            socket,
+            addr,
+            watchdog: env.watchdog,
+            last_ping: Instant::now(),
+            last_status: String::new(),
+        })
+    }
+
+    /// Sends newline-separated `KEY=value` assignments. Failures are only
+    /// logged: systemd going away must not take the daemon down.
+    pub fn notify(&self, state: &str) {
+        if let Err(err) = self.socket.send_to_addr(state.as_bytes(), &self.addr) {
+            debug!("sd_notify {state:?} failed: {err}");
+        }
+    }
+
+    /// Updates `STATUS=`, skipping the send when it hasn't changed.
+    pub fn status(&mut self, status: String) {
+        if status != self.last_status {
+            self.notify(&format!("STATUS={status}"));
+            self.last_status = status;
+        }
+    }
+
+    /// Pings the watchdog if half its timeout has passed since the last one.
+    pub fn ping_watchdog(&mut self) {
+        if self
+            .watchdog
+            .is_some_and(|interval| self.last_ping.elapsed() >= interval)
+        {
+            self.notify("WATCHDOG=1");
+            self.last_ping = Instant::now();
+        }
+    }
+
+    /// When the watchdog next needs a ping, so the main loop wakes in time.
+    pub fn next_ping(&self) -> Option<Instant> {
+        self.watchdog.map(|interval| self.last_ping + interval)
+    }
+}
+
+/// `WATCHDOG_USEC / 2`, when the watchdog is meant for this process.
+fn watchdog_interval() -> Option<Duration> {
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok()? != std::process::id() {
+            return None;
+        }
+    }
+    (usec > 0).then(|| Duration::from_micros(usec / 2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sends_sd_notify_datagrams() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notify.sock");
+        let systemd = UnixDatagram::bind(&path).unwrap();
+        systemd
+            .set_read_timeout(Some(Duration::from_secs(1)))
+            .unwrap();
+        let received = || {
+            let mut buf = [0; 256];
+            let len = systemd.recv(&mut buf).unwrap();
+            String::from_utf8_lossy(&buf[..len]).into_owned()
+        };
+
+        let mut notifier = Notifier::connect(NotifyEnv {
+            socket: path.into_os_string(),
+            watchdog: Some(Duration::ZERO),
+        })
+        .unwrap();
+        notifier.notify("READY=1\nSTATUS=starting");
+        assert_eq!(received(), "READY=1\nSTATUS=starting");
+        notifier.status("last cycle took 3ms".into());
+        notifier.status("last cycle took 3ms".into());
+        notifier.ping_watchdog();
+        notifier.notify("STOPPING=1");
+        assert_eq!(received(), "STATUS=last cycle took 3ms");
+        assert_eq!(received(), "WATCHDOG=1");
+        assert_eq!(received(), "STOPPING=1");
+    }
+}
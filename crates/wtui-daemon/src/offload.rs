@@ -0,0 +1,170 @@
+use anyhow::Result;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use time::OffsetDateTime;
+use wtui_core::metrics::{MetricKind, MetricReading};
+use wtui_core::sources::MetricSource;
+use wtui_core::EventRow;
+
+type Finished = (Box<dyn MetricSource>, Result<Vec<MetricReading>>);
+
+/// Reads a slow source on a thread of its own, so a command running up to
+/// its timeout doesn't hold up the main loop: other sources' slots, control
+/// replies and watchdog pings. Each read starts the next run and returns
+/// the last completed one, stamped with the slot it was started for.
+pub struct OffThread {
+    name: String,
+    kind: MetricKind,
+    /// The source, while no run is in flight.
+    idle: Option<Box<dyn MetricSource>>,
+    running: Option<Receiver<Finished>>,
+    /// The last run's result, until the next read returns it.
+    finished: Option<Result<Vec<MetricReading>>>,
+    events: Vec<EventRow>,
+}
+
+impl OffThread {
+    pub fn new(source: Box<dyn MetricSource>) -> Self {
+        Self {
+            name: source.name().to_string(),
+            kind: source.kind(),
+            idle: Some(source),
+            running: None,
+            finished: None,
+            events: Vec::new(),
+        }
+    }
+
+    /// Moves `[[daemon.custom]]` commands off the main loop; other sources
+    /// pass through unchanged.
+    pub fn custom(source: Box<dyn MetricSource>) -> Box<dyn MetricSource> {
+        if source.kind() == MetricKind::Custom {
+            Box::new(Self::new(source))
+        } else {
+            source
+        }
+    }
+
+    /// Collects the run in flight if it has finished.
+    fn poll(&mut self) {
+        let Some(running) = &self.running else {
+            return;
+        };
+        match running.try_recv() {
+            Ok((mut source, result)) => {
+                self.events.extend(source.take_events());
+                self.idle = Some(source);
+                self.finished = Some(result);
+            }
+            Err(TryRecvError::Empty) => return,
+            // The source panicked and went down with its thread.
+            Err(TryRecvError::Disconnected) => {}
+        }
+        self.running = None;
+    }
+}
+
+impl MetricSource for OffThread {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn kind(&self) -> MetricKind {
+        self.kind
+    }
+
+    /// Skips the slot while the previous run is still going, rather than
+    /// piling up runs of a command that is slower than its interval.
+    fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+        self.poll();
+        if self.idle.is_none() && self.running.is_none() {
+            anyhow::bail!("{} read panicked", self.name);
+        }
+        if let Some(mut source) = self.idle.take() {
+            let (tx, rx) = mpsc::channel();
+            thread::spawn(move || {
+                let result = source.read(now);
+                let _ = tx.send((source, result));
+            });
+            self.running = Some(rx);
+        }
+        self.finished.take().unwrap_or_else(|| Ok(Vec::new()))
+    }
+
+    fn take_events(&mut self) -> Vec<EventRow> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// A run still in flight is left to finish on its own.
+    fn finish(&mut self) {
+        if let Some(source) = &mut self.idle {
+            source.finish();
+            self.events.extend(source.take_events());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+    use wtui_core::now_utc;
+
+    /// Takes `delay` per read and reports how many reads it has done.
+    struct Slow {
+        delay: Duration,
+        reads: u32,
+    }
+
+    impl MetricSource for Slow {
+        fn name(&self) -> &str {
+            "custom:slow"
+        }
+
+        fn kind(&self) -> MetricKind {
+            MetricKind::Custom
+        }
+
+        fn read(&mut self, now: OffsetDateTime) -> Result<Vec<MetricReading>> {
+            thread::sleep(self.delay);
+            self.reads += 1;
+            Ok(vec![MetricReading::new(
+                now,
+                MetricKind::Custom,
+                "slow",
+                None,
+                self.reads as f64,
+                None,
+            )])
+        }
+    }
+
+    fn wait_for(source: &mut OffThread) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while source.finished.is_none() {
+            assert!(Instant::now() < deadline, "read never finished");
+            thread::sleep(Duration::from_millis(5));
+            source.poll();
+        }
+    }
+
+    #[test]
+    fn read_returns_without_waiting_for_the_source() {
+        let mut source = OffThread::new(Box::new(Slow {
+            delay: Duration::from_millis(300),
+            reads: 0,
+        }));
+        let first = now_utc();
+        let started = Instant::now();
+        assert!(source.read(first).unwrap().is_empty());
+        // Still running: the slot is skipped rather than queued.
+        assert!(source.read(now_utc()).unwrap().is_empty());
+        assert!(started.elapsed() < Duration::from_millis(100));
+
+        wait_for(&mut source);
+        let readings = source.read(now_utc()).unwrap();
+        assert_eq!(readings.len(), 1);
+        assert_eq!(readings[0].value, 1.0);
+        assert_eq!(readings[0].timestamp, first);
+    }
+}
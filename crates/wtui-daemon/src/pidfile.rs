@@ -0,0 +1,208 @@
+use anyhow::{Context, Result};
+use nix::errno::Errno;
+use nix::fcntl::{flock, FlockArg};
+use nix::sys::signal::{kill, Signal};
+use nix::unistd::Pid;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+/// How long `--replace` waits for the running daemon to shut down.
+const REPLACE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Single-instance guard: an exclusive `flock` on the PID file, held for
+/// the daemon's lifetime. The kernel drops the lock when the process dies,
+/// so a stale file never blocks a start and a reused PID is never mistaken
+/// for a running daemon.
+pub struct PidGuard {
+    path: Option<PathBuf>,
+    file: Option<File>,
+}
+
+impl PidGuard {
+    /// Takes the lock, or with `replace` asks the daemon holding it to stop
+    /// (`SIGTERM`) and takes it once that one has shut down.
+    pub fn acquire(path: Option<PathBuf>, replace: bool) -> Result<Self> {
+        let Some(path) = path else {
+            anyhow::ensure!(!replace, "--replace needs `daemon.pid_file`");
+            return Ok(Self {
+                path: None,
+                file: None,
+            });
+        };
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = match try_lock(&path)? {
+            Some(file) => {
+                // A daemon from before the file was locked never takes the
+                // lock, so also check who the file names.
+                if let Some(pid) = read_pid(&path).filter(|pid| is_other_daemon(*pid)) {
+                    anyhow::ensure!(replace, "{}", running(Some(pid)));
+                    stop(pid, || Ok((!is_other_daemon(pid)).then_some(())))?;
+                }
+                file
+            }
+            None if replace => take_over(&path)?,
+            None => anyhow::bail!("{}", running(read_pid(&path))),
+        };
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        writeln!(file, "{}", std::process::id())?;
+        file.flush()?;
+        Ok(Self {
+            path: Some(path),
+            file: Some(file),
+        })
+    }
+}
+
+impl Drop for PidGuard {
+    /// Removes the file while still holding the lock, so whoever locks it
+    /// next notices the unlinked inode and starts over on a fresh file.
+    fn drop(&mut self) {
+        if let Some(path) = &self.path {
+            let _ = std::fs::remove_file(path);
+        }
+        drop(self.file.take());
+    }
+}
+
+/// The locked PID file, or `None` while another process holds the lock.
+fn try_lock(path: &Path) -> Result<Option<File>> {
+    loop {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("opening {path:?}"))?;
+        match flock(file.as_raw_fd(), FlockArg::LockExclusiveNonblock) {
+            Ok(()) => {}
+            Err(Errno::EWOULDBLOCK) => return Ok(None),
+            Err(err) => return Err(err).with_context(|| format!("locking {path:?}")),
+        }
+        // The previous holder may have removed the file between our open
+        // and the lock; only the inode still at `path` counts.
+        let current = std::fs::metadata(path).map(|m| m.ino()).ok();
+        if current == Some(file.metadata()?.ino()) {
+            return Ok(Some(file));
+        }
+    }
+}
+
+fn take_over(path: &Path) -> Result<File> {
+    let pid = read_pid(path).with_context(|| {
+        format!("a wtui-daemon holds {path:?} but its PID isn't in the file yet")
+    })?;
+    stop(pid, || try_lock(path))
+}
+
+/// Sends `pid` a `SIGTERM` and waits until `stopped` returns something.
+fn stop<T>(pid: u32, mut stopped: impl FnMut() -> Result<Option<T>>) -> Result<T> {
+    info!("asking {} to stop", describe(Some(pid)));
+    kill(Pid::from_raw(pid as i32), Signal::SIGTERM)
+        .with_context(|| format!("stopping {}", describe(Some(pid))))?;
+    let deadline = Instant::now() + REPLACE_TIMEOUT;
+    while Instant::now() < deadline {
+        if let Some(done) = stopped()? {
+            info!("took over from pid {pid}");
+            return Ok(done);
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+    anyhow::bail!(
+        "{} did not stop within {}s",
+        describe(Some(pid)),
+        REPLACE_TIMEOUT.as_secs()
+    )
+}
+
+fn running(pid: Option<u32>) -> String {
+    format!(
+        "another wtui-daemon is running as {}; stop it or start with --replace",
+        describe(pid)
+    )
+}
+
+/// Whether `pid` is alive, isn't us, and runs `wtui-daemon`.
+fn is_other_daemon(pid: u32) -> bool {
+    pid != std::process::id()
+        && std::fs::read_to_string(format!("/proc/{pid}/comm"))
+            .is_ok_and(|comm| comm.trim() == "wtui-daemon")
+}
+
+fn read_pid(path: &Path) -> Option<u32> {
+    let mut content = String::new();
+    File::open(path).ok()?.read_to_string(&mut content).ok()?;
+    content.trim().parse().ok()
+}
+
+/// `pid 1234 (wtui-daemon)`, naming the command from `/proc/<pid>/comm` so
+/// an unexpected holder is easy to spot.
+fn describe(pid: Option<u32>) -> String {
+    let Some(pid) = pid else {
+        return "an unknown pid".into();
+    };
+    match std::fs::read_to_string(format!("/proc/{pid}/comm")) {
+        Ok(comm) => format!("pid {pid} ({})", comm.trim()),
+        Err(_) => format!("pid {pid}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn second_instance_is_refused_until_the_first_exits() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wtui.pid");
+        let first = PidGuard::acquire(Some(path.clone()), false).unwrap();
+        assert_eq!(read_pid(&path), Some(std::process::id()));
+
+        let err = PidGuard::acquire(Some(path.clone()), false).err().unwrap();
+        assert!(format!("{err:#}").contains("another wtui-daemon is running"));
+
+        drop(first);
+        assert!(!path.exists());
+        let _second = PidGuard::acquire(Some(path.clone()), false).unwrap();
+        assert_eq!(read_pid(&path), Some(std::process::id()));
+    }
+
+    #[test]
+    fn stale_file_is_taken_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wtui.pid");
+        // No process can have this PID: it's above `pid_max`.
+        std::fs::write(&path, "99999999\n").unwrap();
+        let _guard = PidGuard::acquire(Some(path.clone()), false).unwrap();
+        assert_eq!(read_pid(&path), Some(std::process::id()));
+    }
+
+    #[test]
+    fn unlocked_daemon_named_in_the_file_is_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wtui.pid");
+        // Stands in for a daemon that wrote its PID without locking.
+        let binary = dir.path().join("wtui-daemon");
+        std::fs::copy("/bin/sleep", &binary).unwrap();
+        let mut old = Command::new(&binary).arg("30").spawn().unwrap();
+        std::fs::write(&path, format!("{}\n", old.id())).unwrap();
+        let reaper = thread::spawn(move || old.wait());
+
+        let err = PidGuard::acquire(Some(path.clone()), false).err().unwrap();
+        assert!(format!("{err:#}").contains("(wtui-daemon)"), "{err:#}");
+
+        let _guard = PidGuard::acquire(Some(path.clone()), true).unwrap();
+        assert!(!reaper.join().unwrap().unwrap().success());
+        assert_eq!(read_pid(&path), Some(std::process::id()));
+    }
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use time::OffsetDateTime;
+use tracing::warn;
+use wtui_core::quota::{format_bytes, quota_usage, QuotaUsage};
+use wtui_core::{Database, EventRow, QuotaConfig};
+
+use crate::hook;
+
+/// Usage is summed from the database at most this often.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Warns as quotas pass their `warn_at` percentages, once per threshold and
+/// billing cycle. Warnings are recorded as `quota_warning` events, which is
+/// also how a restarted daemon knows what it already warned about.
+#[derive(Default)]
+pub struct QuotaWatch {
+    last_check: Option<Instant>,
+    /// Highest threshold warned about, per interface and cycle start.
+    warned: HashMap<(String, i64), f64>,
+}
+
+impl QuotaWatch {
+    /// Returns the `quota_warning` events of the warnings it sent.
+    pub fn check(
+        &mut self,
+        db: &Database,
+        quotas: &[QuotaConfig],
+        now: OffsetDateTime,
+    ) -> Vec<EventRow> {
+        let mut events = Vec::new();
+        if quotas.is_empty()
+            || self
+                .last_check
+                .is_some_and(|last| last.elapsed() < CHECK_INTERVAL)
+        {
+            return events;
+        }
+        self.last_check = Some(Instant::now());
+        for quota in quotas {
+            let usage = match quota_usage(db, quota, now) {
+                Ok(usage) => usage,
+                Err(err) => {
+                    warn!("quota {}: {err:#}", quota.interface);
+                    continue;
+                }
+            };
+            let Some(threshold) = usage.threshold_reached(&quota.warn_at) else {
+                continue;
+            };
+            let key = (quota.interface.clone(), usage.period_start.unix_timestamp());
+            let warned = match self.warned.get(&key) {
+                Some(warned) => *warned,
+                None => previously_warned(db, &usage),
+            };
+            if threshold > warned {
+                events.push(notify(quota, &usage, threshold));
+            }
+            self.warned.insert(key, threshold.max(warned));
+        }
+        self.warned
+            .retain(|(_, start), _| *start >= (now - time::Duration::days(31)).unix_timestamp());
+        events
+    }
+}
+
+fn previously_warned(db: &Database, usage: &QuotaUsage) -> f64 {
+    let events = db
+        .fetch_events(Some(usage.period_start), Some("quota_warning"))
+        .unwrap_or_default();
+    events
+        .iter()
+        .filter_map(|event| {
+            serde_json::from_str::<serde_json::Value>(event.detail.as_deref()?).ok()
+        })
+        .filter(|detail| detail["interface"] == usage.interface.as_str())
+        .filter_map(|detail| detail["threshold"].as_f64())
+        .fold(f64::MIN, f64::max)
+}
+
+fn notify(quota: &QuotaConfig, usage: &QuotaUsage, threshold: f64) -> EventRow {
+    warn!(
+        "quota {}: {:.0}% used ({} of {}), {} projected by {}",
+        usage.interface,
+        usage.percent(),
+        format_bytes(usage.used),
+        format_bytes(usage.limit),
+        format_bytes(usage.projected()),
+        usage.period_end.date(),
+    );
+    let detail = serde_json::json!({
+        "interface": usage.interface,
+        "threshold": threshold,
+        "used": usage.used,
+        "limit": usage.limit,
+        "projected": usage.projected(),
+        "period_start": usage.period_start.unix_timestamp(),
+        "period_end": usage.period_end.unix_timestamp(),
+    })
+    .to_string();
+    if let Some(command) = &quota.command {
+        let env = [
+            ("WTUI_QUOTA_INTERFACE", usage.interface.clone()),
+            ("WTUI_QUOTA_THRESHOLD", threshold.to_string()),
+            ("WTUI_QUOTA_PERCENT", format!("{:.1}", usage.percent())),
+            ("WTUI_QUOTA_USED", usage.used.to_string()),
+            ("WTUI_QUOTA_LIMIT", usage.limit.to_string()),
+            ("WTUI_QUOTA_REMAINING", usage.remaining().to_string()),
+            ("WTUI_QUOTA_PROJECTED", usage.projected().to_string()),
+            (
+                "WTUI_QUOTA_RESET",
+                usage.period_end.unix_timestamp().to_string(),
+            ),
+        ];
+        hook::spawn(format!("quota {}", usage.interface), command, &env);
+    }
+    EventRow::new(usage.at, "quota_warning", None, Some(&detail))
+}
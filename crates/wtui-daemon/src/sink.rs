@@ -0,0 +1,283 @@
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use time::OffsetDateTime;
+use tracing::{debug, info, warn};
+use wtui_core::control::{Backlog, SourceHealth};
+use wtui_core::metrics::{read_hostname, MetricReading};
+use wtui_core::stream::encode_line;
+use wtui_core::{Config, Database, EventRow, ForwardConfig, SinkConfig};
+
+use crate::forward::Forwarder;
+use crate::spool::{Batch, Spool};
+
+/// Somewhere the readings of each collection cycle go.
+pub trait Sink {
+    fn name(&self) -> String;
+
+    fn write(&mut self, readings: &[MetricReading]) -> Result<()>;
+
+    /// Writes events (suspend, alerts, quota warnings...); `None` from sinks
+    /// that don't store them.
+    fn write_events(&mut self, _events: &[EventRow]) -> Option<Result<()>> {
+        None
+    }
+
+    /// What the sink is holding back, for sinks that queue failed writes.
+    fn backlog(&self) -> Option<Backlog> {
+        None
+    }
+}
+
+/// Writes readings and events to the database, queueing batches in a
+/// `Spool` while it fails (locked, disk full, home unmounted...) and
+/// catching up in order once it accepts writes again.
+pub struct SqliteSink {
+    db: Rc<Database>,
+    spool: Spool,
+}
+
+impl SqliteSink {
+    pub fn new(db: Rc<Database>, spool: Spool) -> Self {
+        Self { db, spool }
+    }
+
+    fn store(&mut self, batch: Batch) -> Result<()> {
+        let db = &self.db;
+        let insert = |batch: &Batch| match batch {
+            Batch::Readings(readings) => db.insert_readings(readings).map(|_| ()),
+            Batch::Events(events) => db.insert_events(events),
+        };
+        if self.spool.is_empty() {
+            if let Err(err) = insert(&batch) {
+                self.spool.push(batch);
+                return Err(err);
+            }
+            return Ok(());
+        }
+        self.spool.push(batch);
+        self.spool.replay(insert)
+    }
+}
+
+impl Sink for SqliteSink {
+    fn name(&self) -> String {
+        "sqlite".into()
+    }
+
+    fn write(&mut self, readings: &[MetricReading]) -> Result<()> {
+        self.store(Batch::Readings(readings.to_vec()))
+    }
+
+    fn write_events(&mut self, events: &[EventRow]) -> Option<Result<()>> {
+        Some(self.store(Batch::Events(events.to_vec())))
+    }
+
+    fn backlog(&self) -> Option<Backlog> {
+        Some(self.spool.backlog())
+    }
+}
+
+/// Appends readings to a file in the reading stream's format, one JSON
+/// object per line.
+pub struct JsonlSink {
+    path: PathBuf,
+    file: BufWriter<File>,
+}
+
+impl JsonlSink {
+    pub fn open(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("creating directory {parent:?}"))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("opening {path:?}"))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: BufWriter::new(file),
+        })
+    }
+}
+
+impl Sink for JsonlSink {
+    fn name(&self) -> String {
+        format!("jsonl {}", self.path.display())
+    }
+
+    fn write(&mut self, readings: &[MetricReading]) -> Result<()> {
+        for reading in readings {
+            self.file.write_all(encode_line(reading)?.as_bytes())?;
+        }
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+pub struct StdoutSink;
+
+impl Sink for StdoutSink {
+    fn name(&self) -> String {
+        "stdout".into()
+    }
+
+    fn write(&mut self, readings: &[MetricReading]) -> Result<()> {
+        let mut out = std::io::stdout().lock();
+        for reading in readings {
+            out.write_all(encode_line(reading)?.as_bytes())?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum OutputConfig {
+    Sink(SinkConfig),
+    Forward(ForwardConfig),
+}
+
+struct Output {
+    config: OutputConfig,
+    sink: Box<dyn Sink>,
+    health: SourceHealth,
+}
+
+/// Every configured sink. Each one fails on its own: an error is logged as
+/// a warning the first time and then only once the sink recovers, and never
+/// keeps the readings from the others.
+#[derive(Default)]
+pub struct Sinks {
+    outputs: Vec<Output>,
+}
+
+impl Sinks {
+    /// Opens `daemon.sinks` and `daemon.forward`, keeping the outputs of
+    /// `previous` whose settings haven't changed (so forwarders keep their
+    /// queues and files stay open across reloads).
+    pub fn build(config: &Config, db: &Rc<Database>, previous: Sinks) -> Sinks {
+        let mut previous = previous.outputs;
+        let host =
+            read_hostname(&config.daemon.host_roots()).unwrap_or_else(|_| "localhost".into());
+        let wanted = config
+            .daemon
+            .sinks
+            .iter()
+            .cloned()
+            .map(OutputConfig::Sink)
+            .chain(
+                config
+                    .daemon
+                    .forward
+                    .iter()
+                    .cloned()
+                    .map(OutputConfig::Forward),
+            );
+        let mut outputs = Vec::new();
+        for wanted in wanted {
+            if let Some(i) = previous.iter().position(|o| o.config == wanted) {
+                outputs.push(previous.swap_remove(i));
+                continue;
+            }
+            match open(&wanted, db, config, &host) {
+                Ok(sink) => outputs.push(Output {
+                    health: SourceHealth {
+                        name: sink.name(),
+                        ..Default::default()
+                    },
+                    config: wanted,
+                    sink,
+                }),
+                Err(err) => warn!("sink disabled: {err:#}"),
+            }
+        }
+        Sinks { outputs }
+    }
+
+    pub fn write(&mut self, readings: &[MetricReading], now: OffsetDateTime) {
+        if readings.is_empty() {
+            return;
+        }
+        for output in &mut self.outputs {
+            let result = output.sink.write(readings);
+            record(&mut output.health, result, now);
+        }
+    }
+
+    /// Hands `events` to the sinks that store them; false when none does.
+    pub fn write_events(&mut self, events: &[EventRow], now: OffsetDateTime) -> bool {
+        let mut stored = false;
+        for output in &mut self.outputs {
+            if let Some(result) = output.sink.write_events(events) {
+                record(&mut output.health, result, now);
+                stored = true;
+            }
+        }
+        stored
+    }
+
+    pub fn health(&self) -> Vec<SourceHealth> {
+        self.outputs
+            .iter()
+            .map(|o| SourceHealth {
+                backlog: o.sink.backlog(),
+                ..o.health.clone()
+            })
+            .collect()
+    }
+}
+
+fn record(health: &mut SourceHealth, result: Result<()>, now: OffsetDateTime) {
+    match result {
+        Ok(()) => {
+            if health.failures > 0 {
+                info!(
+                    "{}: writing again after {} failures",
+                    health.name, health.failures
+                );
+            }
+            health.last_ok = Some(now);
+            health.failures = 0;
+        }
+        Err(err) => {
+            if health.failures == 0 {
+                warn!("{}: write failed: {err:#}", health.name);
+            } else {
+                debug!("{}: write failed: {err:#}", health.name);
+            }
+            health.last_error = Some(format!("{err:#}"));
+            health.last_error_at = Some(now);
+            health.failures += 1;
+        }
+    }
+}
+
+fn open(
+    config: &OutputConfig,
+    db: &Rc<Database>,
+    cfg: &Config,
+    host: &str,
+) -> Result<Box<dyn Sink>> {
+    Ok(match config {
+        OutputConfig::Sink(SinkConfig::Sqlite) => Box::new(SqliteSink::new(
+            db.clone(),
+            Spool::open(&cfg.daemon.spool, cfg.spool_path()),
+        )),
+        OutputConfig::Sink(SinkConfig::Jsonl { path }) => Box::new(JsonlSink::open(path)?),
+        OutputConfig::Sink(SinkConfig::Stdout) => Box::new(StdoutSink),
+        #[cfg(feature = "mqtt")]
+        OutputConfig::Sink(SinkConfig::Mqtt(mqtt)) => {
+            Box::new(crate::mqtt::MqttSink::connect(mqtt, host)?)
+        }
+        #[cfg(not(feature = "mqtt"))]
+        OutputConfig::Sink(SinkConfig::Mqtt(_)) => {
+            anyhow::bail!("wtui-daemon was built without the `mqtt` feature")
+        }
+        OutputConfig::Forward(forward) => Box::new(Forwarder::spawn(forward, host)?),
+    })
+}
@@ -0,0 +1,437 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+use wtui_core::control::Backlog;
+use wtui_core::metrics::MetricReading;
+use wtui_core::stream::encode_line;
+use wtui_core::{EventRow, SpoolConfig};
+
+/// One write of the SQLite sink: a cycle's readings, or events.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Batch {
+    Readings(Vec<MetricReading>),
+    Events(Vec<EventRow>),
+}
+
+impl Batch {
+    fn len(&self) -> usize {
+        match self {
+            Batch::Readings(readings) => readings.len(),
+            Batch::Events(events) => events.len(),
+        }
+    }
+}
+
+/// A line of the spool file: a reading as on the reading stream, or an
+/// event wrapped as `{"event": ...}`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Line {
+    Event { event: EventRow },
+    Reading(MetricReading),
+}
+
+/// Batches that couldn't be written yet, oldest first: those spilled to
+/// the spool file, then those still in memory. The file is in the reading
+/// stream's format, plus event lines, with a blank line after each batch,
+/// and outlives the daemon, so a backlog left at shutdown is replayed by
+/// the next run.
+///
+/// Once the file is at `max_bytes`, batches that don't fit are dropped and
+/// counted; what is already spooled is kept.
+pub struct Spool {
+    path: PathBuf,
+    memory: VecDeque<Batch>,
+    in_memory: usize,
+    memory_limit: usize,
+    max_bytes: u64,
+    /// Bytes at the start of the file already written to the database.
+    offset: u64,
+    /// Readings and events, and bytes, in the file past `offset`.
+    spooled: u64,
+    spooled_bytes: u64,
+    dropped: u64,
+}
+
+impl Spool {
+    pub fn open(config: &SpoolConfig, path: PathBuf) -> Self {
+        let (spooled, spooled_bytes) = match File::open(&path) {
+            Ok(file) => {
+                let bytes = file.metadata().map(|m| m.len()).unwrap_or_default();
+                let lines = BufReader::new(file)
+                    .lines()
+                    .map_while(|line| line.ok())
+                    .filter(|line| !line.trim().is_empty())
+                    .count();
+                (lines as u64, bytes)
+            }
+            Err(_) => (0, 0),
+        };
+        if spooled > 0 {
+            info!("{spooled} spooled readings and events in {path:?} waiting to be written");
+        }
+        Self {
+            path,
+            memory: VecDeque::new(),
+            in_memory: 0,
+            memory_limit: config.memory_readings,
+            max_bytes: config.max_bytes,
+            offset: 0,
+            spooled,
+            spooled_bytes,
+            dropped: 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.memory.is_empty() && self.spooled == 0
+    }
+
+    /// Queues a batch behind the backlog, spilling the oldest in-memory
+    /// batches to the file once memory is over its limit.
+    pub fn push(&mut self, batch: Batch) {
+        self.in_memory += batch.len();
+        self.memory.push_back(batch);
+        while self.in_memory > self.memory_limit {
+            let Some(oldest) = self.memory.pop_front() else {
+                break;
+            };
+            self.in_memory -= oldest.len();
+            self.spill(&oldest);
+        }
+    }
+
+    /// Writes out the backlog in order, one spooled batch per `write`,
+    /// stopping at the first failure with the rest still queued.
+    pub fn replay(&mut self, mut write: impl FnMut(&Batch) -> Result<()>) -> Result<()> {
+        let total = self.spooled + self.in_memory as u64;
+        if self.spooled > 0 {
+            let replayed = self.replay_file(&mut write);
+            if replayed.is_err() && self.offset > 0 {
+                // Drop what was written, so a restart doesn't write it again.
+                if let Err(err) = self.compact() {
+                    warn!("spool {:?}: {err:#}", self.path);
+                }
+            }
+            replayed?;
+        }
+        while let Some(batch) = self.memory.front() {
+            write(batch)?;
+            self.in_memory -= batch.len();
+            self.memory.pop_front();
+        }
+        if total > 0 {
+            info!("wrote {total} queued readings and events");
+        }
+        Ok(())
+    }
+
+    pub fn backlog(&self) -> Backlog {
+        Backlog {
+            queued: self.spooled + self.in_memory as u64,
+            spooled_bytes: self.spooled_bytes,
+            dropped: self.dropped,
+        }
+    }
+
+    fn spill(&mut self, batch: &Batch) {
+        let mut lines = String::new();
+        match batch {
+            Batch::Readings(readings) => {
+                for line in readings.iter().filter_map(|r| encode_line(r).ok()) {
+                    lines.push_str(&line);
+                }
+            }
+            Batch::Events(events) => {
+                for event in events {
+                    lines.push_str(&serde_json::json!({ "event": event }).to_string());
+                    lines.push('\n');
+                }
+            }
+        }
+        lines.push('\n');
+        let size = self.offset + self.spooled_bytes + lines.len() as u64;
+        let appended = if size <= self.max_bytes {
+            append(&self.path, &lines)
+        } else {
+            Err(anyhow::anyhow!("full at {} bytes", self.max_bytes))
+        };
+        match appended {
+            Ok(()) => {
+                self.spooled += batch.len() as u64;
+                self.spooled_bytes += lines.len() as u64;
+            }
+            Err(err) => {
+                if self.dropped == 0 {
+                    warn!(
+                        "spool {:?}: {err:#}; dropping batches that don't fit",
+                        self.path
+                    );
+                }
+                self.dropped += batch.len() as u64;
+            }
+        }
+    }
+
+    /// Writes the file's batches from `offset` on, moving `offset` past
+    /// each one as it is written, and discards the file once all are.
+    fn replay_file(&mut self, write: &mut impl FnMut(&Batch) -> Result<()>) -> Result<()> {
+        let mut file =
+            File::open(&self.path).with_context(|| format!("opening {:?}", self.path))?;
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        let (mut readings, mut events, mut lines, mut bytes) = (Vec::new(), Vec::new(), 0, 0);
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            bytes += read as u64;
+            if read > 0 && !line.trim().is_empty() {
+                lines += 1;
+                match serde_json::from_str(&line) {
+                    Ok(Line::Reading(reading)) => readings.push(reading),
+                    Ok(Line::Event { event }) => events.push(event),
+                    Err(err) => warn!("skipping malformed spool line: {err}"),
+                }
+                continue;
+            }
+            // Each spilled batch is of one kind; both are only written in
+            // order if the file was edited by hand.
+            if !readings.is_empty() {
+                write(&Batch::Readings(std::mem::take(&mut readings)))?;
+            }
+            if !events.is_empty() {
+                write(&Batch::Events(std::mem::take(&mut events)))?;
+            }
+            self.offset += bytes;
+            self.spooled = self.spooled.saturating_sub(lines);
+            self.spooled_bytes = self.spooled_bytes.saturating_sub(bytes);
+            (lines, bytes) = (0, 0);
+            if read == 0 {
+                break;
+            }
+        }
+        self.spooled = 0;
+        self.spooled_bytes = 0;
+        self.discard_file();
+        Ok(())
+    }
+
+    /// Removes the fully written file, or failing that empties it. If
+    /// neither works, `offset` stays at its end so nothing in it is
+    /// written twice by this run.
+    fn discard_file(&mut self) {
+        let emptied = std::fs::remove_file(&self.path).or_else(|_| {
+            OpenOptions::new()
+                .write(true)
+                .truncate(true)
+                .open(&self.path)
+                .map(|_| ())
+        });
+        match emptied {
+            Ok(()) => self.offset = 0,
+            Err(err) => warn!("spool {:?}: emptying after replay: {err}", self.path),
+        }
+    }
+
+    /// Rewrites the file without the batches before `offset`.
+    fn compact(&mut self) -> Result<()> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        let mut file =
+            File::open(&self.path).with_context(|| format!("opening {:?}", self.path))?;
+        file.seek(SeekFrom::Start(self.offset))?;
+        let mut rest = File::create(&tmp).with_context(|| format!("creating {tmp:?}"))?;
+        std::io::copy(&mut file, &mut rest)?;
+        rest.sync_all()?;
+        std::fs::rename(&tmp, &self.path).with_context(|| format!("replacing {:?}", self.path))?;
+        self.offset = 0;
+        Ok(())
+    }
+}
+
+impl Drop for Spool {
+    /// Spills what is still in memory so the next run can replay it.
+    fn drop(&mut self) {
+        while let Some(batch) = self.memory.pop_front() {
+            self.in_memory -= batch.len();
+            self.spill(&batch);
+        }
+        if self.offset > 0 {
+            if self.spooled == 0 {
+                self.discard_file();
+            } else if let Err(err) = self.compact() {
+                warn!("spool {:?}: {err:#}", self.path);
+            }
+        }
+    }
+}
+
+fn append(path: &Path, lines: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("opening {path:?}"))?;
+    file.write_all(lines.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wtui_core::metrics::MetricKind;
+    use wtui_core::now_utc;
+
+    fn batch(value: f64) -> Batch {
+        Batch::Readings(vec![MetricReading::new(
+            now_utc(),
+            MetricKind::Cpu,
+            "cpu_usage",
+            Some("total"),
+            value,
+            Some("%"),
+        )])
+    }
+
+    /// An event carrying `value` as its duration.
+    fn event(value: f64) -> Batch {
+        Batch::Events(vec![EventRow::new(
+            wtui_core::utc_from_timestamp(1_700_000_000),
+            "suspend",
+            Some(value),
+            None,
+        )])
+    }
+
+    fn spool(dir: &tempfile::TempDir, memory_readings: usize, max_bytes: u64) -> Spool {
+        let config = SpoolConfig {
+            path: None,
+            memory_readings,
+            max_bytes,
+        };
+        Spool::open(&config, dir.path().join("spool.jsonl"))
+    }
+
+    /// Replays the backlog, returning the value of every batch written.
+    fn replay(spool: &mut Spool, fail_at: Option<usize>) -> (Vec<f64>, Result<()>) {
+        let mut written = Vec::new();
+        let result = spool.replay(|batch| {
+            if fail_at == Some(written.len()) {
+                anyhow::bail!("database is locked");
+            }
+            written.push(match batch {
+                Batch::Readings(readings) => readings[0].value,
+                Batch::Events(events) => events[0].duration_secs.unwrap(),
+            });
+            Ok(())
+        });
+        (written, result)
+    }
+
+    #[test]
+    fn replays_the_file_before_memory() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spool = spool(&dir, 2, u64::MAX);
+        for value in [1.0, 2.0, 3.0, 4.0] {
+            spool.push(batch(value));
+        }
+        let backlog = spool.backlog();
+        assert_eq!(backlog.queued, 4);
+        assert!(backlog.spooled_bytes > 0, "two batches should have spilled");
+
+        let (written, result) = replay(&mut spool, None);
+        result.unwrap();
+        assert_eq!(written, [1.0, 2.0, 3.0, 4.0]);
+        assert!(spool.is_empty());
+        assert!(!dir.path().join("spool.jsonl").exists());
+    }
+
+    #[test]
+    fn batches_past_max_bytes_are_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let Batch::Readings(readings) = batch(1.0) else {
+            unreachable!()
+        };
+        let line = encode_line(&readings[0]).unwrap().len() as u64 + 1;
+        let mut spool = spool(&dir, 0, line * 2);
+        for value in [1.0, 2.0, 3.0] {
+            spool.push(batch(value));
+        }
+        let backlog = spool.backlog();
+        assert_eq!((backlog.queued, backlog.dropped), (2, 1));
+
+        let (written, result) = replay(&mut spool, None);
+        result.unwrap();
+        assert_eq!(written, [1.0, 2.0]);
+    }
+
+    #[test]
+    fn reopening_picks_up_the_backlog() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut first = spool(&dir, 1, u64::MAX);
+        first.push(batch(1.0));
+        first.push(batch(2.0));
+        // 1.0 spilled over the memory limit, 2.0 spills on drop.
+        drop(first);
+
+        let mut second = spool(&dir, 10, u64::MAX);
+        assert_eq!(second.backlog().queued, 2);
+        let (written, result) = replay(&mut second, None);
+        result.unwrap();
+        assert_eq!(written, [1.0, 2.0]);
+    }
+
+    #[test]
+    fn events_keep_their_place_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut first = spool(&dir, 1, u64::MAX);
+        first.push(batch(1.0));
+        first.push(event(600.0));
+        first.push(batch(2.0));
+        drop(first);
+
+        let mut second = spool(&dir, 10, u64::MAX);
+        assert_eq!(second.backlog().queued, 3);
+        let mut written = Vec::new();
+        second
+            .replay(|batch| {
+                written.push(batch.clone());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(written.len(), 3);
+        assert!(matches!(written[0], Batch::Readings(_)));
+        assert_eq!(written[1], event(600.0));
+        assert!(matches!(written[2], Batch::Readings(_)));
+    }
+
+    #[test]
+    fn replay_stops_at_the_first_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut spool = spool(&dir, 1, u64::MAX);
+        for value in [1.0, 2.0, 3.0, 4.0] {
+            spool.push(batch(value));
+        }
+        let (written, result) = replay(&mut spool, Some(1));
+        assert!(result.is_err());
+        assert_eq!(written, [1.0]);
+        assert_eq!(spool.backlog().queued, 3);
+        drop(spool);
+
+        // The written batch is gone from the file, so it isn't written again.
+        let mut reopened = self::spool(&dir, 1, u64::MAX);
+        assert_eq!(reopened.backlog().queued, 3);
+        let (written, result) = replay(&mut reopened, None);
+        result.unwrap();
+        assert_eq!(written, [2.0, 3.0, 4.0]);
+    }
+}
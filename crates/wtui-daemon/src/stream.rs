@@ -0,0 +1,174 @@
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, warn};
+use wtui_core::metrics::MetricReading;
+use wtui_core::stream::{encode_line, HistoryRing};
+
+/// Upper bound on buffered readings, whatever the history window.
+const HISTORY_CAPACITY: usize = 50_000;
+
+/// A subscriber that can't take a line within this long is dropped rather
+/// than allowed to stall collection.
+const WRITE_TIMEOUT: Duration = Duration::from_millis(200);
+
+struct Shared {
+    history: HistoryRing,
+    subscribers: Vec<UnixStream>,
+    /// Readings published since the server started, so a replay written
+    /// outside the lock knows what it missed.
+    published: u64,
+}
+
+/// Publishes every reading the daemon takes to local subscribers (the
+/// viewer's live mode), replaying a short history to each new one. The
+/// socket file is removed on drop.
+pub struct StreamServer {
+    path: PathBuf,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl StreamServer {
+    pub fn spawn(path: &Path, history: Duration) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let _ = std::fs::remove_file(path);
+        let listener =
+            UnixListener::bind(path).with_context(|| format!("binding stream socket {path:?}"))?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        let shared = Arc::new(Mutex::new(Shared {
+            history: HistoryRing::new(history, HISTORY_CAPACITY),
+            subscribers: Vec::new(),
+            published: 0,
+        }));
+        let accept_shared = shared.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        if let Err(err) = add_subscriber(&accept_shared, stream) {
+                            debug!("stream subscriber dropped during replay: {err}");
+                        }
+                    }
+                    Err(err) => warn!("stream socket accept failed: {err}"),
+                }
+            }
+        });
+        Ok(Self {
+            path: path.to_path_buf(),
+            shared,
+        })
+    }
+
+    pub fn set_history(&self, window: Duration) {
+        self.shared.lock().unwrap().history.set_window(window);
+    }
+
+    pub fn publish(&self, readings: &[MetricReading]) {
+        if readings.is_empty() {
+            return;
+        }
+        let mut shared = self.shared.lock().unwrap();
+        shared.history.extend(readings.iter().cloned());
+        shared.published += readings.len() as u64;
+        if shared.subscribers.is_empty() {
+            return;
+        }
+        let lines: String = readings
+            .iter()
+            .filter_map(|r| encode_line(r).ok())
+            .collect();
+        shared
+            .subscribers
+            .retain_mut(|s| s.write_all(lines.as_bytes()).is_ok());
+    }
+}
+
+impl Drop for StreamServer {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Replays the history outside the lock, so a slow subscriber can't hold up
+/// `publish` (and with it the daemon's main loop), then registers it.
+fn add_subscriber(shared: &Mutex<Shared>, mut stream: UnixStream) -> std::io::Result<()> {
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let (history, replayed) = snapshot(shared);
+    write_lines(&mut stream, &history)?;
+    register(shared, stream, replayed)
+}
+
+/// The history to replay, and how many readings had been published by then.
+fn snapshot(shared: &Mutex<Shared>) -> (Vec<MetricReading>, u64) {
+    let shared = shared.lock().unwrap();
+    (shared.history.iter().cloned().collect(), shared.published)
+}
+
+/// Sends what was published since the replay was taken (`replayed` readings
+/// in), then adds the subscriber to the ones `publish` writes to.
+fn register(shared: &Mutex<Shared>, mut stream: UnixStream, replayed: u64) -> std::io::Result<()> {
+    let mut shared = shared.lock().unwrap();
+    let missed = (shared.published - replayed).min(shared.history.len() as u64) as usize;
+    let skip = shared.history.len() - missed;
+    let missed: Vec<MetricReading> = shared.history.iter().skip(skip).cloned().collect();
+    write_lines(&mut stream, &missed)?;
+    shared.subscribers.push(stream);
+    Ok(())
+}
+
+fn write_lines(stream: &mut UnixStream, readings: &[MetricReading]) -> std::io::Result<()> {
+    let lines: String = readings
+        .iter()
+        .filter_map(|r| encode_line(r).ok())
+        .collect();
+    stream.write_all(lines.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader};
+    use wtui_core::metrics::MetricKind;
+    use wtui_core::now_utc;
+
+    fn readings(from: u32, to: u32) -> Vec<MetricReading> {
+        let now = now_utc();
+        (from..to)
+            .map(|i| MetricReading::new(now, MetricKind::Cpu, "cpu_usage", None, i as f64, None))
+            .collect()
+    }
+
+    #[test]
+    fn readings_published_during_a_replay_follow_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let server =
+            StreamServer::spawn(&dir.path().join("stream.sock"), Duration::from_secs(600)).unwrap();
+        server.publish(&readings(0, 3));
+
+        let (mut ours, theirs) = UnixStream::pair().unwrap();
+        let (history, replayed) = snapshot(&server.shared);
+        // Publishing isn't held up while the replay is being written.
+        server.publish(&readings(3, 5));
+        write_lines(&mut ours, &history).unwrap();
+        register(&server.shared, ours, replayed).unwrap();
+        server.publish(&readings(5, 6));
+
+        let values: Vec<f64> = BufReader::new(theirs)
+            .lines()
+            .take(6)
+            .map(|line| {
+                serde_json::from_str::<MetricReading>(&line.unwrap())
+                    .unwrap()
+                    .value
+            })
+            .collect();
+        assert_eq!(values, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]);
+    }
+}
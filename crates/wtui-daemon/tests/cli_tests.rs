@@ -0,0 +1,36 @@
+use assert_cmd::cargo::cargo_bin_cmd;
+use predicates::str::contains;
+
+#[test]
+fn rejects_sub_second_interval_override() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = dir.path().join("config.toml");
+    let db = dir.path().join("wtui.db");
+    std::fs::write(
+        &config,
+        format!("[database]\npath = {db:?}\n\n[daemon]\ninterval = \"5s\"\n"),
+    )
+    .unwrap();
+    for interval in ["0s", "500ms"] {
+        cargo_bin_cmd!("wtui-daemon")
+            .arg("--config")
+            .arg(&config)
+            .args(["--interval", interval, "--once"])
+            .assert()
+            .failure()
+            .stderr(contains("daemon.interval must be at least 1s"));
+    }
+    assert!(!db.exists());
+}
+
+#[test]
+fn rejects_interval_override_without_config_file() {
+    let dir = tempfile::tempdir().unwrap();
+    cargo_bin_cmd!("wtui-daemon")
+        .arg("--config")
+        .arg(dir.path().join("missing.toml"))
+        .args(["--interval", "0s", "--dry-run"])
+        .assert()
+        .failure()
+        .stderr(contains("daemon.interval must be at least 1s"));
+}
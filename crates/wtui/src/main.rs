@@ -11,15 +11,22 @@ use ratatui::Terminal;
 use std::collections::HashMap;
 use std::io::{self, Write};
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
 use std::time::{Duration, Instant};
 use time::OffsetDateTime;
-use wtui_core::config::{Config, Preset};
-use wtui_core::metrics::{
-    cpu_usage_percent, read_batteries, read_cpu_times, read_disk_usage, read_net_snapshot,
-    read_powercap, read_ram_usage, read_temperatures, NetSnapshot,
-};
+use wtui_core::config::{Config, CustomMetricConfig, Preset};
+use wtui_core::metrics::{MetricKind, MetricReading, ThrottleEpisode};
+use wtui_core::quota::{format_bytes, quota_usage, QuotaUsage};
+use wtui_core::sources::{resolve_metric, Collected, CustomSource, SourceRegistry, TextfileSource};
+use wtui_core::stream::{subscribe, HistoryRing};
 use wtui_core::timeutils::{duration_from_std, duration_to_std};
-use wtui_core::{parse_range, Database, MetricPoint, MetricSeries, RangeSpec};
+use wtui_core::{parse_range, Database, EventRow, MetricPoint, MetricSeries, RangeSpec};
+
+/// Readings kept from the daemon stream for live charts.
+const LIVE_HISTORY_CAPACITY: usize = 100_000;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Mode {
@@ -55,11 +62,19 @@ struct App {
     presets: Vec<(String, Preset)>,
     selected_preset: usize,
     series: Vec<MetricSeries>,
+    events: Vec<EventRow>,
+    quotas: Vec<QuotaUsage>,
     status: String,
     filter: String,
     filter_mode: bool,
-    live_cpu_prev: Option<wtui_core::metrics::CpuTimes>,
-    live_net_prev: HashMap<String, NetSnapshot>,
+    live: SourceRegistry,
+    live_metrics: Vec<String>,
+    live_custom: HashMap<(MetricKind, String), Vec<MetricReading>>,
+    live_worker: Option<CustomWorker>,
+    live_stream: Option<Receiver<MetricReading>>,
+    live_history: HistoryRing,
+    /// Set for `--csv`, where only the first refresh is ever shown.
+    one_shot: bool,
 }
 
 impl App {
@@ -102,11 +117,18 @@ impl App {
             presets,
             selected_preset: 0,
             series: Vec::new(),
+            events: Vec::new(),
+            quotas: Vec::new(),
             status: String::from("Press q to quit, arrows to choose presets, Enter to apply"),
             filter: String::new(),
             filter_mode: false,
-            live_cpu_prev: None,
-            live_net_prev: HashMap::new(),
+            live: SourceRegistry::default(),
+            live_metrics: Vec::new(),
+            live_custom: HashMap::new(),
+            live_worker: None,
+            live_stream: None,
+            live_history: HistoryRing::new(range, LIVE_HISTORY_CAPACITY),
+            one_shot: args.csv,
         };
 
         if let Some(name) = &args.preset {
@@ -145,12 +167,36 @@ impl App {
     fn refresh(&mut self) {
         let range_spec = RangeSpec::ending_now(duration_from_std(self.range));
         let result = match self.mode {
-            Mode::Historical => self.load_from_db(range_spec),
+            Mode::Historical => {
+                // Nothing drains the stream outside live mode.
+                self.live_stream = None;
+                self.live_worker = None;
+                self.live_metrics.clear();
+                self.live_history.clear();
+                self.load_from_db(range_spec)
+            }
             Mode::Live => self.load_live(),
         };
         if let Err(err) = result {
             self.status = format!("error: {err}");
         }
+        if let Err(err) = self.load_quotas() {
+            self.status = format!("quota: {err:#}");
+        }
+    }
+
+    /// Current-cycle usage of every `[[quotas]]` entry; the totals always
+    /// come from the database, whatever the mode.
+    fn load_quotas(&mut self) -> Result<()> {
+        self.quotas.clear();
+        let Some(db) = &self.db else {
+            return Ok(());
+        };
+        let now = OffsetDateTime::now_utc();
+        for quota in &self.config.quotas {
+            self.quotas.push(quota_usage(db, quota, now)?);
+        }
+        Ok(())
     }
 
     fn load_from_db(&mut self, range: RangeSpec) -> Result<()> {
@@ -173,6 +219,20 @@ impl App {
                 series.push(s);
                 continue;
             }
+            if let Some((kind, name, custom)) = custom_metric(&self.config, metric) {
+                let rows = db.fetch_custom_series(kind, name, range.since)?;
+                let unit = custom.and_then(|c| c.unit.as_deref());
+                let mut s = MetricSeries::new(metric, unit);
+                for row in rows {
+                    s.push(MetricPoint {
+                        timestamp: row.timestamp,
+                        value: row.value,
+                        label: row.label,
+                    });
+                }
+                series.push(s);
+                continue;
+            }
             if let Some(table) = table_for_metric(metric) {
                 let rows = db.fetch_series(table, range.since)?;
                 let mut s = MetricSeries::new(metric, None);
@@ -187,137 +247,108 @@ impl App {
             }
         }
         self.series = series;
+        self.events = db.fetch_events(range.since, None)?;
         Ok(())
     }
 
+    fn in_throttle_episode(&self, timestamp: OffsetDateTime) -> bool {
+        self.events
+            .iter()
+            .filter(|e| e.kind == "throttle")
+            .any(|e| {
+                let end = e.timestamp + time::Duration::seconds_f64(e.duration_secs.unwrap_or(0.0));
+                timestamp >= e.timestamp && timestamp <= end
+            })
+    }
+
     fn load_live(&mut self) -> Result<()> {
-        let now = OffsetDateTime::now_utc();
-        let mut series = Vec::new();
-        for metric in &self.metrics {
-            match metric.as_str() {
-                "cpu" => {
-                    if let Some(point) = live_cpu_sample(&mut self.live_cpu_prev)? {
-                        let mut s = MetricSeries::new("cpu", Some("%"));
-                        s.push(point);
-                        series.push(s);
-                    }
-                }
-                "ram" => {
-                    if let Ok(ram) = read_ram_usage() {
-                        let used = ram.total_bytes.saturating_sub(ram.available_bytes) as f64;
-                        let pct = if ram.total_bytes > 0 {
-                            used / ram.total_bytes as f64 * 100.0
-                        } else {
-                            0.0
-                        };
-                        let mut s = MetricSeries::new("ram", Some("%"));
-                        s.push(MetricPoint {
-                            timestamp: now,
-                            value: pct,
-                            label: None,
-                        });
-                        series.push(s);
-                    }
-                }
-                "net" | "net_bytes" => {
-                    let mut s = MetricSeries::new("net", Some("bytes/s"));
-                    for iface in desired_interfaces(&self.config.daemon.net_interfaces) {
-                        if let Ok(snapshot) = read_net_snapshot(&iface) {
-                            let prev = self.live_net_prev.insert(iface.clone(), snapshot);
-                            if let Some(prev) = prev {
-                                let rx = snapshot.rx_bytes.saturating_sub(prev.rx_bytes);
-                                let tx = snapshot.tx_bytes.saturating_sub(prev.tx_bytes);
-                                let delta = rx + tx;
-                                s.push(MetricPoint {
-                                    timestamp: now,
-                                    value: delta as f64,
-                                    label: Some(iface.clone()),
-                                });
-                            }
-                        }
-                    }
-                    if !s.points.is_empty() {
-                        series.push(s);
-                    }
-                }
-                m if m.starts_with("battery") => {
-                    if let Ok(batts) = read_batteries() {
-                        let mut s = MetricSeries::new("battery", Some("%"));
-                        for b in batts {
-                            if let Some(cap) = b.capacity {
-                                s.push(MetricPoint {
-                                    timestamp: now,
-                                    value: cap,
-                                    label: Some(b.name.clone()),
-                                });
-                            }
-                        }
-                        if !s.points.is_empty() {
-                            series.push(s);
-                        }
-                    }
-                }
-                m if m.contains("temp") || m == "temps" => {
-                    if let Ok(temps) = read_temperatures() {
-                        let mut s = MetricSeries::new("temps", Some("C"));
-                        for t in temps {
-                            s.push(MetricPoint {
-                                timestamp: now,
-                                value: t.value_c,
-                                label: Some(t.sensor.clone()),
-                            });
-                        }
-                        if !s.points.is_empty() {
-                            series.push(s);
-                        }
-                    }
-                }
-                m if m.contains("disk") => {
-                    let mounts = if self.config.daemon.disk_devices.is_empty() {
-                        vec!["/".into()]
-                    } else {
-                        self.config.daemon.disk_devices.clone()
-                    };
-                    let mut s = MetricSeries::new("disk", Some("%"));
-                    for mount in mounts {
-                        if let Ok(usage) = read_disk_usage(&mount) {
-                            let used = usage.total_bytes.saturating_sub(usage.available_bytes);
-                            let pct = if usage.total_bytes > 0 {
-                                used as f64 / usage.total_bytes as f64 * 100.0
-                            } else {
-                                0.0
-                            };
-                            s.push(MetricPoint {
-                                timestamp: now,
-                                value: pct,
-                                label: Some(mount.clone()),
-                            });
-                        }
-                    }
-                    if !s.points.is_empty() {
-                        series.push(s);
-                    }
+        if self.poll_daemon_stream() {
+            self.live_worker = None;
+            self.live_metrics.clear();
+            self.live_history.set_window(self.range);
+            self.series = live_series(&self.config, &self.metrics, self.live_history.iter());
+            return Ok(());
+        }
+
+        if self.live_metrics != self.metrics {
+            let (builtin, custom) = live_registry(&self.config, &self.metrics);
+            self.live = builtin;
+            self.live_worker = (!custom.is_empty()).then(|| CustomWorker::spawn(custom));
+            self.live_metrics = self.metrics.clone();
+            self.live_custom.clear();
+        }
+        let mut collected = self.live.collect_due();
+        if let Some(worker) = &self.live_worker {
+            // A one-shot export has no later refresh to pick the commands up.
+            let wait = self.one_shot.then(|| {
+                let slowest = self.config.daemon.custom.iter().map(|c| c.timeout).max();
+                slowest.unwrap_or_default() + Duration::from_secs(1)
+            });
+            worker.drain(&mut collected, wait);
+        }
+        // Custom commands only produce readings when they are due; keep the
+        // last batch per metric so their series don't blink out in between.
+        let mut fresh: HashMap<(MetricKind, String), Vec<MetricReading>> = HashMap::new();
+        for r in collected.readings.iter().filter(|r| is_custom(r)) {
+            fresh
+                .entry((r.kind, r.metric.clone()))
+                .or_default()
+                .push(r.clone());
+        }
+        self.live_custom.extend(fresh);
+
+        let readings = collected
+            .readings
+            .iter()
+            .filter(|r| !is_custom(r))
+            .chain(self.live_custom.values().flatten());
+        self.series = live_series(&self.config, &self.metrics, readings);
+        if let Some((name, err)) = collected.errors.first() {
+            self.status = format!("{name}: {err:#}");
+        }
+        Ok(())
+    }
+
+    /// Drains the daemon's reading stream, subscribing first if needed.
+    /// Returns false when no daemon is publishing, so the caller reads the
+    /// kernel counters itself.
+    fn poll_daemon_stream(&mut self) -> bool {
+        if self.live_stream.is_none() {
+            let socket = self.config.daemon.stream_socket_path();
+            self.live_stream = subscribe(&socket).ok();
+            // Let the history replay arrive before the first frame (or a
+            // one-shot `--csv`): wait for it to start, then until it pauses.
+            if let Some(stream) = &self.live_stream {
+                let mut wait = Duration::from_millis(200);
+                while let Ok(reading) = stream.recv_timeout(wait) {
+                    self.live_history.push(reading);
+                    wait = Duration::from_millis(20);
                 }
-                m if m.contains("power") => {
-                    if let Ok(power) = read_powercap() {
-                        let mut s = MetricSeries::new("power", Some("mW"));
-                        for p in power {
-                            s.push(MetricPoint {
-                                timestamp: now,
-                                value: p.draw_mw,
-                                label: Some(p.domain),
-                            });
-                        }
-                        if !s.points.is_empty() {
-                            series.push(s);
-                        }
-                    }
+            }
+        }
+        let Some(stream) = &self.live_stream else {
+            return false;
+        };
+        loop {
+            match stream.try_recv() {
+                Ok(reading) => self.live_history.push(reading),
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => {
+                    self.live_stream = None;
+                    self.live_history.clear();
+                    self.status = "daemon stream closed; reading counters directly".into();
+                    return false;
                 }
-                _ => {}
             }
         }
-        self.series = series;
-        Ok(())
+    }
+
+    fn mode_label(&self) -> &'static str {
+        match self.mode {
+            Mode::Historical => "historical",
+            Mode::Live if self.live_stream.is_some() => "live (daemon)",
+            Mode::Live => "live (direct)",
+        }
     }
 
     fn export_csv<W: Write>(&self, mut writer: W) -> Result<()> {
@@ -338,55 +369,152 @@ impl App {
     }
 }
 
-fn table_for_metric(metric: &str) -> Option<&str> {
-    match metric {
-        "cpu" | "cpu_usage" => Some("cpu_samples"),
-        "ram" | "ram_usage" => Some("ram_samples"),
-        "net" | "net_bytes" => Some("net_samples"),
-        m if m.starts_with("battery") => Some("battery_samples"),
-        m if m.contains("temp") || m == "temps" => Some("temp_samples"),
-        m if m.contains("disk") => Some("disk_samples"),
-        m if m.contains("power") => Some("power_samples"),
-        _ => None,
-    }
+fn table_for_metric(metric: &str) -> Option<&'static str> {
+    resolve_metric(metric).map(|r| r.view)
 }
 
-fn live_cpu_sample(prev: &mut Option<wtui_core::metrics::CpuTimes>) -> Result<Option<MetricPoint>> {
-    let current = read_cpu_times()?;
-    let now = OffsetDateTime::now_utc();
-    if let Some(prev_times) = prev {
-        if let Some(usage) = cpu_usage_percent(prev_times, &current) {
-            *prev = Some(current);
-            return Ok(Some(MetricPoint {
-                timestamp: now,
-                value: usage,
-                label: Some("total".into()),
-            }));
+fn is_custom(reading: &MetricReading) -> bool {
+    matches!(reading.kind, MetricKind::Custom | MetricKind::Textfile)
+}
+
+/// Groups live readings into one series per requested metric, matching on
+/// the family and reading name each metric resolves to.
+fn live_series<'r>(
+    config: &Config,
+    metrics: &[String],
+    readings: impl Iterator<Item = &'r MetricReading> + Clone,
+) -> Vec<MetricSeries> {
+    let mut series = Vec::new();
+    for metric in metrics {
+        let custom = custom_metric(config, metric).map(|(kind, name, _)| (kind, name));
+        let live = resolve_metric(metric).and_then(|r| r.live);
+        let matches = |r: &MetricReading| match (custom, live) {
+            (Some((kind, name)), _) => r.kind == kind && r.metric == name,
+            (None, Some((kind, reading))) => r.kind == kind && r.metric == reading,
+            (None, None) => false,
+        };
+        let mut s = MetricSeries::new(metric, None);
+        for r in readings.clone().filter(|r| matches(r)) {
+            if s.unit.is_none() {
+                s.unit = r.unit.clone();
+            }
+            s.push(MetricPoint {
+                timestamp: r.timestamp,
+                value: r.value,
+                label: r.label.clone(),
+            });
+        }
+        if !s.points.is_empty() {
+            series.push(s);
         }
     }
-    *prev = Some(current);
-    Ok(None)
+    series
 }
 
-fn desired_interfaces(user: &[String]) -> Vec<String> {
-    if !user.is_empty() {
-        return user.to_vec();
+/// Builds the sources needed to show `metrics` live: the built-in families
+/// they resolve to, and separately any custom commands and the textfile
+/// directory, which are too slow to read on the UI thread.
+fn live_registry(config: &Config, metrics: &[String]) -> (SourceRegistry, SourceRegistry) {
+    let kinds: Vec<MetricKind> = metrics
+        .iter()
+        .filter(|m| custom_metric(config, m).is_none())
+        .filter_map(|m| resolve_metric(m)?.live.map(|(kind, _)| kind))
+        .collect();
+    let builtin = SourceRegistry::for_kinds(config, &kinds);
+    let mut registry = SourceRegistry::default();
+    let mut textfile = false;
+    for metric in metrics {
+        match custom_metric(config, metric) {
+            Some((_, _, Some(custom))) => registry.push(
+                Box::new(CustomSource::new(custom.clone())),
+                custom.interval.unwrap_or(config.daemon.interval),
+            ),
+            Some((MetricKind::Textfile, _, _)) => textfile = true,
+            _ => {}
+        }
     }
-    let base = std::path::PathBuf::from("/sys/class/net");
-    let mut found = Vec::new();
-    if let Ok(entries) = std::fs::read_dir(base) {
-        for entry in entries.flatten() {
-            let name = entry.file_name().to_string_lossy().to_string();
-            if name == "lo" {
-                continue;
+    if let (true, Some(dir)) = (textfile, &config.daemon.textfile_dir) {
+        registry.push(Box::new(TextfileSource::new(dir.clone())), Duration::ZERO);
+    }
+    (builtin, registry)
+}
+
+/// Reads custom commands and the textfile directory on a thread of their
+/// own, so a command running up to its timeout doesn't freeze the UI.
+struct CustomWorker {
+    collected: Receiver<Collected>,
+    stop: Arc<AtomicBool>,
+}
+
+impl CustomWorker {
+    fn spawn(mut registry: SourceRegistry) -> Self {
+        let (tx, rx) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        thread::spawn(move || {
+            while !stopped.load(Ordering::Relaxed) {
+                let collected = registry.collect_due();
+                if (!collected.readings.is_empty() || !collected.errors.is_empty())
+                    && tx.send(collected).is_err()
+                {
+                    return;
+                }
+                // Zero-interval sources are due on every pass; poll them at
+                // most once a second.
+                let wake = registry
+                    .next_due()
+                    .unwrap_or_else(Instant::now)
+                    .max(Instant::now() + Duration::from_secs(1));
+                while !stopped.load(Ordering::Relaxed) {
+                    let left = wake.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        break;
+                    }
+                    thread::sleep(left.min(Duration::from_millis(100)));
+                }
             }
-            found.push(name);
+        });
+        Self {
+            collected: rx,
+            stop,
+        }
+    }
+
+    /// Moves what the worker read since the last call into `collected`,
+    /// first waiting up to `wait` for something to arrive.
+    fn drain(&self, collected: &mut Collected, wait: Option<Duration>) {
+        let first = wait.and_then(|wait| self.collected.recv_timeout(wait).ok());
+        for batch in first.into_iter().chain(self.collected.try_iter()) {
+            collected.readings.extend(batch.readings);
+            collected.errors.extend(batch.errors);
         }
     }
-    if found.is_empty() {
-        vec!["eth0".into()]
+}
+
+impl Drop for CustomWorker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Resolves `custom:<name>`, or a bare `<name>` naming a `[[daemon.custom]]`
+/// entry, to a command's series in `custom_samples`, and `textfile:<name>` to
+/// a series from the textfile directory. Prefixed names need no config entry,
+/// so series recorded under another config can be charted too.
+fn custom_metric<'a, 'm>(
+    config: &'a Config,
+    metric: &'m str,
+) -> Option<(MetricKind, &'m str, Option<&'a CustomMetricConfig>)> {
+    if let Some(name) = metric.strip_prefix("textfile:") {
+        return Some((MetricKind::Textfile, name, None));
+    }
+    let prefixed = metric.strip_prefix("custom:");
+    let name = prefixed.unwrap_or(metric);
+    let custom = config.daemon.custom.iter().find(|c| c.name == name);
+    if prefixed.is_some() || custom.is_some() {
+        Some((MetricKind::Custom, name, custom))
     } else {
-        found
+        None
     }
 }
 
@@ -402,8 +530,8 @@ fn draw_ui(frame: &mut ratatui::Frame<'_>, app: &App) {
 
     // Header
     let header_text = format!(
-        "Mode: {:?} | Range: {:?} | Metrics: {}",
-        app.mode,
+        "Mode: {} | Range: {:?} | Metrics: {}",
+        app.mode_label(),
         humantime::format_duration(app.range),
         app.metrics.join(",")
     );
@@ -439,22 +567,33 @@ fn draw_ui(frame: &mut ratatui::Frame<'_>, app: &App) {
         .highlight_style(Style::default().add_modifier(Modifier::BOLD));
     frame.render_widget(presets, body[0]);
 
+    let quota_height = if app.quotas.is_empty() {
+        0
+    } else {
+        app.quotas.len() as u16 + 3
+    };
     let right_chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Min(5), Constraint::Length(3)])
+        .constraints([
+            Constraint::Min(5),
+            Constraint::Length(quota_height),
+            Constraint::Length(8),
+        ])
         .split(body[1]);
 
     let mut rows = Vec::new();
     for s in &app.series {
         if let Some(last) = s.points.last() {
-            rows.push(Row::new(vec![
+            let mut row = Row::new(vec![
                 s.name.clone(),
                 last.label.clone().unwrap_or_else(|| "-".into()),
                 format!("{:.2}", last.value),
-                last.timestamp
-                    .format(&time::macros::format_description!("%H:%M:%S"))
-                    .unwrap_or_else(|_| "".into()),
-            ]));
+                format_time(last.timestamp),
+            ]);
+            if app.in_throttle_episode(last.timestamp) {
+                row = row.style(Style::default().fg(Color::Red));
+            }
+            rows.push(row);
         }
     }
     if rows.is_empty() {
@@ -482,12 +621,112 @@ fn draw_ui(frame: &mut ratatui::Frame<'_>, app: &App) {
     .block(Block::default().borders(Borders::ALL).title("Data"));
     frame.render_widget(table, right_chunks[0]);
 
+    if !app.quotas.is_empty() {
+        frame.render_widget(quota_table(&app.quotas), right_chunks[1]);
+    }
+
+    let event_items: Vec<ListItem> = app.events.iter().rev().map(event_item).collect();
+    let events =
+        List::new(event_items).block(Block::default().borders(Borders::ALL).title("Events"));
+    frame.render_widget(events, right_chunks[2]);
+
     let footer = Paragraph::new(app.status.clone())
         .block(Block::default().borders(Borders::ALL).title("Status"))
         .style(Style::default().fg(Color::White));
     frame.render_widget(footer, chunks[2]);
 }
 
+/// Used and remaining data per quota, with the end-of-cycle projection in
+/// red when it would go over the limit.
+fn quota_table(quotas: &[QuotaUsage]) -> Table<'static> {
+    let rows: Vec<Row> = quotas
+        .iter()
+        .map(|q| {
+            let row = Row::new(vec![
+                q.interface.clone(),
+                format!("{} ({:.0}%)", format_bytes(q.used), q.percent()),
+                format_bytes(q.remaining()),
+                format_bytes(q.limit),
+                format_bytes(q.projected()),
+                q.period_end.date().to_string(),
+            ]);
+            if q.projected() > q.limit {
+                row.style(Style::default().fg(Color::Red))
+            } else {
+                row
+            }
+        })
+        .collect();
+    Table::new(
+        rows,
+        [
+            Constraint::Length(10),
+            Constraint::Length(18),
+            Constraint::Length(11),
+            Constraint::Length(11),
+            Constraint::Length(11),
+            Constraint::Length(11),
+        ],
+    )
+    .header(
+        Row::new(vec![
+            "Interface",
+            "Used",
+            "Remaining",
+            "Limit",
+            "Projected",
+            "Resets",
+        ])
+        .style(Style::default().fg(Color::Yellow)),
+    )
+    .block(Block::default().borders(Borders::ALL).title("Quota"))
+}
+
+fn format_time(timestamp: OffsetDateTime) -> String {
+    timestamp
+        .format(&time::macros::format_description!(
+            "[hour]:[minute]:[second]"
+        ))
+        .unwrap_or_default()
+}
+
+fn event_item(event: &EventRow) -> ListItem<'static> {
+    let duration = event
+        .duration_secs
+        .map(|d| {
+            format!(
+                " ({})",
+                humantime::format_duration(Duration::from_secs(d as u64))
+            )
+        })
+        .unwrap_or_default();
+    if event.kind == "throttle" {
+        let episode: Option<ThrottleEpisode> = event
+            .detail
+            .as_deref()
+            .and_then(|d| serde_json::from_str(d).ok());
+        let text = match episode {
+            Some(ep) => format!(
+                "THROTTLE {} -> {}{duration} peak {} on {}",
+                format_time(ep.start),
+                format_time(ep.end),
+                ep.peak_temp_c
+                    .map(|t| format!("{t:.1}C"))
+                    .unwrap_or_else(|| "?".into()),
+                ep.cores.iter().cloned().collect::<Vec<_>>().join(","),
+            ),
+            None => format!("THROTTLE {}{duration}", format_time(event.timestamp)),
+        };
+        ListItem::new(text).style(Style::default().fg(Color::Red))
+    } else {
+        ListItem::new(format!(
+            "{} {}{duration}",
+            event.kind.to_uppercase(),
+            format_time(event.timestamp)
+        ))
+    }
+}
+
 fn run_tui(mut app: App) -> Result<()> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -520,15 +759,11 @@ fn run_tui(mut app: App) -> Result<()> {
 
                     match key.code {
                         KeyCode::Char('q') => break,
-                        KeyCode::Down => {
-                            if app.selected_preset + 1 < app.presets.len() {
-                                app.selected_preset += 1;
-                            }
+                        KeyCode::Down if app.selected_preset + 1 < app.presets.len() => {
+                            app.selected_preset += 1;
                         }
-                        KeyCode::Up => {
-                            if app.selected_preset > 0 {
-                                app.selected_preset -= 1;
-                            }
+                        KeyCode::Up if app.selected_preset > 0 => {
+                            app.selected_preset -= 1;
                         }
                         KeyCode::Enter => {
                             if let Some((name, _)) = app.presets.get(app.selected_preset).cloned() {
@@ -609,6 +844,12 @@ mod tests {
             table_for_metric("battery_capacity"),
             Some("battery_samples")
         );
+        assert_eq!(table_for_metric("gpu"), Some("gpu_samples"));
+        assert_eq!(table_for_metric("gpu_temp"), Some("gpu_temp_samples"));
+        assert_eq!(
+            table_for_metric("power_profile"),
+            Some("power_profile_samples")
+        );
     }
 
     #[test]
@@ -631,11 +872,18 @@ mod tests {
                 });
                 s
             }],
+            events: Vec::new(),
+            quotas: Vec::new(),
             status: String::new(),
             filter: String::new(),
             filter_mode: false,
-            live_cpu_prev: None,
-            live_net_prev: HashMap::new(),
+            live: SourceRegistry::default(),
+            live_metrics: Vec::new(),
+            live_custom: HashMap::new(),
+            live_worker: None,
+            live_stream: None,
+            live_history: HistoryRing::new(Duration::from_secs(60), LIVE_HISTORY_CAPACITY),
+            one_shot: true,
         };
 
         let mut buf = Vec::new();